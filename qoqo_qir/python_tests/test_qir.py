@@ -13,7 +13,8 @@
 import pytest
 import sys
 from qoqo_qir import QirBackend
-from qoqo import Circuit
+from qoqo import Circuit, QuantumProgram
+from qoqo import measurements
 from qoqo import operations as ops # type: ignore
 
 
@@ -26,5 +27,21 @@ def test_qir() -> None:
     assert qir
 
 
+def test_quantum_program() -> None:
+    circuit = Circuit()
+    circuit += ops.DefinitionBit("ro", 1, True)
+    circuit += ops.RotateX(0, "angle")
+    circuit += ops.MeasureQubit(0, "ro", 0)
+    measurement = measurements.ClassicalRegister(constant_circuit=None, circuits=[circuit])
+    program = QuantumProgram(measurement=measurement, input_parameter_names=["angle"])
+
+    backend = QirBackend(None, "0.1")
+    entry_points = backend.quantum_program_to_qir(program, [0.5])
+    assert len(entry_points) == 1
+    assert entry_points[0]["name"] == "circuit_0"
+    assert entry_points[0]["readout_registers"] == ["ro"]
+    assert "double 0.5" in entry_points[0]["qir"]
+
+
 if __name__ == "__main__":
     pytest.main(sys.argv)
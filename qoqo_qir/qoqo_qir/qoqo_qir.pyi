@@ -10,8 +10,8 @@
 # or implied. See the License for the specific language governing permissions and limitations under
 # the License.
 
-from qoqo import Circuit
-from typing import Optional
+from qoqo import Circuit, QuantumProgram
+from typing import Any, Dict, List, Optional, Tuple
 
 class QirBackend:
     """
@@ -21,10 +21,119 @@ class QirBackend:
         """Create a new QirBackend
 
         Args:
-            QirProfile (QirProfile): Qir profile to use.
-            QirVersion (QirVersion): Qir version to use.
+            QirProfile (QirProfile): Qir profile to use, "base_profile" (default) or "adaptive_profile".
+            QirVersion (QirVersion): Qir version to use, "0.1" (default), "1.0" or "2.0" (opaque pointers).
         """
     
+    def opaque_pointers(self) -> bool:
+        """Returns whether the backend emits opaque `ptr` pointers.
+
+        Returns:
+            bool: True if opaque pointers are emitted, False for typed pointers.
+        """
+
+    def set_opaque_pointers(self, opaque_pointers: bool) -> None:
+        """Sets whether the backend emits opaque `ptr` pointers (LLVM 15 and newer) or typed pointers.
+
+        Args:
+            opaque_pointers (bool): Whether to emit opaque pointers.
+
+        Raises:
+            ValueError: Typed pointers were requested for QIR 2.0
+        """
+
+    def entry_point_name(self) -> str:
+        """Returns the name of the entry point function.
+
+        Returns:
+            str: The name of the entry point function.
+        """
+
+    def set_entry_point_name(self, name: str) -> None:
+        """Sets the name of the entry point function, `main` by default.
+
+        Args:
+            name (str): The name of the entry point function.
+
+        Raises:
+            ValueError: The name is not a valid LLVM identifier
+        """
+
+    def output_labeling_schema(self) -> Optional[str]:
+        """Returns the value of the `output_labeling_schema` entry point attribute.
+
+        Returns:
+            Optional[str]: The name of the schema, None if the attribute has no value.
+        """
+
+    def set_output_labeling_schema(self, schema: Optional[str]) -> None:
+        """Sets the value of the `output_labeling_schema` entry point attribute.
+
+        Args:
+            schema (Optional[str]): The name of the schema, None to emit the attribute without value.
+
+        Raises:
+            ValueError: The schema contains quotes or backslashes
+        """
+
+    def add_entry_point_attribute(self, key: str, value: Optional[str] = None) -> None:
+        """Adds an attribute to the entry point functions.
+
+        An attribute with the key of an attribute emitted by the backend replaces that attribute.
+
+        Args:
+            key (str): The key of the attribute.
+            value (Optional[str]): The value of the attribute, None for attributes without value.
+
+        Raises:
+            ValueError: The key or value contains quotes or backslashes
+        """
+
+    def add_module_flag(self, behavior: int, name: str, value: int, bits: int = 32) -> None:
+        """Adds an integer module flag.
+
+        A flag with the name of a flag emitted by the backend replaces that flag.
+
+        Args:
+            behavior (int): The LLVM merge behavior of the flag, for example 1 (Error) or 7 (Max).
+            name (str): The name of the flag.
+            value (int): The value of the flag.
+            bits (int): The bit width of the integer value, 1 for booleans.
+
+        Raises:
+            ValueError: The name contains quotes or backslashes or the bit width is 0
+        """
+
+    def atomic_writes(self) -> bool:
+        """Returns whether QIR files are written atomically.
+
+        Returns:
+            bool: True if files are written to a temporary file that is renamed once complete.
+        """
+
+    def set_atomic_writes(self, atomic_writes: bool) -> None:
+        """Sets whether QIR files are written atomically, False by default.
+
+        Atomic writes never leave a partially written file behind.
+
+        Args:
+            atomic_writes (bool): Whether to write files atomically.
+        """
+
+    def create_directories(self) -> bool:
+        """Returns whether missing parent directories of QIR files are created.
+
+        Returns:
+            bool: True if missing parent directories are created.
+        """
+
+    def set_create_directories(self, create_directories: bool) -> None:
+        """Sets whether missing parent directories of QIR files are created, False by default.
+
+        Args:
+            create_directories (bool): Whether to create missing parent directories.
+        """
+
     def circuit_to_qir_str(self, circuit: Circuit, measure_all: bool = False) -> str:
         """Translates a Circuit to a valid QIR string.
 
@@ -36,6 +145,38 @@ class QirBackend:
             str: The QIR string
         """
 
+    def circuit_to_qir_str_with_parameters(
+        self, circuit: Circuit, substitutions: Dict[str, float], measure_all: bool = False
+    ) -> str:
+        """Translates a Circuit to a valid QIR string after binding its symbolic parameters.
+
+        Args:
+            circuit (Circuit): The Circuit items that is translated
+            substitutions (Dict[str, float]): The values of the symbolic parameters of the circuit
+            measure_all (bool): Wether or not to measure all qubits at the end.
+
+        Returns:
+            str: The QIR string
+
+        Raises:
+            ValueError: A symbolic parameter has no value or an operation is not in the QIR backend
+        """
+
+    def circuits_to_qir_str(
+        self, circuits: List[Tuple[str, Circuit]], measure_all: bool = False
+    ) -> str:
+        """Translates named Circuits to a QIR string with one entry point function per Circuit.
+
+        Gate definitions and declarations used by several Circuits are only emitted once.
+
+        Args:
+            circuits (List[Tuple[str, Circuit]]): The entry point names and the Circuits that are translated
+            measure_all (bool): Wether or not to measure all qubits at the end of each circuit.
+
+        Returns:
+            str: The QIR string
+        """
+
     def circuit_to_qir_file(
         self,
         circuit: Circuit,
@@ -53,3 +194,51 @@ class QirBackend:
             overwrite (bool): Wether or not to overwrite an existing file.
             measure_all (bool): Wether or not to measure all qubits at the end.
         """
+
+    def quantum_program_to_qir(
+        self,
+        program: QuantumProgram,
+        parameters: List[float] = [],
+        measure_all: bool = False,
+    ) -> List[Dict[str, Any]]:
+        """Translates all circuits of a QuantumProgram to QIR, one module per measured circuit.
+
+        The constant circuit of the measurement is prepended to every measured circuit.
+
+        Args:
+            program (QuantumProgram): The QuantumProgram that is translated
+            parameters (List[float]): The values of the input parameters of the program
+            measure_all (bool): Wether or not to measure all qubits at the end of each circuit.
+
+        Returns:
+            List[Dict[str, Any]]: The `name`, `qir` string and `readout_registers` of each circuit
+        """
+
+    def circuit_to_qir_bitcode(self, circuit: Circuit, measure_all: bool = False) -> bytes:
+        """Translates a Circuit to QIR in LLVM bitcode.
+
+        Args:
+            circuit (Circuit): The Circuit items that is translated
+            measure_all (bool): Wether or not to measure all qubits at the end.
+
+        Returns:
+            bytes: The QIR bitcode
+        """
+
+    def circuit_to_qir_bitcode_file(
+        self,
+        circuit: Circuit,
+        folder_name: str = ".",
+        filename: str = "qir_output.bc",
+        overwrite: bool = True,
+        measure_all: bool = False,
+    ):
+        """Translates a Circuit to a QIR bitcode file with the `.bc` extension.
+
+        Args:
+            circuit (Circuit): The Circuit items that is translated
+            folder_name (str): The folder to save the QIR file in.
+            filename (str): The name of the QIR file.
+            overwrite (bool): Wether or not to overwrite an existing file.
+            measure_all (bool): Wether or not to measure all qubits at the end.
+        """
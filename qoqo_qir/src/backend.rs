@@ -10,14 +10,15 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 use pyo3::{
     exceptions::{PyTypeError, PyValueError},
     prelude::*,
+    types::{PyBytes, PyDict},
 };
-use qoqo::convert_into_circuit;
-use roqoqo_qir::Backend;
+use qoqo::{convert_into_circuit, convert_into_quantum_program};
+use roqoqo_qir::{Backend, PointerStyle};
 
 /// Backend to qoqo that produces QIR output which can be imported.
 ///
@@ -39,7 +40,8 @@ impl QirBackendWrapper {
     /// Creates new QIR backend.
     ///
     /// Args:
-    ///     qubit_register_name (Optional[str]): The name of the qubit register.
+    ///     qir_profile (Optional[str]): The QIR profile to use, `base_profile` (default) or `adaptive_profile`.
+    ///     qir_version (Optional[str]): The QIR version to use, `0.1` (default), `1.0` or `2.0` (opaque pointers).
     ///
     /// Returns:
     ///     Self: The new QirBackend intance.
@@ -52,6 +54,152 @@ impl QirBackendWrapper {
         })
     }
 
+    /// Returns whether the backend emits opaque `ptr` pointers.
+    ///
+    /// Returns:
+    ///     bool: True if opaque pointers are emitted, False for typed pointers.
+    pub fn opaque_pointers(&self) -> bool {
+        self.internal.pointer_style() == PointerStyle::Opaque
+    }
+
+    /// Sets whether the backend emits opaque `ptr` pointers (LLVM 15 and newer) or typed pointers.
+    ///
+    /// Args:
+    ///     opaque_pointers (bool): Whether to emit opaque pointers.
+    ///
+    /// Raises:
+    ///     ValueError: Typed pointers were requested for QIR 2.0
+    pub fn set_opaque_pointers(&mut self, opaque_pointers: bool) -> PyResult<()> {
+        let pointer_style = if opaque_pointers {
+            PointerStyle::Opaque
+        } else {
+            PointerStyle::Typed
+        };
+        self.internal
+            .set_pointer_style(pointer_style)
+            .map_err(|x| PyValueError::new_err(format!("{x}")))
+    }
+
+    /// Returns the name of the entry point function.
+    ///
+    /// Returns:
+    ///     str: The name of the entry point function.
+    pub fn entry_point_name(&self) -> String {
+        self.internal.entry_point_name().to_owned()
+    }
+
+    /// Sets the name of the entry point function, `main` by default.
+    ///
+    /// Args:
+    ///     name (str): The name of the entry point function.
+    ///
+    /// Raises:
+    ///     ValueError: The name is not a valid LLVM identifier
+    pub fn set_entry_point_name(&mut self, name: &str) -> PyResult<()> {
+        self.internal
+            .set_entry_point_name(name)
+            .map_err(|x| PyValueError::new_err(format!("{x}")))
+    }
+
+    /// Returns the value of the `output_labeling_schema` entry point attribute.
+    ///
+    /// Returns:
+    ///     Optional[str]: The name of the schema, None if the attribute has no value.
+    pub fn output_labeling_schema(&self) -> Option<String> {
+        self.internal
+            .output_labeling_schema()
+            .map(|schema| schema.to_owned())
+    }
+
+    /// Sets the value of the `output_labeling_schema` entry point attribute.
+    ///
+    /// Args:
+    ///     schema (Optional[str]): The name of the schema, None to emit the attribute without value.
+    ///
+    /// Raises:
+    ///     ValueError: The schema contains quotes or backslashes
+    pub fn set_output_labeling_schema(&mut self, schema: Option<String>) -> PyResult<()> {
+        self.internal
+            .set_output_labeling_schema(schema)
+            .map_err(|x| PyValueError::new_err(format!("{x}")))
+    }
+
+    /// Adds an attribute to the entry point functions.
+    ///
+    /// An attribute with the key of an attribute emitted by the backend replaces that attribute.
+    ///
+    /// Args:
+    ///     key (str): The key of the attribute.
+    ///     value (Optional[str]): The value of the attribute, None for attributes without value.
+    ///
+    /// Raises:
+    ///     ValueError: The key or value contains quotes or backslashes
+    #[pyo3(signature = (key, value=None))]
+    pub fn add_entry_point_attribute(&mut self, key: &str, value: Option<String>) -> PyResult<()> {
+        self.internal
+            .add_entry_point_attribute(key, value)
+            .map_err(|x| PyValueError::new_err(format!("{x}")))
+    }
+
+    /// Adds an integer module flag.
+    ///
+    /// A flag with the name of a flag emitted by the backend replaces that flag.
+    ///
+    /// Args:
+    ///     behavior (int): The LLVM merge behavior of the flag, for example 1 (Error) or 7 (Max).
+    ///     name (str): The name of the flag.
+    ///     value (int): The value of the flag.
+    ///     bits (int): The bit width of the integer value, 1 for booleans.
+    ///
+    /// Raises:
+    ///     ValueError: The name contains quotes or backslashes or the bit width is 0
+    #[pyo3(signature = (behavior, name, value, bits=32))]
+    pub fn add_module_flag(
+        &mut self,
+        behavior: u32,
+        name: &str,
+        value: i64,
+        bits: u32,
+    ) -> PyResult<()> {
+        self.internal
+            .add_module_flag(behavior, name, bits, value)
+            .map_err(|x| PyValueError::new_err(format!("{x}")))
+    }
+
+    /// Returns whether QIR files are written atomically.
+    ///
+    /// Returns:
+    ///     bool: True if files are written to a temporary file that is renamed once complete.
+    pub fn atomic_writes(&self) -> bool {
+        self.internal.atomic_writes()
+    }
+
+    /// Sets whether QIR files are written atomically, False by default.
+    ///
+    /// Atomic writes never leave a partially written file behind.
+    ///
+    /// Args:
+    ///     atomic_writes (bool): Whether to write files atomically.
+    pub fn set_atomic_writes(&mut self, atomic_writes: bool) {
+        self.internal.set_atomic_writes(atomic_writes)
+    }
+
+    /// Returns whether missing parent directories of QIR files are created.
+    ///
+    /// Returns:
+    ///     bool: True if missing parent directories are created.
+    pub fn create_directories(&self) -> bool {
+        self.internal.create_directories()
+    }
+
+    /// Sets whether missing parent directories of QIR files are created, False by default.
+    ///
+    /// Args:
+    ///     create_directories (bool): Whether to create missing parent directories.
+    pub fn set_create_directories(&mut self, create_directories: bool) {
+        self.internal.set_create_directories(create_directories)
+    }
+
     /// Translates a Circuit to a QIR string.
     ///
     /// Args:
@@ -76,6 +224,70 @@ impl QirBackendWrapper {
             .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))
     }
 
+    /// Translates a Circuit to a QIR string after binding its symbolic parameters.
+    ///
+    /// Args:
+    ///     circuit: The Circuit items that is translated
+    ///     substitutions: The values of the symbolic parameters of the circuit
+    ///     measure_all: Whether to measure and record all qubits at the end of the circuit.
+    ///
+    /// Returns:
+    ///     str: The QIR string
+    ///
+    /// Raises:
+    ///     TypeError: Circuit conversion error
+    ///     ValueError: Symbolic parameter without value or operation not in QIR backend
+    #[pyo3(signature = (circuit, substitutions, measure_all=false))]
+    pub fn circuit_to_qir_str_with_parameters(
+        &self,
+        circuit: &Bound<PyAny>,
+        substitutions: HashMap<String, f64>,
+        measure_all: bool,
+    ) -> PyResult<String> {
+        let circuit = convert_into_circuit(circuit).map_err(|x| {
+            PyTypeError::new_err(format!("Cannot convert python object to Circuit: {x:?}"))
+        })?;
+        self.internal
+            .circuit_to_qir_str_with_parameters(&circuit, &substitutions, measure_all)
+            .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))
+    }
+
+    /// Translates named Circuits to a QIR string with one entry point function per Circuit.
+    ///
+    /// Gate definitions and declarations used by several Circuits are only emitted once.
+    ///
+    /// Args:
+    ///     circuits: The names of the entry point functions and the Circuits that are translated
+    ///     measure_all: Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// Returns:
+    ///     str: The QIR string
+    ///
+    /// Raises:
+    ///     TypeError: Circuit conversion error
+    ///     ValueError: Entry point names not unique or operation not in QIR backend
+    #[pyo3(signature = (circuits, measure_all=false))]
+    pub fn circuits_to_qir_str(
+        &self,
+        circuits: Vec<(String, Bound<PyAny>)>,
+        measure_all: bool,
+    ) -> PyResult<String> {
+        let circuits = circuits
+            .iter()
+            .map(|(name, circuit)| {
+                convert_into_circuit(circuit)
+                    .map(|circuit| (name.clone(), circuit))
+                    .map_err(|x| {
+                        PyTypeError::new_err(format!(
+                            "Cannot convert python object to Circuit: {x:?}"
+                        ))
+                    })
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        Backend::circuits_to_qir_str(&self.internal, &circuits, measure_all)
+            .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))
+    }
+
     /// Translates a Circuit to a QIR file.
     ///
     /// Args:
@@ -89,7 +301,7 @@ impl QirBackendWrapper {
     ///
     /// Raises:
     ///     TypeError: Circuit conversion error
-    ///     ValueError: Operation not in QIR backend
+    ///     ValueError: Operation not in QIR backend or the file could not be written
     #[pyo3(
         signature = (circuit, folder_name=".".to_owned(), filename="qir_output.ll".to_owned(), overwrite=true, measure_all=false)
     )]
@@ -116,4 +328,115 @@ impl QirBackendWrapper {
         )
         .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))
     }
+
+    /// Translates all circuits of a QuantumProgram to QIR, one module per measured circuit.
+    ///
+    /// The constant circuit of the measurement is prepended to every measured circuit.
+    ///
+    /// Args:
+    ///     program: The QuantumProgram that is translated
+    ///     parameters: The values of the input parameters of the program
+    ///     measure_all: Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// Returns:
+    ///     List[Dict[str, Any]]: The `name`, `qir` string and `readout_registers` of each circuit
+    ///
+    /// Raises:
+    ///     TypeError: QuantumProgram conversion error
+    ///     ValueError: Wrong number of parameters or operation not in QIR backend
+    #[pyo3(signature = (program, parameters=vec![], measure_all=false))]
+    pub fn quantum_program_to_qir<'py>(
+        &self,
+        py: Python<'py>,
+        program: &Bound<PyAny>,
+        parameters: Vec<f64>,
+        measure_all: bool,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let program = convert_into_quantum_program(program).map_err(|x| {
+            PyTypeError::new_err(format!(
+                "Cannot convert python object to QuantumProgram: {x:?}"
+            ))
+        })?;
+        let entry_points = self
+            .internal
+            .quantum_program_to_qir(&program, &parameters, measure_all)
+            .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))?;
+        entry_points
+            .into_iter()
+            .map(|entry_point| {
+                let dict = PyDict::new(py);
+                dict.set_item("name", entry_point.name)?;
+                dict.set_item("qir", entry_point.qir)?;
+                dict.set_item("readout_registers", entry_point.readout_registers)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// Translates a Circuit to QIR in LLVM bitcode.
+    ///
+    /// Args:
+    ///     circuit: The Circuit items that is translated
+    ///
+    /// Returns:
+    ///     bytes: The QIR bitcode
+    ///
+    /// Raises:
+    ///     TypeError: Circuit conversion error
+    ///     ValueError: Operation not in QIR backend
+    #[pyo3(signature = (circuit, measure_all=false))]
+    pub fn circuit_to_qir_bitcode<'py>(
+        &self,
+        py: Python<'py>,
+        circuit: &Bound<PyAny>,
+        measure_all: bool,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let circuit = convert_into_circuit(circuit).map_err(|x| {
+            PyTypeError::new_err(format!("Cannot convert python object to Circuit: {x:?}"))
+        })?;
+        let bitcode = Backend::circuit_to_qir_bitcode(&self.internal, &circuit, measure_all)
+            .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))?;
+        Ok(PyBytes::new(py, &bitcode))
+    }
+
+    /// Translates a Circuit to a QIR bitcode file with the `.bc` extension.
+    ///
+    /// Args:
+    ///     circuit: The Circuit that is translated
+    ///     folder_name: The name of the folder that is prepended to all filenames.
+    ///     filename: The name of the file the QIR bitcode is saved to.
+    ///     overwrite: Whether to overwrite file if it already exists.
+    ///
+    /// Returns:
+    ///     Ok(()): The QIR file was correctly written
+    ///
+    /// Raises:
+    ///     TypeError: Circuit conversion error
+    ///     ValueError: Operation not in QIR backend
+    #[pyo3(
+        signature = (circuit, folder_name=".".to_owned(), filename="qir_output.bc".to_owned(), overwrite=true, measure_all=false)
+    )]
+    pub fn circuit_to_qir_bitcode_file(
+        &self,
+        circuit: &Bound<PyAny>,
+        folder_name: String,
+        filename: String,
+        overwrite: bool,
+        measure_all: bool,
+    ) -> PyResult<()> {
+        let circuit = convert_into_circuit(circuit).map_err(|x| {
+            PyTypeError::new_err(format!("Cannot convert python object to Circuit: {x:?}"))
+        })?;
+        let folder_name = Path::new(&folder_name);
+        let filename = Path::new(&filename);
+        Backend::circuit_to_qir_bitcode_file(
+            &self.internal,
+            &circuit,
+            folder_name,
+            filename,
+            overwrite,
+            measure_all,
+        )
+        .map_err(|x| PyValueError::new_err(format!("Error during QIR translation: {x:?}")))
+    }
 }
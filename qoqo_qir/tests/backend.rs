@@ -12,14 +12,17 @@
 //
 //! Testing the qoqo-qir Backend
 
-use std::{fs, path::Path};
+use std::{collections::HashMap, fs, path::Path};
 
-use pyo3::{types::PyAnyMethods, Bound, Py, Python};
-use qoqo::{operations::convert_operation_to_pyobject, CircuitWrapper};
+use pyo3::{
+    types::{PyAnyMethods, PyDict},
+    Bound, Py, Python,
+};
+use qoqo::{operations::convert_operation_to_pyobject, CircuitWrapper, QuantumProgramWrapper};
 use qoqo_calculator::CalculatorFloat;
 use qoqo_calculator_pyo3::CalculatorFloatWrapper;
 use qoqo_qir::QirBackendWrapper;
-use roqoqo::{operations::*, Circuit};
+use roqoqo::{measurements::ClassicalRegister, operations::*, Circuit, QuantumProgram};
 
 // helper functions
 fn circuitpy_from_circuitru(py: Python, circuit: Circuit) -> Bound<CircuitWrapper> {
@@ -54,9 +57,95 @@ fn test_backend_error() {
         let backend_type = py.get_type::<QirBackendWrapper>();
         assert!(backend_type.call1(("error", "0.1")).is_err());
         assert!(backend_type.call1(("base_profile", "error")).is_err());
+        assert!(backend_type.call1(("adaptive_profile", "0.1")).is_ok());
     })
 }
 
+#[test]
+fn test_opaque_pointers() {
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(1));
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, Some("1.0".to_string()));
+        let circuitpy = circuitpy_from_circuitru(py, circuit);
+
+        let opaque: bool = backendpy
+            .call_method0("opaque_pointers")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(!opaque);
+        backendpy
+            .call_method1("set_opaque_pointers", (true,))
+            .unwrap();
+        let result: String = backendpy
+            .call_method1("circuit_to_qir_str", (circuitpy,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(result, "define void @main() #0 {\nentry:\n  call void @__quantum__qis__x__body(ptr inttoptr (i64 1 to ptr))\n  ret void\n}\n\ndeclare void @__quantum__qis__x__body(ptr)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+
+        let backendpy = new_qirbackend(py, None, Some("2.0".to_string()));
+        assert!(backendpy
+            .call_method1("set_opaque_pointers", (false,))
+            .is_err());
+    });
+}
+
+#[test]
+fn test_entry_point_configuration() {
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, None);
+        let name: String = backendpy
+            .call_method0("entry_point_name")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(name, "main");
+        backendpy
+            .call_method1("set_entry_point_name", ("entry",))
+            .unwrap();
+        assert!(backendpy
+            .call_method1("set_entry_point_name", ("not valid",))
+            .is_err());
+        backendpy
+            .call_method1("set_output_labeling_schema", ("schema",))
+            .unwrap();
+        let schema: Option<String> = backendpy
+            .call_method0("output_labeling_schema")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(schema, Some("schema".to_owned()));
+        backendpy
+            .call_method1("add_entry_point_attribute", ("key", "value"))
+            .unwrap();
+        backendpy
+            .call_method1("add_module_flag", (1, "flag", 2))
+            .unwrap();
+        assert!(backendpy
+            .call_method1("add_module_flag", (1, "flag", 2, 0))
+            .is_err());
+
+        let circuitpy = circuitpy_from_circuitru(py, circuit);
+        let result: String = backendpy
+            .call_method1("circuit_to_qir_str", (circuitpy,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(result.contains("define void @entry() #0"));
+        assert!(result.contains("\"output_labeling_schema\"=\"schema\""));
+        assert!(result.contains("\"key\"=\"value\""));
+        assert!(result.contains("!{i32 1, !\"flag\", i32 2}"));
+    });
+}
+
 #[test]
 fn test_simple_circuit() {
     let mut circuit = Circuit::new();
@@ -76,6 +165,161 @@ fn test_simple_circuit() {
     });
 }
 
+#[test]
+fn test_bitcode() {
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, None);
+        let circuitpy = circuitpy_from_circuitru(py, circuit.clone());
+
+        let result: Vec<u8> = backendpy
+            .call_method1("circuit_to_qir_bitcode", (circuitpy.clone(),))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected = backendpy
+            .borrow()
+            .internal
+            .circuit_to_qir_bitcode(&circuit, false)
+            .unwrap();
+        assert_eq!(result, expected);
+
+        backendpy
+            .call_method1(
+                "circuit_to_qir_bitcode_file",
+                (circuitpy, ".", "bitcode_test", true),
+            )
+            .unwrap();
+        assert_eq!(fs::read(Path::new("bitcode_test.bc")).unwrap(), expected);
+        fs::remove_file(Path::new("bitcode_test.bc")).unwrap();
+    });
+}
+
+#[test]
+fn test_quantum_program() {
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += RotateX::new(0, CalculatorFloat::from("angle"));
+    circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let program = QuantumProgram::ClassicalRegister {
+        measurement: ClassicalRegister {
+            constant_circuit: None,
+            circuits: vec![circuit],
+        },
+        input_parameter_names: vec!["angle".to_owned()],
+    };
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, None);
+        let programpy = Py::new(
+            py,
+            QuantumProgramWrapper {
+                internal: program.clone(),
+            },
+        )
+        .unwrap();
+        let entry_points = backendpy
+            .call_method1(
+                "quantum_program_to_qir",
+                (programpy.clone_ref(py), vec![0.5]),
+            )
+            .unwrap();
+        let expected = backendpy
+            .borrow()
+            .internal
+            .quantum_program_to_qir(&program, &[0.5], false)
+            .unwrap();
+        let entry_point = entry_points.get_item(0).unwrap();
+        let entry_point = entry_point.downcast::<PyDict>().unwrap();
+        let name: String = entry_point.get_item("name").unwrap().extract().unwrap();
+        let qir: String = entry_point.get_item("qir").unwrap().extract().unwrap();
+        let readout_registers: Vec<String> = entry_point
+            .get_item("readout_registers")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(name, expected[0].name);
+        assert_eq!(qir, expected[0].qir);
+        assert_eq!(readout_registers, expected[0].readout_registers);
+
+        assert!(backendpy
+            .call_method1("quantum_program_to_qir", (programpy, Vec::<f64>::new()))
+            .is_err());
+    });
+}
+
+#[test]
+fn test_multiple_entry_points() {
+    let mut circuit_0 = Circuit::new();
+    circuit_0.add_operation(PauliX::new(0));
+    let mut circuit_1 = Circuit::new();
+    circuit_1.add_operation(PauliY::new(1));
+    let circuits = vec![
+        ("entry_0".to_owned(), circuit_0.clone()),
+        ("entry_1".to_owned(), circuit_1.clone()),
+    ];
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, None);
+        let circuitspy = vec![
+            ("entry_0", circuitpy_from_circuitru(py, circuit_0)),
+            ("entry_1", circuitpy_from_circuitru(py, circuit_1)),
+        ];
+        let result: String = backendpy
+            .call_method1("circuits_to_qir_str", (circuitspy,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected = backendpy
+            .borrow()
+            .internal
+            .circuits_to_qir_str(&circuits, false)
+            .unwrap();
+        assert_eq!(result, expected);
+        assert!(backendpy
+            .call_method1("circuits_to_qir_str", (vec![("entry", 0.0)],))
+            .is_err());
+    });
+}
+
+#[test]
+fn test_circuit_with_parameters() {
+    let mut circuit = Circuit::new();
+    circuit.add_operation(RotateX::new(0, CalculatorFloat::from("theta")));
+    let substitutions: HashMap<String, f64> = [("theta".to_owned(), 0.5)].into_iter().collect();
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, None);
+        let circuitpy = circuitpy_from_circuitru(py, circuit.clone());
+        let result: String = backendpy
+            .call_method1(
+                "circuit_to_qir_str_with_parameters",
+                (circuitpy.clone(), substitutions.clone()),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected = backendpy
+            .borrow()
+            .internal
+            .circuit_to_qir_str_with_parameters(&circuit, &substitutions, false)
+            .unwrap();
+        assert_eq!(result, expected);
+        assert!(backendpy
+            .call_method1(
+                "circuit_to_qir_str_with_parameters",
+                (circuitpy, HashMap::<String, f64>::new()),
+            )
+            .is_err());
+    });
+}
+
 #[test]
 fn test_to_str_errors() {
     let mut circuit = Circuit::new();
@@ -127,6 +371,30 @@ fn test_to_file_errors() {
         assert!(backendpy
             .call_method1("circuit_to_qir_file", (circuitpy,))
             .is_err());
+
+        let mut circuit = Circuit::new();
+        circuit.add_operation(PauliX::new(0));
+        let circuitpy = circuitpy_from_circuitru(py, circuit);
+        assert!(backendpy
+            .call_method1("circuit_to_qir_file", (circuitpy, "missing_folder"))
+            .is_err());
+    });
+}
+
+#[test]
+fn test_file_options() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let backendpy = new_qirbackend(py, None, None);
+        for option in ["atomic_writes", "create_directories"] {
+            let value: bool = backendpy.call_method0(option).unwrap().extract().unwrap();
+            assert!(!value);
+            backendpy
+                .call_method1(format!("set_{option}").as_str(), (true,))
+                .unwrap();
+            let value: bool = backendpy.call_method0(option).unwrap().extract().unwrap();
+            assert!(value);
+        }
     });
 }
 
@@ -10,19 +10,33 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
-use qoqo_calculator::CalculatorFloat;
-use roqoqo::{operations::*, Circuit, RoqoqoBackendError};
+use qoqo_calculator::{Calculator, CalculatorError, CalculatorFloat};
+use roqoqo::{
+    measurements::Measure, operations::*, Circuit, QuantumProgram, RoqoqoBackendError, RoqoqoError,
+};
 use std::{
-    fs::File,
-    io::{BufWriter, Write},
+    collections::{hash_map::DefaultHasher, HashMap},
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufWriter, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Mutex,
     vec,
 };
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::ir::{
+    Attribute, AttributeGroup, BinaryOperator, Function, Instruction, Module, ModuleFlag,
+    Parameter, Pointee, Type, Value,
+};
 use crate::{
-    call_operation, format_arg, gate_declaration, pre_process_circuit, NO_CALL_OPERATIONS,
-    NO_DECLARATION_OPERATIONS, NUMBER_LABEL, NUMBER_VARS,
+    call_operation, gate_declaration, math_declarations, pre_process_circuit,
+    prepare_circuit_for_profile, substitute_operation, PointerStyle, QirError, TranslationContext,
 };
 
 /// QIR backend to qoqo
@@ -39,83 +53,424 @@ use crate::{
 fn process_operation_circuit(
     circuit: &Circuit,
     already_seen_declarations: &mut Vec<String>,
-    declarations: &mut String,
-) -> Result<(), RoqoqoBackendError> {
+    declarations: &mut Vec<Function>,
+) -> Result<(), QirError> {
     for operation in pre_process_circuit(circuit)?.iter() {
-        if !already_seen_declarations.contains(&operation.hqslang().to_string()) {
-            let mut continue_process = false;
-            if let Operation::GateDefinition(gate_definition) = operation {
-                if !already_seen_declarations.contains(gate_definition.name()) {
-                    already_seen_declarations.push(gate_definition.name().to_owned());
-                    continue_process = true;
-                }
-            } else {
-                already_seen_declarations.push(operation.hqslang().to_string());
-                continue_process = true;
+        declare_operation(operation, already_seen_declarations, declarations)?;
+    }
+    Ok(())
+}
+
+/// Adds the declarations needed by an operation, unless they have already been added.
+///
+/// The functions used inside of helper gates and conditional or looped circuits are declared
+/// before the operation itself.
+fn declare_operation(
+    operation: &Operation,
+    already_seen_declarations: &mut Vec<String>,
+    declarations: &mut Vec<Function>,
+) -> Result<(), QirError> {
+    let key = match operation {
+        Operation::GateDefinition(gate_definition) => gate_definition.name().to_owned(),
+        _ => operation.hqslang().to_owned(),
+    };
+    // The circuits of conditionals and loops differ from one another and always need to be checked
+    let is_container = matches!(
+        operation,
+        Operation::PragmaConditional(_) | Operation::PragmaLoop(_)
+    );
+    if already_seen_declarations.contains(&key) && !is_container {
+        return Ok(());
+    }
+    if !already_seen_declarations.contains(&key) {
+        already_seen_declarations.push(key);
+    }
+    match operation {
+        Operation::GateDefinition(gate_definition) => process_operation_circuit(
+            gate_definition.circuit(),
+            already_seen_declarations,
+            declarations,
+        )?,
+        Operation::PragmaConditional(pragma_conditional) => {
+            if !already_seen_declarations.contains(&"read_result".to_owned()) {
+                already_seen_declarations.push("read_result".to_owned());
+                declarations.push(Function::declaration(
+                    "__quantum__qis__read_result__body",
+                    Type::Integer(1),
+                    &[Type::RESULT],
+                ));
             }
+            process_operation_circuit(
+                pragma_conditional.circuit(),
+                already_seen_declarations,
+                declarations,
+            )?
+        }
+        Operation::PragmaLoop(pragma_loop) => process_operation_circuit(
+            pragma_loop.circuit(),
+            already_seen_declarations,
+            declarations,
+        )?,
+        Operation::SqrtPauliX(_) | Operation::InvSqrtPauliX(_) => process_operation_circuit(
+            &[Operation::from(RotateX::new(0, CalculatorFloat::ZERO))]
+                .into_iter()
+                .collect(),
+            already_seen_declarations,
+            declarations,
+        )?,
+        Operation::SqrtPauliY(_) | Operation::InvSqrtPauliY(_) => process_operation_circuit(
+            &[Operation::from(RotateY::new(0, CalculatorFloat::ZERO))]
+                .into_iter()
+                .collect(),
+            already_seen_declarations,
+            declarations,
+        )?,
+        Operation::PhaseShiftState0(_) | Operation::PhaseShiftState1(_) => {
+            process_operation_circuit(
+                &[Operation::from(RotateZ::new(0, CalculatorFloat::ZERO))]
+                    .into_iter()
+                    .collect(),
+                already_seen_declarations,
+                declarations,
+            )?
+        }
+        Operation::SingleQubitGate(_) => process_operation_circuit(
+            &[
+                Operation::from(RotateZ::new(0, CalculatorFloat::ZERO)),
+                Operation::from(RotateY::new(0, CalculatorFloat::ZERO)),
+            ]
+            .into_iter()
+            .collect(),
+            already_seen_declarations,
+            declarations,
+        )?,
+        Operation::GPi(_) => process_operation_circuit(
+            &[
+                Operation::from(PauliX::new(0)),
+                Operation::from(RotateZ::new(0, CalculatorFloat::ZERO)),
+            ]
+            .into_iter()
+            .collect(),
+            already_seen_declarations,
+            declarations,
+        )?,
+        _ => {}
+    }
+    if let Some(function) = gate_declaration(operation)? {
+        declarations.push(function);
+    }
+    Ok(())
+}
 
-            if continue_process {
-                match operation {
-                    Operation::GateDefinition(gate_definition) => process_operation_circuit(
-                        gate_definition.circuit(),
-                        already_seen_declarations,
-                        declarations,
-                    )?,
-                    Operation::PragmaConditional(pragma_conditional) => process_operation_circuit(
-                        pragma_conditional.circuit(),
-                        already_seen_declarations,
-                        declarations,
-                    )?,
-                    Operation::PragmaLoop(pragma_loop) => process_operation_circuit(
-                        pragma_loop.circuit(),
-                        already_seen_declarations,
-                        declarations,
-                    )?,
-                    Operation::SqrtPauliX(_) | Operation::InvSqrtPauliX(_) => {
-                        process_operation_circuit(
-                            &[Operation::from(RotateX::new(0, CalculatorFloat::ZERO))]
-                                .into_iter()
-                                .collect(),
-                            already_seen_declarations,
-                            declarations,
-                        )?
-                    }
-                    Operation::PhaseShiftState1(_) => process_operation_circuit(
-                        &[Operation::from(RotateZ::new(0, CalculatorFloat::ZERO))]
-                            .into_iter()
-                            .collect(),
-                        already_seen_declarations,
-                        declarations,
-                    )?,
-                    Operation::ControlledPauliY(_) => {
-                        if !already_seen_declarations.contains(&"s_adj".to_owned()) {
-                            already_seen_declarations.push("s_adj".to_owned());
-                            declarations
-                                .push_str("declare void @__quantum__qis__s__adj(%Qubit*)\n");
-                        }
-                        process_operation_circuit(
-                            &[
-                                Operation::from(CNOT::new(0, 1)),
-                                Operation::from(SGate::new(0)),
-                            ]
-                            .into_iter()
-                            .collect(),
-                            already_seen_declarations,
-                            declarations,
-                        )?
+/// An entry point function with the properties needed for its attribute group.
+struct EntryPoint {
+    function: Function,
+    number_qubits: usize,
+    number_results: usize,
+    has_measurements: bool,
+    is_irreversible: bool,
+}
+
+/// Key of an operation in a gate set: its hqslang name and a hash of its content.
+///
+/// Only GateDefinitions, PragmaConditionals and PragmaLoops are hashed, as their declarations
+/// depend on their circuits. The content hash of all other operations is 0.
+type GateKey = (&'static str, u64);
+
+/// Declarations computed for the gate sets of translated modules.
+///
+/// Modules whose entry points use the same gates in the same order need the same declarations,
+/// so the circuits of a batch only compute them once per gate set.
+#[derive(Debug, Default)]
+struct DeclarationCache(Mutex<HashMap<Vec<Vec<GateKey>>, CachedDeclarations>>);
+
+/// The already seen declaration keys and the declared functions of a gate set.
+#[derive(Debug, Clone)]
+struct CachedDeclarations {
+    already_seen_declarations: Vec<String>,
+    declarations: Vec<Function>,
+}
+
+impl DeclarationCache {
+    fn get(&self, gate_sets: &[Vec<GateKey>]) -> Option<CachedDeclarations> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(gate_sets)
+            .cloned()
+    }
+
+    fn insert(&self, gate_sets: Vec<Vec<GateKey>>, cached: CachedDeclarations) {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(gate_sets)
+            .or_insert(cached);
+    }
+}
+
+/// Feeds formatted text to a hasher without building the String.
+struct HashWriter<'a>(&'a mut DefaultHasher);
+
+impl fmt::Write for HashWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Returns the keys of the prepared operations of an entry point that determine its declarations,
+/// in order of first use.
+fn gate_set(operations: &[(usize, Operation)]) -> Vec<GateKey> {
+    let mut gate_set: Vec<GateKey> = vec![];
+    for (_, operation) in operations.iter() {
+        let content = match operation {
+            Operation::GateDefinition(_)
+            | Operation::PragmaConditional(_)
+            | Operation::PragmaLoop(_) => {
+                let mut hasher = DefaultHasher::new();
+                fmt::write(&mut HashWriter(&mut hasher), format_args!("{operation:?}"))
+                    .expect("Hashing the debug representation of an operation cannot fail");
+                hasher.finish()
+            }
+            _ => 0,
+        };
+        let key = (operation.hqslang(), content);
+        if !gate_set.contains(&key) {
+            gate_set.push(key);
+        }
+    }
+    gate_set
+}
+
+/// Applies `translate` to every circuit with its index, in parallel with the `parallel` feature.
+fn map_circuits<T, F>(circuits: &[Circuit], translate: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, &Circuit) -> T + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        circuits
+            .par_iter()
+            .enumerate()
+            .map(|(index, circuit)| translate(index, circuit))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        circuits
+            .iter()
+            .enumerate()
+            .map(|(index, circuit)| translate(index, circuit))
+            .collect()
+    }
+}
+
+/// Creates or truncates a file and writes to it through a buffer.
+fn write_data(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<File> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer)?;
+    writer.into_inner().map_err(|error| error.into_error())
+}
+
+/// Returns true if a name can be used as an unquoted LLVM identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut characters = name.chars();
+    let valid_start = characters
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || "_.$-".contains(c));
+    valid_start && characters.all(|c| c.is_ascii_alphanumeric() || "_.$-".contains(c))
+}
+
+/// Checks that a name can be used as an unquoted LLVM identifier.
+fn check_identifier(name: &str) -> Result<(), QirError> {
+    if is_identifier(name) {
+        Ok(())
+    } else {
+        Err(QirError::InvalidConfiguration {
+            msg: format!("'{name}' is not a valid QIR function name"),
+        })
+    }
+}
+
+/// Checks that a string can be emitted in an LLVM string without escaping.
+fn check_string(string: &str) -> Result<(), QirError> {
+    if string.contains(['"', '\\']) || string.chars().any(|c| c.is_control()) {
+        Err(QirError::InvalidConfiguration {
+            msg: format!("'{string}' cannot contain quotes, backslashes or control characters"),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the free symbolic parameters of an entry point function as `double` parameters.
+///
+/// Parameters defined by a DefinitionFloat come first in order of definition,
+/// followed by the other symbols in order of first use.
+fn symbolic_parameters(circuit: &Circuit, function: &Function) -> Result<Vec<Parameter>, QirError> {
+    let definitions: Vec<&String> = circuit
+        .iter()
+        .filter_map(|op| match op {
+            Operation::DefinitionFloat(definition) => Some(definition.name()),
+            _ => None,
+        })
+        .collect();
+    let inputs: Vec<&String> = circuit
+        .iter()
+        .filter_map(|op| match op {
+            Operation::InputSymbolic(input) => Some(input.name()),
+            _ => None,
+        })
+        .collect();
+    let mut free_values = function.free_values();
+    for (name, ty) in free_values.iter() {
+        if inputs.contains(&name) {
+            return Err(QirError::InvalidParameter {
+                parameter: name.clone(),
+                msg:
+                    "it is set by an InputSymbolic operation but used together with unbound symbols"
+                        .to_owned(),
+            });
+        }
+        if *ty != Type::Double || !is_identifier(name) {
+            return Err(QirError::InvalidParameter {
+                parameter: name.clone(),
+                msg: "it is not a valid QIR identifier".to_owned(),
+            });
+        }
+        // Arguments and block labels share the local names of a function
+        if function.blocks.iter().any(|block| block.label == *name) {
+            return Err(QirError::InvalidParameter {
+                parameter: name.clone(),
+                msg: "it is also the label of a basic block".to_owned(),
+            });
+        }
+    }
+    free_values.sort_by_key(|(name, _)| {
+        definitions
+            .iter()
+            .position(|definition| *definition == name)
+            .unwrap_or(definitions.len())
+    });
+    Ok(free_values
+        .into_iter()
+        .map(|(name, _)| Parameter::named(Type::Double, name))
+        .collect())
+}
+
+/// Returns a circuit with the symbolic parameters replaced by their values.
+///
+/// Symbols that are neither given a value nor set by an InputSymbolic operation are reported
+/// with the first operation using them.
+fn substitute_parameters(
+    circuit: &Circuit,
+    substitutions: &HashMap<String, f64>,
+) -> Result<Circuit, QirError> {
+    let mut calculator = Calculator::new();
+    for (name, value) in substitutions.iter() {
+        calculator.set_variable(name, *value);
+    }
+    let mut new_circuit = Circuit::new();
+    for (index, operation) in circuit.iter().enumerate() {
+        let substituted =
+            substitute_operation(operation, &calculator).map_err(|error| match error {
+                RoqoqoError::CalculatorError(CalculatorError::VariableNotSet { name }) => {
+                    QirError::UnboundParameter {
+                        index,
+                        hqslang: operation.hqslang(),
+                        parameter: name,
                     }
-                    _ => {}
-                }
-                declarations.push_str(&gate_declaration(operation)?);
-                if !declarations.is_empty()
-                    && !NO_DECLARATION_OPERATIONS.contains(&operation.hqslang())
-                {
-                    declarations.push('\n');
                 }
+                _ => QirError::from(RoqoqoBackendError::from(error)).locate(index, operation),
+            })?;
+        // InputSymbolic operations only bind their symbol and are not translated
+        if let Operation::InputSymbolic(input_symbolic) = &substituted {
+            calculator.set_variable(input_symbolic.name(), *input_symbolic.input());
+        } else {
+            new_circuit.add_operation(substituted);
+        }
+    }
+    Ok(new_circuit)
+}
+
+/// Returns the circuits of a measurement with the input parameters substituted.
+///
+/// Each returned circuit is the constant circuit of the measurement followed by one measured circuit.
+fn measurement_circuits<T: Measure>(
+    measurement: &T,
+    input_parameter_names: &[String],
+    parameters: &[f64],
+) -> Result<Vec<Circuit>, QirError> {
+    if parameters.len() != input_parameter_names.len() {
+        return Err(QirError::WrongParameterCount {
+            expected: input_parameter_names.len(),
+            given: parameters.len(),
+        });
+    }
+    let substituted_parameters: HashMap<String, f64> = input_parameter_names
+        .iter()
+        .cloned()
+        .zip(parameters.iter().copied())
+        .collect();
+    let measurement = measurement
+        .substitute_parameters(substituted_parameters)
+        .map_err(RoqoqoBackendError::from)?;
+    let constant_circuit = measurement.constant_circuit().clone().unwrap_or_default();
+    Ok(measurement
+        .circuits()
+        .map(|circuit| constant_circuit.clone() + circuit.clone())
+        .collect())
+}
+
+/// Returns the names of the classical registers a circuit defines as outputs, in order of definition.
+fn readout_registers(circuit: &Circuit) -> Vec<String> {
+    let mut registers: Vec<String> = vec![];
+    for op in circuit.iter() {
+        let name = match op {
+            Operation::DefinitionBit(definition) if *definition.is_output() => definition.name(),
+            Operation::DefinitionFloat(definition) if *definition.is_output() => definition.name(),
+            Operation::DefinitionComplex(definition) if *definition.is_output() => {
+                definition.name()
             }
+            _ => continue,
+        };
+        if !registers.contains(name) {
+            registers.push(name.to_owned());
         }
     }
-    Ok(())
+    registers
+}
+
+/// Checks whether an operation matching `predicate` is used anywhere in the circuit.
+///
+/// The circuit is pre-processed first and the circuits of GateDefinitions, PragmaConditionals
+/// and PragmaLoops are searched recursively.
+fn circuit_contains(
+    circuit: &Circuit,
+    predicate: &dyn Fn(&Operation) -> bool,
+) -> Result<bool, QirError> {
+    for operation in pre_process_circuit(circuit)?.iter() {
+        if predicate(operation) {
+            return Ok(true);
+        }
+        let inner_circuit = match operation {
+            Operation::GateDefinition(gate_definition) => Some(gate_definition.circuit()),
+            Operation::PragmaConditional(pragma_conditional) => Some(pragma_conditional.circuit()),
+            Operation::PragmaLoop(pragma_loop) => Some(pragma_loop.circuit()),
+            _ => None,
+        };
+        if let Some(inner_circuit) = inner_circuit {
+            if circuit_contains(inner_circuit, predicate)? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -125,6 +480,20 @@ pub struct Backend {
     qir_profile: QirProfile,
     /// Which version of QIR to use
     qir_version: QirVersion,
+    /// Whether pointers are emitted as typed pointers or opaque `ptr`
+    pointer_style: PointerStyle,
+    /// Name of the entry point function
+    entry_point_name: String,
+    /// Value of the `output_labeling_schema` entry point attribute
+    output_labeling_schema: Option<String>,
+    /// Additional attributes of the entry point function
+    entry_point_attributes: Vec<Attribute>,
+    /// Additional module flags as behavior, name, bit width and value
+    module_flags: Vec<(u32, String, u32, i64)>,
+    /// Whether files are written to a temporary file that is renamed once complete
+    atomic_writes: bool,
+    /// Whether missing parent directories of written files are created
+    create_directories: bool,
 }
 
 impl Backend {
@@ -133,224 +502,970 @@ impl Backend {
     /// # Arguments
     ///
     /// * `qir_profile` - The name of the profile.
-    /// * `qir_version` - The version of QIR.
-    pub fn new(
-        qir_profile: Option<String>,
-        qir_version: Option<String>,
-    ) -> Result<Self, RoqoqoBackendError> {
+    /// * `qir_version` - The version of QIR: `0.1` (default), `1.0` or `2.0`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Backend)` - The backend with the given profile and version
+    /// * `QirError::InvalidConfiguration` - The profile or version is unknown
+    pub fn new(qir_profile: Option<String>, qir_version: Option<String>) -> Result<Self, QirError> {
+        let qir_version = QirVersion::from_str(&qir_version.unwrap_or("0.1".to_owned()))?;
         Ok(Self {
             qir_profile: QirProfile::from_str(&qir_profile.unwrap_or("base_profile".to_owned()))?,
-            qir_version: QirVersion::from_str(&qir_version.unwrap_or("0.1".to_owned()))?,
+            qir_version,
+            pointer_style: default_pointer_style(qir_version),
+            entry_point_name: "main".to_owned(),
+            output_labeling_schema: None,
+            entry_point_attributes: vec![],
+            module_flags: vec![],
+            atomic_writes: false,
+            create_directories: false,
         })
     }
 
+    /// Returns the pointer style used in the emitted QIR.
+    pub fn pointer_style(&self) -> PointerStyle {
+        self.pointer_style
+    }
+
+    /// Sets the pointer style used in the emitted QIR.
+    ///
+    /// Opaque pointers can be used with any QIR version, as required by LLVM 15 and newer.
+    /// QIR 2.0 only allows opaque pointers.
+    ///
+    /// # Arguments
+    ///
+    /// * `pointer_style` - The pointer style to use.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The pointer style was set.
+    /// * `QirError::InvalidConfiguration` - Typed pointers were requested for QIR 2.0.
+    pub fn set_pointer_style(&mut self, pointer_style: PointerStyle) -> Result<(), QirError> {
+        if pointer_style == PointerStyle::Typed && self.qir_version == QirVersion::V2point0 {
+            return Err(QirError::InvalidConfiguration {
+                msg: "QIR 2.0 requires opaque pointers".to_owned(),
+            });
+        }
+        self.pointer_style = pointer_style;
+        Ok(())
+    }
+
+    /// Returns the name of the entry point function.
+    pub fn entry_point_name(&self) -> &str {
+        &self.entry_point_name
+    }
+
+    /// Sets the name of the entry point function, `main` by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry point function.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The name was set.
+    /// * `QirError::InvalidConfiguration` - The name is not a valid LLVM identifier.
+    pub fn set_entry_point_name(&mut self, name: &str) -> Result<(), QirError> {
+        check_identifier(name)?;
+        self.entry_point_name = name.to_owned();
+        Ok(())
+    }
+
+    /// Returns the value of the `output_labeling_schema` entry point attribute.
+    pub fn output_labeling_schema(&self) -> Option<&str> {
+        self.output_labeling_schema.as_deref()
+    }
+
+    /// Sets the value of the `output_labeling_schema` entry point attribute.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The name of the schema, None to emit the attribute without value.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The schema was set.
+    /// * `QirError::InvalidConfiguration` - The schema contains quotes or backslashes.
+    pub fn set_output_labeling_schema(&mut self, schema: Option<String>) -> Result<(), QirError> {
+        if let Some(schema) = &schema {
+            check_string(schema)?;
+        }
+        self.output_labeling_schema = schema;
+        Ok(())
+    }
+
+    /// Adds an attribute to the entry point functions.
+    ///
+    /// An attribute with the key of an attribute emitted by the backend replaces that attribute.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the attribute.
+    /// * `value` - The value of the attribute, None for attributes without value.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The attribute was added.
+    /// * `QirError::InvalidConfiguration` - The key or value contains quotes or backslashes.
+    pub fn add_entry_point_attribute(
+        &mut self,
+        key: &str,
+        value: Option<String>,
+    ) -> Result<(), QirError> {
+        check_string(key)?;
+        let attribute = match value {
+            Some(value) => {
+                check_string(&value)?;
+                Attribute::key_value(key, value)
+            }
+            None => Attribute::key(key),
+        };
+        self.entry_point_attributes
+            .retain(|existing| existing.key != attribute.key);
+        self.entry_point_attributes.push(attribute);
+        Ok(())
+    }
+
+    /// Adds an integer module flag.
+    ///
+    /// A flag with the name of a flag emitted by the backend replaces that flag.
+    ///
+    /// # Arguments
+    ///
+    /// * `behavior` - The LLVM merge behavior of the flag, for example 1 (Error) or 7 (Max).
+    /// * `name` - The name of the flag.
+    /// * `bits` - The bit width of the integer value, 1 for booleans.
+    /// * `value` - The value of the flag.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The flag was added.
+    /// * `QirError::InvalidConfiguration` - The name contains quotes or backslashes or the bit width is 0.
+    pub fn add_module_flag(
+        &mut self,
+        behavior: u32,
+        name: &str,
+        bits: u32,
+        value: i64,
+    ) -> Result<(), QirError> {
+        check_string(name)?;
+        if bits == 0 || bits > 64 {
+            return Err(QirError::InvalidConfiguration {
+                msg: format!("Module flag '{name}' needs a bit width between 1 and 64"),
+            });
+        }
+        self.module_flags.retain(|flag| flag.1 != name);
+        self.module_flags
+            .push((behavior, name.to_owned(), bits, value));
+        Ok(())
+    }
+
+    /// Returns whether QIR files are written atomically.
+    pub fn atomic_writes(&self) -> bool {
+        self.atomic_writes
+    }
+
+    /// Sets whether QIR files are written atomically, false by default.
+    ///
+    /// Atomic writes go to a temporary file in the folder of the output file that is renamed once
+    /// it is complete, so a failed write never leaves a partially written file behind.
+    ///
+    /// # Arguments
+    ///
+    /// * `atomic_writes` - Whether to write files atomically.
+    pub fn set_atomic_writes(&mut self, atomic_writes: bool) {
+        self.atomic_writes = atomic_writes;
+    }
+
+    /// Returns whether missing parent directories of QIR files are created.
+    pub fn create_directories(&self) -> bool {
+        self.create_directories
+    }
+
+    /// Sets whether missing parent directories of QIR files are created, false by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `create_directories` - Whether to create missing parent directories.
+    pub fn set_create_directories(&mut self, create_directories: bool) {
+        self.create_directories = create_directories;
+    }
+
     /// Translates a Circuit to a valid QIR string.
     ///
     ///
     /// # Arguments
     ///
     /// * `circuit` - The Circuit items that is translated
+    /// * `measure_all` - Whether to measure and record all qubits at the end of the circuit.
     ///
     /// # Returns
     ///
     /// * `Ok(String)` - The valid QIR string
-    /// * `RoqoqoBackendError::OperationNotInBackend` - An operation is not available on the backend
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
     pub fn circuit_to_qir_str(
         &self,
         circuit: &Circuit,
         measure_all: bool,
-    ) -> Result<String, RoqoqoBackendError> {
-        *NUMBER_VARS.lock().unwrap() = 0;
-        *NUMBER_LABEL.lock().unwrap() = 0;
-        let pre_processed_circuit = pre_process_circuit(circuit)?;
-        match self.qir_profile {
-            QirProfile::BaseProfile => {
-                let mut has_measurements = false;
-                let mut number_qubits_required = 0;
-                let mut number_bits_required = 0;
-                let mut definitions = "%Qubit = type opaque\n".to_owned();
-                let mut already_seen_declarations: Vec<String> = vec![];
-                let mut declarations = "".to_owned();
-                let mut main = "define void @main() #0 {\nentry:\n".to_owned();
-
-                for op in pre_processed_circuit.iter() {
-                    // Taking note of the maximum number of qubits involved in the circuit for registers definition
-                    if let InvolvedQubits::Set(involved_qubits) = op.involved_qubits() {
-                        number_qubits_required =
-                            number_qubits_required.max(match involved_qubits.iter().max() {
-                                None => 0,
-                                Some(n) => *n + 1,
-                            })
-                    }
+    ) -> Result<String, QirError> {
+        Ok(self
+            .circuit_to_qir_module(circuit, measure_all)?
+            .to_string())
+    }
 
-                    if let Operation::MeasureQubit(measure_qubit) = op {
-                        has_measurements = true;
-                        number_bits_required =
-                            number_bits_required.max(measure_qubit.readout_index().to_owned() + 1);
-                    }
-                    if let Operation::PragmaConditional(conditional) = op {
-                        has_measurements = true;
-                        number_bits_required =
-                            number_bits_required.max(conditional.condition_index().to_owned() + 1);
-                    }
-                    // Appending gate declaration if not already seen before
-                    if !already_seen_declarations.contains(&op.hqslang().to_string()) {
-                        let mut continue_process = false;
-                        if let Operation::GateDefinition(gate_definition) = op {
-                            if !already_seen_declarations.contains(gate_definition.name()) {
-                                already_seen_declarations.push(gate_definition.name().to_owned());
-                                continue_process = true;
-                            }
-                        } else {
-                            already_seen_declarations.push(op.hqslang().to_string());
-                            continue_process = true;
-                        }
+    /// Translates a Circuit to a valid QIR string after binding its symbolic parameters.
+    ///
+    /// The values are substituted with roqoqo's `Calculator` before translation,
+    /// so the entry point has no `double` arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The Circuit items that is translated
+    /// * `substitutions` - The values of the symbolic parameters of the circuit
+    /// * `measure_all` - Whether to measure and record all qubits at the end of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The valid QIR string
+    /// * `QirError::UnboundParameter` - A symbolic parameter has no value
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    pub fn circuit_to_qir_str_with_parameters(
+        &self,
+        circuit: &Circuit,
+        substitutions: &HashMap<String, f64>,
+        measure_all: bool,
+    ) -> Result<String, QirError> {
+        let circuit = substitute_parameters(circuit, substitutions)?;
+        self.circuit_to_qir_str(&circuit, measure_all)
+    }
 
-                        if continue_process {
-                            match op {
-                                Operation::GateDefinition(gate_definition) => {
-                                    process_operation_circuit(
-                                        gate_definition.circuit(),
-                                        &mut already_seen_declarations,
-                                        &mut declarations,
-                                    )?
-                                }
-                                Operation::PragmaConditional(pragma_conditional) => {
-                                    if !already_seen_declarations
-                                        .contains(&"read_result".to_owned())
-                                    {
-                                        already_seen_declarations.push("read_result".to_owned());
-                                        declarations.push_str("declare i1 @__quantum__qis__read_result__body(%Result*)\n");
-                                    }
-                                    process_operation_circuit(
-                                        pragma_conditional.circuit(),
-                                        &mut already_seen_declarations,
-                                        &mut declarations,
-                                    )?
-                                }
-                                Operation::PragmaLoop(pragma_loop) => process_operation_circuit(
-                                    pragma_loop.circuit(),
-                                    &mut already_seen_declarations,
-                                    &mut declarations,
-                                )?,
-                                Operation::SqrtPauliX(_) | Operation::InvSqrtPauliX(_) => {
-                                    process_operation_circuit(
-                                        &[Operation::from(RotateX::new(0, CalculatorFloat::ZERO))]
-                                            .into_iter()
-                                            .collect(),
-                                        &mut already_seen_declarations,
-                                        &mut declarations,
-                                    )?
-                                }
-                                Operation::PhaseShiftState1(_) => process_operation_circuit(
-                                    &[Operation::from(RotateZ::new(0, CalculatorFloat::ZERO))]
-                                        .into_iter()
-                                        .collect(),
-                                    &mut already_seen_declarations,
-                                    &mut declarations,
-                                )?,
-                                Operation::ControlledPauliY(_) => {
-                                    if !already_seen_declarations.contains(&"s_adj".to_owned()) {
-                                        already_seen_declarations.push("s_adj".to_owned());
-                                        declarations.push_str(
-                                            "declare void @__quantum__qis__s__adj(%Qubit*)\n",
-                                        );
-                                    }
-                                    process_operation_circuit(
-                                        &[
-                                            Operation::from(CNOT::new(0, 1)),
-                                            Operation::from(SGate::new(0)),
-                                        ]
-                                        .into_iter()
-                                        .collect(),
-                                        &mut already_seen_declarations,
-                                        &mut declarations,
-                                    )?
-                                }
-                                _ => {}
-                            }
-                            declarations.push_str(&gate_declaration(op)?);
-                            if !declarations.is_empty()
-                                && !NO_DECLARATION_OPERATIONS.contains(&op.hqslang())
-                            {
-                                declarations.push('\n');
+    /// Translates a Circuit to a QIR module that can be inspected or modified before printing.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The Circuit items that is translated
+    /// * `measure_all` - Whether to measure and record all qubits at the end of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Module)` - The QIR module, printed as LLVM IR text by its `Display` implementation
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    pub fn circuit_to_qir_module(
+        &self,
+        circuit: &Circuit,
+        measure_all: bool,
+    ) -> Result<Module, QirError> {
+        self.entry_points_to_qir_module(&[(&self.entry_point_name, circuit)], measure_all, None)
+    }
+
+    /// Translates named Circuits to a QIR string with one entry point function per Circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuits` - The names of the entry point functions and the Circuits that are translated
+    /// * `measure_all` - Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The valid QIR string
+    /// * `QirError::InvalidConfiguration` - The names of the entry points are empty or not unique
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    pub fn circuits_to_qir_str(
+        &self,
+        circuits: &[(String, Circuit)],
+        measure_all: bool,
+    ) -> Result<String, QirError> {
+        Ok(self
+            .circuits_to_qir_module(circuits, measure_all)?
+            .to_string())
+    }
+
+    /// Translates named Circuits to a QIR module with one entry point function per Circuit.
+    ///
+    /// Gate definitions and declarations used by several Circuits are only emitted once.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuits` - The names of the entry point functions and the Circuits that are translated
+    /// * `measure_all` - Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Module)` - The QIR module, printed as LLVM IR text by its `Display` implementation
+    /// * `QirError::InvalidConfiguration` - The names of the entry points are empty or not unique
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    pub fn circuits_to_qir_module(
+        &self,
+        circuits: &[(String, Circuit)],
+        measure_all: bool,
+    ) -> Result<Module, QirError> {
+        let entry_points: Vec<(&str, &Circuit)> = circuits
+            .iter()
+            .map(|(name, circuit)| (name.as_str(), circuit))
+            .collect();
+        self.entry_points_to_qir_module(&entry_points, measure_all, None)
+    }
+
+    /// Translates many Circuits to one QIR string each.
+    ///
+    /// Every Circuit is translated like in `circuit_to_qir_str`. Circuits using the same gates
+    /// reuse the declarations computed for the first of them. With the `parallel` feature the
+    /// Circuits are translated on the rayon thread pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuits` - The Circuits that are translated
+    /// * `measure_all` - Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Result<String, QirError>>` - The QIR string or the error of every Circuit, in the order of `circuits`
+    pub fn circuits_to_qir_batch(
+        &self,
+        circuits: &[Circuit],
+        measure_all: bool,
+    ) -> Vec<Result<String, QirError>> {
+        let cache = DeclarationCache::default();
+        map_circuits(circuits, |_, circuit| -> Result<String, QirError> {
+            Ok(self
+                .entry_points_to_qir_module(
+                    &[(&self.entry_point_name, circuit)],
+                    measure_all,
+                    Some(&cache),
+                )?
+                .to_string())
+        })
+    }
+
+    /// Translates many Circuits to QIR files named `circuit_{index}.ll` in a folder.
+    ///
+    /// Every Circuit is translated like in `circuits_to_qir_batch` and written like in
+    /// `circuit_to_qir_file`. A Circuit that cannot be translated or written does not stop the
+    /// others from being written.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuits` - The Circuits that are translated
+    /// * `folder_name` - The folder the files are written to.
+    /// * `overwrite` - Whether to overwrite files that already exist.
+    /// * `measure_all` - Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Result<PathBuf, QirError>>` - The path of the written file or the error of every Circuit, in the order of `circuits`
+    pub fn circuits_to_qir_files(
+        &self,
+        circuits: &[Circuit],
+        folder_name: &Path,
+        overwrite: bool,
+        measure_all: bool,
+    ) -> Vec<Result<PathBuf, QirError>> {
+        let cache = DeclarationCache::default();
+        map_circuits(circuits, |index, circuit| -> Result<PathBuf, QirError> {
+            let module = self.entry_points_to_qir_module(
+                &[(&self.entry_point_name, circuit)],
+                measure_all,
+                Some(&cache),
+            )?;
+            let output_path = folder_name.join(format!("circuit_{index}.ll"));
+            self.write_file(&output_path, overwrite, |writer| write!(writer, "{module}"))?;
+            Ok(output_path)
+        })
+    }
+
+    /// Writes to a file, returning an error instead of panicking if it cannot be written.
+    fn write_file(
+        &self,
+        output_path: &Path,
+        overwrite: bool,
+        write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+    ) -> Result<(), QirError> {
+        if output_path.is_file() && !overwrite {
+            return Err(QirError::Backend(RoqoqoBackendError::FileAlreadyExists {
+                path: output_path.display().to_string(),
+            }));
+        }
+        let io_error = |error: io::Error| QirError::Io {
+            path: Some(output_path.display().to_string()),
+            kind: error.kind(),
+            msg: error.to_string(),
+        };
+        if self.create_directories {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent).map_err(io_error)?;
+            }
+        }
+        if !self.atomic_writes {
+            return write_data(output_path, write).map(|_| ()).map_err(io_error);
+        }
+        // The temporary file is in the same folder so that renaming it does not copy the data
+        let mut temporary_name = OsString::from(".");
+        temporary_name.push(output_path.file_name().ok_or_else(|| QirError::Io {
+            path: Some(output_path.display().to_string()),
+            kind: io::ErrorKind::InvalidInput,
+            msg: "The path does not end in a file name".to_owned(),
+        })?);
+        temporary_name.push(format!(".{}.tmp", std::process::id()));
+        let temporary_path = output_path.with_file_name(temporary_name);
+        write_data(&temporary_path, write)
+            .and_then(|file| file.sync_all())
+            .and_then(|_| fs::rename(&temporary_path, output_path))
+            .map_err(|error| {
+                // The temporary file may not exist if it could not be created
+                let _ = fs::remove_file(&temporary_path);
+                io_error(error)
+            })
+    }
+
+    fn entry_points_to_qir_module(
+        &self,
+        entry_points: &[(&str, &Circuit)],
+        measure_all: bool,
+        cache: Option<&DeclarationCache>,
+    ) -> Result<Module, QirError> {
+        if entry_points.is_empty() {
+            return Err(QirError::InvalidConfiguration {
+                msg: "At least one circuit is needed to build a QIR module".to_owned(),
+            });
+        }
+        let mut already_seen_declarations: Vec<String> = vec![];
+        let mut declarations: Vec<Function> = vec![];
+        if let QirVersion::V2point0 = self.qir_version {
+            declarations.push(Function::declaration(
+                "__quantum__rt__initialize",
+                Type::Void,
+                &[Type::LABEL],
+            ));
+        }
+        let prepared_operations = entry_points
+            .iter()
+            .map(|(_, circuit)| self.prepare_operations(circuit))
+            .collect::<Result<Vec<Vec<(usize, Operation)>>, QirError>>()?;
+        // Entry points with the gate sets of an earlier module start from its declarations
+        let gate_sets: Option<Vec<Vec<GateKey>>> = match cache {
+            Some(cache) => {
+                let gate_sets: Vec<Vec<GateKey>> = prepared_operations
+                    .iter()
+                    .map(|operations| gate_set(operations))
+                    .collect();
+                if let Some(cached) = cache.get(&gate_sets) {
+                    already_seen_declarations = cached.already_seen_declarations;
+                    declarations = cached.declarations;
+                }
+                Some(gate_sets)
+            }
+            None => None,
+        };
+        // The irreversible attribute group is numbered after the attribute groups of the entry points
+        let irreversible_group = entry_points.len() as u32;
+        let mut has_measurements = false;
+        let mut is_irreversible = false;
+        let mut functions: Vec<Function> = vec![];
+        let mut attribute_groups: Vec<AttributeGroup> = vec![];
+        for (index, ((name, circuit), operations)) in entry_points
+            .iter()
+            .zip(prepared_operations.iter())
+            .enumerate()
+        {
+            let entry_point = self.entry_point(
+                name,
+                circuit,
+                operations,
+                measure_all,
+                &mut already_seen_declarations,
+                &mut declarations,
+            )?;
+            has_measurements |= entry_point.has_measurements;
+            is_irreversible |= entry_point.is_irreversible;
+            let mut function = entry_point.function;
+            function.attribute_group = Some(index as u32);
+            functions.push(function);
+
+            let mut entry_point_attributes = vec![
+                Attribute::key("entry_point"),
+                Attribute::key_value("required_num_qubits", entry_point.number_qubits.to_string()),
+                Attribute::key_value(
+                    "required_num_results",
+                    entry_point.number_results.to_string(),
+                ),
+                match &self.output_labeling_schema {
+                    Some(schema) => Attribute::key_value("output_labeling_schema", schema),
+                    None => Attribute::key("output_labeling_schema"),
+                },
+                Attribute::key_value("qir_profiles", profile_name(self.qir_profile)),
+            ];
+            if entry_point.is_irreversible {
+                entry_point_attributes.push(Attribute::key("irreversible"));
+            }
+            for attribute in self.entry_point_attributes.iter() {
+                match entry_point_attributes
+                    .iter_mut()
+                    .find(|existing| existing.key == attribute.key)
+                {
+                    Some(existing) => *existing = attribute.clone(),
+                    None => entry_point_attributes.push(attribute.clone()),
+                }
+            }
+            attribute_groups.push(AttributeGroup {
+                id: index as u32,
+                attributes: entry_point_attributes,
+            });
+        }
+        if let (Some(cache), Some(gate_sets)) = (cache, gate_sets) {
+            cache.insert(
+                gate_sets,
+                CachedDeclarations {
+                    already_seen_declarations,
+                    declarations: declarations.clone(),
+                },
+            );
+        }
+        functions.extend(declarations);
+        let math_declarations = math_declarations(&functions);
+        // Symbolic expressions are computed at runtime, possibly calling the C math library
+        let uses_float_computations = !math_declarations.is_empty()
+            || functions
+                .iter()
+                .flat_map(|function| function.blocks.iter())
+                .flat_map(|block| block.instructions.iter())
+                .any(|instruction| {
+                    matches!(
+                        instruction,
+                        Instruction::Binary {
+                            operator: BinaryOperator::FAdd
+                                | BinaryOperator::FSub
+                                | BinaryOperator::FMul
+                                | BinaryOperator::FDiv,
+                            ..
+                        } | Instruction::FloatNegate { .. }
+                            | Instruction::FloatCompare { .. }
+                            | Instruction::Select { .. }
+                    )
+                });
+        let uses_extern_functions = math_declarations
+            .iter()
+            .any(|declaration| !declaration.name.starts_with("llvm."));
+        functions.extend(math_declarations);
+        for (index, function) in functions.iter().enumerate() {
+            check_identifier(&function.name)?;
+            if functions[..index]
+                .iter()
+                .any(|other| other.name == function.name)
+            {
+                return Err(QirError::InvalidConfiguration {
+                    msg: format!(
+                        "Function name '{}' is used more than once in the QIR module",
+                        function.name
+                    ),
+                });
+            }
+        }
+        if is_irreversible {
+            // Translated operations refer to the irreversible attribute group as #1
+            for function in functions.iter_mut().skip(entry_points.len()) {
+                if function.attribute_group == Some(1) {
+                    function.attribute_group = Some(irreversible_group);
+                }
+            }
+            for function in functions.iter_mut() {
+                for block in function.blocks.iter_mut() {
+                    for instruction in block.instructions.iter_mut() {
+                        if let Instruction::Call {
+                            attribute_group, ..
+                        } = instruction
+                        {
+                            if *attribute_group == Some(1) {
+                                *attribute_group = Some(irreversible_group);
                             }
                         }
                     }
-                    main.push_str(&call_operation(op)?);
-                    if !main.is_empty() && !NO_CALL_OPERATIONS.contains(&op.hqslang()) {
-                        main.push('\n');
-                    }
                 }
-                if measure_all {
-                    has_measurements = true;
-                    if !already_seen_declarations.contains(&"MeasureQubit".to_owned()) {
-                        already_seen_declarations.push("MeasureQubit".to_owned());
-                        declarations.push_str(
-                            "declare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\n",
-                        );
-                    }
-                    declarations.push_str("declare void @__quantum__rt__result_record_output(%Result*, i8*)\ndeclare void @__quantum__rt__array_record_output(i64, i8*)\n");
-                    let mut record_output = "".to_owned();
-                    for qubit in 0..number_qubits_required {
-                        main.push_str(&format!(
-                            "  call void @__quantum__qis__mz__body(%Qubit* {}, %Result* {}) #1\n",
-                            format_arg(&qubit, "Qubit"),
-                            format_arg(&qubit, "Result")
-                        ));
-                        record_output.push_str(&format!(
-                            "  call void @__quantum__rt__result_record_output(%Result* {}, i8* null)\n",
-                            format_arg(&qubit, "Result")
-                        ));
-                    }
-                    main.push_str(&format!(
-                        "  call void @__quantum__rt__array_record_output(i64 {}, i8* null)\n",
-                        number_qubits_required
-                    ));
-                    main.push_str(&record_output);
-                    number_bits_required = number_qubits_required.max(number_bits_required)
+            }
+            attribute_groups.push(AttributeGroup {
+                id: irreversible_group,
+                attributes: vec![Attribute::key("irreversible")],
+            });
+        }
+
+        let mut module = Module::new(self.pointer_style);
+        module.opaque_types.push("Qubit".to_owned());
+        if has_measurements {
+            module.opaque_types.push("Result".to_owned());
+        }
+        module.functions = functions;
+        module.attribute_groups = attribute_groups;
+
+        module.module_flags = vec![
+            ModuleFlag::new(
+                1,
+                "qir_major_version",
+                Value::Integer {
+                    bits: 32,
+                    value: major_version(self.qir_version) as i64,
+                },
+            ),
+            ModuleFlag::new(
+                7,
+                "qir_minor_version",
+                Value::Integer {
+                    bits: 32,
+                    value: minor_version(self.qir_version) as i64,
+                },
+            ),
+            ModuleFlag::boolean("dynamic_qubit_management", false),
+            ModuleFlag::boolean("dynamic_result_management", false),
+        ];
+        if let (QirProfile::AdaptiveProfile, QirVersion::V2point0) =
+            (self.qir_profile, self.qir_version)
+        {
+            if uses_float_computations {
+                module
+                    .module_flags
+                    .push(ModuleFlag::strings("float_computations", ["double"]));
+            }
+            module.module_flags.extend([
+                ModuleFlag::new(
+                    7,
+                    "backwards_branching",
+                    Value::Integer { bits: 2, value: 0 },
+                ),
+                ModuleFlag::boolean("multiple_target_branching", false),
+                ModuleFlag::boolean("multiple_return_points", false),
+            ]);
+        } else if let QirProfile::AdaptiveProfile = self.qir_profile {
+            // Symbolic parameters of the entry points are passed on to gates at runtime
+            let uses_float_args = module
+                .functions
+                .iter()
+                .take(entry_points.len())
+                .any(|function| !function.parameters.is_empty());
+            let mut uses_reset = false;
+            let mut uses_user_functions = false;
+            for (_, circuit) in entry_points {
+                uses_reset |=
+                    circuit_contains(circuit, &|op| matches!(op, Operation::PragmaActiveReset(_)))?;
+                uses_user_functions |=
+                    circuit_contains(circuit, &|op| matches!(op, Operation::GateDefinition(_)))?;
+            }
+            module.module_flags.extend([
+                ModuleFlag::boolean("qubit_resetting", uses_reset),
+                ModuleFlag::boolean("classical_ints", false),
+                ModuleFlag::boolean("classical_floats", uses_float_computations),
+                ModuleFlag::boolean("classical_fixed_points", false),
+                ModuleFlag::boolean("user_functions", uses_user_functions),
+                ModuleFlag::boolean("dynamic_float_args", uses_float_args),
+                ModuleFlag::boolean("extern_functions", uses_extern_functions),
+                ModuleFlag::boolean("backwards_branching", false),
+            ]);
+        }
+        for (behavior, name, bits, value) in self.module_flags.iter() {
+            let flag = ModuleFlag::new(
+                *behavior,
+                name.clone(),
+                Value::Integer {
+                    bits: *bits,
+                    value: *value,
+                },
+            );
+            match module
+                .module_flags
+                .iter_mut()
+                .find(|existing| existing.name == flag.name)
+            {
+                Some(existing) => *existing = flag,
+                None => module.module_flags.push(flag),
+            }
+        }
+        Ok(module)
+    }
+
+    /// Prepares the operations of a Circuit for the profile of the backend one at a time.
+    ///
+    /// Every prepared operation comes with the index of the operation of `circuit` it was prepared
+    /// from, so that errors refer to the circuit of the user. Definitions come first like in a
+    /// Circuit, which keeps the order of the declarations of a circuit prepared as a whole.
+    /// The symbols set by InputSymbolic operations are substituted in the following operations.
+    fn prepare_operations(&self, circuit: &Circuit) -> Result<Vec<(usize, Operation)>, QirError> {
+        let mut definitions: Vec<(usize, Operation)> = vec![];
+        let mut operations: Vec<(usize, Operation)> = vec![];
+        let mut calculator = Calculator::new();
+        for (index, operation) in circuit.iter().enumerate() {
+            let locate = |error: QirError| error.locate(index, operation);
+            let operation = match operation {
+                Operation::InputSymbolic(input_symbolic) => {
+                    calculator.set_variable(input_symbolic.name(), *input_symbolic.input());
+                    continue;
                 }
-                if has_measurements {
-                    definitions.push_str("%Result = type opaque\n");
+                Operation::GateDefinition(_) => operation.clone(),
+                // Operations also using unbound symbols keep them, these become entry point arguments
+                _ => substitute_operation(operation, &calculator)
+                    .unwrap_or_else(|_| operation.clone()),
+            };
+            let operation_circuit: Circuit = [operation].into_iter().collect();
+            let prepared_circuit = pre_process_circuit(
+                &prepare_circuit_for_profile(&operation_circuit, self.qir_profile)
+                    .map_err(locate)?,
+            )
+            .map_err(locate)?;
+            definitions.extend(
+                prepared_circuit
+                    .definitions()
+                    .iter()
+                    .map(|op| (index, op.clone())),
+            );
+            operations.extend(
+                prepared_circuit
+                    .operations()
+                    .iter()
+                    .map(|op| (index, op.clone())),
+            );
+        }
+        definitions.extend(operations);
+        Ok(definitions)
+    }
+
+    /// Translates a Circuit to an entry point function, adding the declarations it needs.
+    ///
+    /// `operations` are the operations of the circuit returned by `prepare_operations`.
+    fn entry_point(
+        &self,
+        name: &str,
+        circuit: &Circuit,
+        operations: &[(usize, Operation)],
+        measure_all: bool,
+        already_seen_declarations: &mut Vec<String>,
+        declarations: &mut Vec<Function>,
+    ) -> Result<EntryPoint, QirError> {
+        // Numbered values and labels restart in every function
+        let mut context = TranslationContext::new();
+        let mut number_qubits_required = 0;
+        let mut number_bits_required = 0;
+        // Number of qubits of the gates defined in the circuit, by name
+        let mut defined_gates: HashMap<String, usize> = HashMap::new();
+        let mut function = match self.qir_version {
+            QirVersion::V2point0 => {
+                let mut function = Function::definition(name, Type::Integer(64), vec![]);
+                function.push(Instruction::call(
+                    "__quantum__rt__initialize",
+                    vec![Value::Null(Pointee::I8)],
+                ));
+                function
+            }
+            _ => Function::definition(name, Type::Void, vec![]),
+        };
+
+        for (index, op) in operations.iter() {
+            let locate = |error: QirError| error.locate(*index, op);
+            // Taking note of the maximum number of qubits involved in the circuit for registers definition
+            if let InvolvedQubits::Set(involved_qubits) = op.involved_qubits() {
+                number_qubits_required =
+                    number_qubits_required.max(match involved_qubits.iter().max() {
+                        None => 0,
+                        Some(n) => *n + 1,
+                    })
+            }
+
+            match op {
+                Operation::MeasureQubit(measure_qubit) => {
+                    number_bits_required =
+                        number_bits_required.max(measure_qubit.readout_index().to_owned() + 1);
                 }
-                if declarations.ends_with("\n\n") {
-                    declarations = declarations.strip_suffix('\n').map(str::to_owned).unwrap();
+                Operation::PragmaConditional(conditional) => {
+                    number_bits_required =
+                        number_bits_required.max(conditional.condition_index().to_owned() + 1);
                 }
-                declarations = declarations.replace("\n\n\n", "\n\n");
-                main.push_str("  ret void\n}\n\n");
-                main.push_str(&declarations);
-                let mut attributes = format!(
-                    "attributes #0 = {{ \"entry_point\" \"required_num_qubits\"=\"{}\" \"required_num_results\"=\"{}\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\"{} }}\n",
-                    number_qubits_required,
-                    number_bits_required,
-                    if has_measurements { " \"irreversible\"" } else { "" }
-                );
-                if has_measurements {
-                    attributes.push_str("attributes #1 = { \"irreversible\" }\n");
+                Operation::GateDefinition(gate_definition) => {
+                    defined_gates.insert(
+                        gate_definition.name().to_owned(),
+                        gate_definition.qubits().len(),
+                    );
                 }
-                let mut flags = "!llvm.module.flags = !{!0, !1, !2, !3}\n\n".to_owned();
-                flags.push_str(
-                    format!(
-                        "!0 = !{{i32 1, !\"qir_major_version\", i32 {}}}\n",
-                        major_version(self.qir_version)
-                    )
-                    .as_str(),
-                );
-                flags.push_str(
-                    format!(
-                        "!1 = !{{i32 7, !\"qir_minor_version\", i32 {}}}\n",
-                        minor_version(self.qir_version)
-                    )
-                    .as_str(),
-                );
-                flags.push_str("!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
-                Ok([definitions, main, attributes, flags].join("\n"))
+                Operation::CallDefinedGate(call) => match defined_gates.get(call.gate_name()) {
+                    Some(number_qubits) if *number_qubits != call.qubits().len() => {
+                        return Err(QirError::InvalidQubitCount {
+                            index: *index,
+                            hqslang: op.hqslang(),
+                            msg: format!(
+                                "Gate '{}' is defined on {} qubits but called on {}",
+                                call.gate_name(),
+                                number_qubits,
+                                call.qubits().len()
+                            ),
+                        });
+                    }
+                    _ => {}
+                },
+                _ => {}
             }
+            // Appending gate declaration if not already seen before
+            declare_operation(op, already_seen_declarations, declarations).map_err(locate)?;
+            function.append(call_operation(op, &mut context).map_err(locate)?);
         }
+        // Measurements and resets in gate definitions, conditionals and loops count as well
+        let mut has_measurements = circuit_contains(circuit, &|op| {
+            matches!(
+                op,
+                Operation::MeasureQubit(_) | Operation::PragmaConditional(_)
+            )
+        })?;
+        let mut is_irreversible = circuit_contains(circuit, &|op| {
+            matches!(
+                op,
+                Operation::MeasureQubit(_) | Operation::PragmaActiveReset(_)
+            )
+        })?;
+        if measure_all {
+            has_measurements = true;
+            is_irreversible = true;
+            declare_operation(
+                &Operation::from(MeasureQubit::new(0, "ro".to_owned(), 0)),
+                already_seen_declarations,
+                declarations,
+            )?;
+            if !already_seen_declarations.contains(&"record_output".to_owned()) {
+                already_seen_declarations.push("record_output".to_owned());
+                declarations.push(Function::declaration(
+                    "__quantum__rt__result_record_output",
+                    Type::Void,
+                    &[Type::RESULT, Type::LABEL],
+                ));
+                declarations.push(Function::declaration(
+                    "__quantum__rt__array_record_output",
+                    Type::Void,
+                    &[Type::Integer(64), Type::LABEL],
+                ));
+            }
+            for qubit in 0..number_qubits_required {
+                function.append(call_operation(
+                    &Operation::from(MeasureQubit::new(qubit, "ro".to_owned(), qubit)),
+                    &mut context,
+                )?);
+            }
+            function.push(Instruction::call(
+                "__quantum__rt__array_record_output",
+                vec![
+                    Value::Integer {
+                        bits: 64,
+                        value: number_qubits_required as i64,
+                    },
+                    Value::Null(Pointee::I8),
+                ],
+            ));
+            for qubit in 0..number_qubits_required {
+                function.push(Instruction::call(
+                    "__quantum__rt__result_record_output",
+                    vec![Value::Result(qubit), Value::Null(Pointee::I8)],
+                ));
+            }
+            number_bits_required = number_qubits_required.max(number_bits_required)
+        }
+        function.push(Instruction::Return(match self.qir_version {
+            QirVersion::V2point0 => Some(Value::Integer { bits: 64, value: 0 }),
+            _ => None,
+        }));
+        function.parameters = symbolic_parameters(circuit, &function)?;
+        Ok(EntryPoint {
+            function,
+            number_qubits: number_qubits_required,
+            number_results: number_bits_required,
+            has_measurements,
+            is_irreversible,
+        })
+    }
+
+    /// Translates all circuits of a QuantumProgram to QIR, one module per measured circuit.
+    ///
+    /// The constant circuit of the measurement is prepended to every measured circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - The QuantumProgram that is translated
+    /// * `parameters` - The values of the input parameters of the program, in order of `input_parameter_names`
+    /// * `measure_all` - Whether to measure and record all qubits at the end of each circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<QirEntryPoint>)` - The QIR modules in the order of the measured circuits
+    /// * `QirError::WrongParameterCount` - The number of parameters does not match the program
+    /// * `QirError::UnsupportedQuantumProgram` - The variant of the program cannot be translated
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    pub fn quantum_program_to_qir(
+        &self,
+        program: &QuantumProgram,
+        parameters: &[f64],
+        measure_all: bool,
+    ) -> Result<Vec<QirEntryPoint>, QirError> {
+        let circuits = match program {
+            QuantumProgram::PauliZProduct {
+                measurement,
+                input_parameter_names,
+            } => measurement_circuits(measurement, input_parameter_names, parameters)?,
+            QuantumProgram::CheatedPauliZProduct {
+                measurement,
+                input_parameter_names,
+            } => measurement_circuits(measurement, input_parameter_names, parameters)?,
+            QuantumProgram::Cheated {
+                measurement,
+                input_parameter_names,
+            } => measurement_circuits(measurement, input_parameter_names, parameters)?,
+            QuantumProgram::ClassicalRegister {
+                measurement,
+                input_parameter_names,
+            } => measurement_circuits(measurement, input_parameter_names, parameters)?,
+            _ => return Err(QirError::UnsupportedQuantumProgram),
+        };
+        circuits
+            .iter()
+            .enumerate()
+            .map(|(index, circuit)| {
+                Ok(QirEntryPoint {
+                    name: format!("circuit_{index}"),
+                    qir: self.circuit_to_qir_str(circuit, measure_all)?,
+                    readout_registers: readout_registers(circuit),
+                })
+            })
+            .collect()
+    }
+
+    /// Translates a Circuit to QIR and writes it to a writer.
+    ///
+    /// The whole module is translated first, its text is then written through an internal
+    /// `BufWriter` without building the intermediate String of `circuit_to_qir_str`.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The Circuit that is translated
+    /// * `writer` - The writer the QIR text is written to, for example stdout or a `Vec<u8>`.
+    /// * `measure_all` - Whether to measure and record all qubits at the end of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The QIR text was written and the writer flushed
+    /// * `QirError::Io` - The writer returned an error
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    pub fn write_qir<W: Write>(
+        &self,
+        circuit: &Circuit,
+        writer: W,
+        measure_all: bool,
+    ) -> Result<(), QirError> {
+        let module = self.circuit_to_qir_module(circuit, measure_all)?;
+        let mut writer = BufWriter::new(writer);
+        write!(writer, "{module}")
+            .and_then(|_| writer.flush())
+            .map_err(|error| QirError::Io {
+                path: None,
+                kind: error.kind(),
+                msg: error.to_string(),
+            })
     }
 
     /// Translates a Circuit to a QIR file.
     ///
+    /// The file is written atomically and its folder is created if the backend is configured to,
+    /// see `set_atomic_writes` and `set_create_directories`.
+    ///
     /// # Arguments
     ///
     /// * `circuit` - The Circuit that is translated
@@ -361,7 +1476,8 @@ impl Backend {
     /// # Returns
     ///
     /// * `Ok(())` - The QIR file was correctly written
-    /// * `RoqoqoBackendError::FileAlreadyExists` - The file at this location already exists
+    /// * `QirError::Backend(RoqoqoBackendError::FileAlreadyExists)` - The file at this location already exists
+    /// * `QirError::Io` - The file could not be written
     pub fn circuit_to_qir_file(
         &self,
         circuit: &Circuit,
@@ -369,36 +1485,93 @@ impl Backend {
         filename: &Path,
         overwrite: bool,
         measure_all: bool,
-    ) -> Result<(), RoqoqoBackendError> {
-        let data: String = self.circuit_to_qir_str(circuit, measure_all)?;
+    ) -> Result<(), QirError> {
+        let module = self.circuit_to_qir_module(circuit, measure_all)?;
 
         let output_path: PathBuf = folder_name.join(filename.with_extension("ll"));
-        if output_path.is_file() && !overwrite {
-            return Err(RoqoqoBackendError::FileAlreadyExists {
-                path: output_path.to_str().unwrap().to_string(),
-            });
-        } else {
-            let f = File::create(output_path).expect("Unable to create file");
-            let mut f = BufWriter::new(f);
-            f.write_all(data.as_bytes()).expect("Unable to write file")
-        }
+        self.write_file(&output_path, overwrite, |writer| write!(writer, "{module}"))
+    }
 
-        Ok(())
+    /// Translates a Circuit to a QIR module in LLVM bitcode.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The Circuit items that is translated
+    /// * `measure_all` - Whether to measure and record all qubits at the end of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The valid QIR bitcode
+    /// * `QirError::UnsupportedOperation` - An operation is not available on the backend
+    #[cfg(feature = "bitcode")]
+    pub fn circuit_to_qir_bitcode(
+        &self,
+        circuit: &Circuit,
+        measure_all: bool,
+    ) -> Result<Vec<u8>, QirError> {
+        Ok(self
+            .circuit_to_qir_module(circuit, measure_all)?
+            .to_bitcode()?)
     }
+
+    /// Translates a Circuit to a QIR bitcode file with the `.bc` extension.
+    ///
+    /// The file is written like in `circuit_to_qir_file`.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The Circuit that is translated
+    /// * `folder_name` - The name of the folder that is prepended to all filenames.
+    /// * `filename` - The name of the file the QIR bitcode is saved to.
+    /// * `overwrite` - Whether to overwrite file if it already exists.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The QIR file was correctly written
+    /// * `QirError::Backend(RoqoqoBackendError::FileAlreadyExists)` - The file at this location already exists
+    /// * `QirError::Io` - The file could not be written
+    #[cfg(feature = "bitcode")]
+    pub fn circuit_to_qir_bitcode_file(
+        &self,
+        circuit: &Circuit,
+        folder_name: &Path,
+        filename: &Path,
+        overwrite: bool,
+        measure_all: bool,
+    ) -> Result<(), QirError> {
+        let data: Vec<u8> = self.circuit_to_qir_bitcode(circuit, measure_all)?;
+
+        let output_path: PathBuf = folder_name.join(filename.with_extension("bc"));
+        self.write_file(&output_path, overwrite, |writer| writer.write_all(&data))
+    }
+}
+
+/// The QIR translation of one circuit of a QuantumProgram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QirEntryPoint {
+    /// The name of the circuit, `circuit_` followed by the index of the measured circuit.
+    pub name: String,
+    /// The QIR module of the circuit.
+    pub qir: String,
+    /// The classical registers the circuit defines as outputs.
+    pub readout_registers: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum QirProfile {
     /// QIR base profile: https://github.com/qir-alliance/qir-spec/blob/main/specification/under_development/profiles/Base_Profile.md
     BaseProfile,
+    /// QIR adaptive profile: https://github.com/qir-alliance/qir-spec/blob/main/specification/under_development/profiles/Adaptive_Profile.md
+    AdaptiveProfile,
 }
 
 impl FromStr for QirProfile {
-    type Err = RoqoqoBackendError;
+    type Err = QirError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "base_profile" | "base" | "base profile" => Ok(QirProfile::BaseProfile),
-            _ => Err(RoqoqoBackendError::GenericError {
+            "adaptive_profile" | "adaptive" | "adaptive profile" => Ok(QirProfile::AdaptiveProfile),
+            _ => Err(QirError::InvalidConfiguration {
                 msg: format!("Profile '{}' not supported", s),
             }),
         }
@@ -409,28 +1582,49 @@ impl FromStr for QirProfile {
 pub enum QirVersion {
     /// QIR 0.1
     V0point1,
+    /// QIR 1.0, using typed pointers
+    V1point0,
+    /// QIR 2.0, using opaque pointers
+    V2point0,
 }
 
 impl FromStr for QirVersion {
-    type Err = RoqoqoBackendError;
+    type Err = QirError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "0.1" => Ok(QirVersion::V0point1),
-            _ => Err(RoqoqoBackendError::GenericError {
+            "1.0" | "1" => Ok(QirVersion::V1point0),
+            "2.0" | "2" => Ok(QirVersion::V2point0),
+            _ => Err(QirError::InvalidConfiguration {
                 msg: format!("Version '{}' not supported", s),
             }),
         }
     }
 }
 
+pub(crate) fn profile_name(profile: QirProfile) -> &'static str {
+    match profile {
+        QirProfile::BaseProfile => "base_profile",
+        QirProfile::AdaptiveProfile => "adaptive_profile",
+    }
+}
+
 fn minor_version(version: QirVersion) -> usize {
     match version {
-        QirVersion::V0point1 => 0,
+        QirVersion::V0point1 | QirVersion::V1point0 | QirVersion::V2point0 => 0,
     }
 }
 
 fn major_version(version: QirVersion) -> usize {
     match version {
-        QirVersion::V0point1 => 1,
+        QirVersion::V0point1 | QirVersion::V1point0 => 1,
+        QirVersion::V2point0 => 2,
+    }
+}
+
+fn default_pointer_style(version: QirVersion) -> PointerStyle {
+    match version {
+        QirVersion::V0point1 | QirVersion::V1point0 => PointerStyle::Typed,
+        QirVersion::V2point0 => PointerStyle::Opaque,
     }
 }
@@ -0,0 +1,911 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pure-Rust writer of LLVM bitcode for QIR modules.
+//!
+//! Only the subset of the bitcode format needed for QIR is written: opaque struct, integer,
+//! double, pointer and function types, module level constants, attribute groups, module flags
+//! and the instructions of [crate::ir::Instruction]. All records are written unabbreviated,
+//! except for the string table blob.
+
+use std::collections::HashMap;
+
+use roqoqo::RoqoqoBackendError;
+
+use crate::ir::{
+    Attribute, BinaryOperator, FloatPredicate, Function, Instruction, IntegerPredicate, Module,
+    ModuleFlagValue, Pointee, Type, Value,
+};
+use crate::PointerStyle;
+
+// Block ids
+const MODULE_BLOCK_ID: u64 = 8;
+const PARAMATTR_BLOCK_ID: u64 = 9;
+const PARAMATTR_GROUP_BLOCK_ID: u64 = 10;
+const CONSTANTS_BLOCK_ID: u64 = 11;
+const FUNCTION_BLOCK_ID: u64 = 12;
+const IDENTIFICATION_BLOCK_ID: u64 = 13;
+const VALUE_SYMTAB_BLOCK_ID: u64 = 14;
+const METADATA_BLOCK_ID: u64 = 15;
+const TYPE_BLOCK_ID: u64 = 17;
+const STRTAB_BLOCK_ID: u64 = 23;
+
+// Record codes
+const IDENTIFICATION_CODE_STRING: u64 = 1;
+const IDENTIFICATION_CODE_EPOCH: u64 = 2;
+const MODULE_CODE_VERSION: u64 = 1;
+const MODULE_CODE_FUNCTION: u64 = 8;
+const PARAMATTR_CODE_ENTRY: u64 = 2;
+const PARAMATTR_GRP_CODE_ENTRY: u64 = 3;
+const TYPE_CODE_NUMENTRY: u64 = 1;
+const TYPE_CODE_VOID: u64 = 2;
+const TYPE_CODE_DOUBLE: u64 = 4;
+const TYPE_CODE_OPAQUE: u64 = 6;
+const TYPE_CODE_INTEGER: u64 = 7;
+const TYPE_CODE_POINTER: u64 = 8;
+const TYPE_CODE_STRUCT_NAME: u64 = 19;
+const TYPE_CODE_FUNCTION: u64 = 21;
+const TYPE_CODE_OPAQUE_POINTER: u64 = 25;
+const CST_CODE_SETTYPE: u64 = 1;
+const CST_CODE_NULL: u64 = 2;
+const CST_CODE_INTEGER: u64 = 4;
+const CST_CODE_FLOAT: u64 = 6;
+const CST_CODE_CE_CAST: u64 = 11;
+const CAST_INTTOPTR: u64 = 10;
+const METADATA_STRING_OLD: u64 = 1;
+const METADATA_VALUE: u64 = 2;
+const METADATA_NODE: u64 = 3;
+const METADATA_NAME: u64 = 4;
+const METADATA_NAMED_NODE: u64 = 10;
+const FUNC_CODE_DECLAREBLOCKS: u64 = 1;
+const FUNC_CODE_INST_BINOP: u64 = 2;
+const FUNC_CODE_INST_RET: u64 = 10;
+const FUNC_CODE_INST_BR: u64 = 11;
+const FUNC_CODE_INST_PHI: u64 = 16;
+const FUNC_CODE_INST_CMP2: u64 = 28;
+const FUNC_CODE_INST_VSELECT: u64 = 29;
+const FUNC_CODE_INST_CALL: u64 = 34;
+const FUNC_CODE_INST_UNOP: u64 = 56;
+const VST_CODE_ENTRY: u64 = 1;
+const VST_CODE_BBENTRY: u64 = 2;
+const STRTAB_BLOB: u64 = 1;
+
+/// Index of the function attributes in an attribute group entry.
+const FUNCTION_INDEX: u64 = 0xFFFF_FFFF;
+/// Call record flag marking an explicit function type.
+const CALL_EXPLICIT_TYPE: u64 = 1 << 15;
+
+/// Low level writer of the LLVM bitstream container format.
+struct BitstreamWriter {
+    buffer: Vec<u8>,
+    current: u64,
+    bit_count: u32,
+    abbrev_width: u32,
+    blocks: Vec<(u32, usize)>,
+}
+
+impl BitstreamWriter {
+    fn new() -> Self {
+        BitstreamWriter {
+            buffer: vec![],
+            current: 0,
+            bit_count: 0,
+            abbrev_width: 2,
+            blocks: vec![],
+        }
+    }
+
+    fn emit(&mut self, value: u64, width: u32) {
+        self.current |= value << self.bit_count;
+        self.bit_count += width;
+        while self.bit_count >= 8 {
+            self.buffer.push(self.current as u8);
+            self.current >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn emit_vbr(&mut self, mut value: u64, width: u32) {
+        let threshold = 1 << (width - 1);
+        while value >= threshold {
+            self.emit((value & (threshold - 1)) | threshold, width);
+            value >>= width - 1;
+        }
+        self.emit(value, width);
+    }
+
+    fn align_32(&mut self) {
+        while (self.buffer.len() * 8 + self.bit_count as usize) % 32 != 0 {
+            self.emit(0, 1);
+        }
+    }
+
+    fn enter_block(&mut self, block_id: u64, abbrev_width: u32) {
+        self.emit(1, self.abbrev_width);
+        self.emit_vbr(block_id, 8);
+        self.emit_vbr(abbrev_width as u64, 4);
+        self.align_32();
+        self.blocks.push((self.abbrev_width, self.buffer.len()));
+        self.emit(0, 32);
+        self.abbrev_width = abbrev_width;
+    }
+
+    fn exit_block(&mut self) {
+        self.emit(0, self.abbrev_width);
+        self.align_32();
+        if let Some((abbrev_width, offset)) = self.blocks.pop() {
+            let length = ((self.buffer.len() - offset - 4) / 4) as u32;
+            self.buffer[offset..offset + 4].copy_from_slice(&length.to_le_bytes());
+            self.abbrev_width = abbrev_width;
+        }
+    }
+
+    fn record(&mut self, code: u64, operands: &[u64]) {
+        self.emit(3, self.abbrev_width);
+        self.emit_vbr(code, 6);
+        self.emit_vbr(operands.len() as u64, 6);
+        for &operand in operands {
+            self.emit_vbr(operand, 6);
+        }
+    }
+
+    fn string_record(&mut self, code: u64, prefix: &[u64], string: &str) {
+        let mut operands = prefix.to_vec();
+        operands.extend(string.bytes().map(u64::from));
+        self.record(code, &operands);
+    }
+
+    /// Writes a record consisting of a blob, defining the abbreviation it needs first.
+    fn blob_record(&mut self, code: u64, blob: &[u8]) {
+        // DEFINE_ABBREV with a literal record code and a blob operand
+        self.emit(2, self.abbrev_width);
+        self.emit_vbr(2, 5);
+        self.emit(1, 1);
+        self.emit_vbr(code, 8);
+        self.emit(0, 1);
+        self.emit(5, 3);
+        // The first abbreviation defined in a block has the id 4
+        self.emit(4, self.abbrev_width);
+        self.emit_vbr(blob.len() as u64, 6);
+        self.align_32();
+        for &byte in blob {
+            self.emit(byte as u64, 8);
+        }
+        self.align_32();
+    }
+}
+
+/// Encodes a signed value as used by constants and phi nodes, with the sign in the lowest bit.
+fn signed_vbr(value: i64) -> u64 {
+    if value >= 0 {
+        (value as u64) << 1
+    } else {
+        ((value.unsigned_abs()) << 1) | 1
+    }
+}
+
+fn bitcode_error(msg: String) -> RoqoqoBackendError {
+    RoqoqoBackendError::GenericError {
+        msg: format!("Unable to write bitcode: {}", msg),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TypeEntry {
+    Void,
+    Integer(u32),
+    Double,
+    OpaqueStruct(&'static str),
+    Pointer(u64),
+    OpaquePointer,
+    Function(u64, Vec<u64>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Constant {
+    Integer(u64, i64),
+    Double(u64),
+    Null(u64),
+    IntToPtr(u64, u64),
+}
+
+/// Numbering of the types, constants and attributes of a module.
+struct Tables {
+    pointer_style: PointerStyle,
+    types: Vec<TypeEntry>,
+    type_ids: HashMap<TypeEntry, u64>,
+    constants: Vec<Constant>,
+    constant_ids: HashMap<Constant, u64>,
+    number_functions: u64,
+    attribute_groups: Vec<(u64, Vec<Attribute>, Vec<String>)>,
+    attribute_lists: Vec<Vec<u64>>,
+}
+
+impl Tables {
+    fn type_entry(&mut self, entry: TypeEntry) -> u64 {
+        if let Some(&id) = self.type_ids.get(&entry) {
+            return id;
+        }
+        let id = self.types.len() as u64;
+        self.types.push(entry.clone());
+        self.type_ids.insert(entry, id);
+        id
+    }
+
+    fn type_id(&mut self, ty: Type) -> u64 {
+        match (ty, self.pointer_style) {
+            (Type::Void, _) => self.type_entry(TypeEntry::Void),
+            (Type::Integer(bits), _) => self.type_entry(TypeEntry::Integer(bits)),
+            (Type::Double, _) => self.type_entry(TypeEntry::Double),
+            (Type::Pointer(_), PointerStyle::Opaque) => self.type_entry(TypeEntry::OpaquePointer),
+            (Type::Pointer(pointee), PointerStyle::Typed) => {
+                let pointee = match pointee {
+                    Pointee::Qubit => self.type_entry(TypeEntry::OpaqueStruct("Qubit")),
+                    Pointee::Result => self.type_entry(TypeEntry::OpaqueStruct("Result")),
+                    Pointee::I8 => self.type_entry(TypeEntry::Integer(8)),
+                };
+                self.type_entry(TypeEntry::Pointer(pointee))
+            }
+        }
+    }
+
+    fn function_type_id(&mut self, function: &Function) -> u64 {
+        let return_type = self.type_id(function.return_type);
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|parameter| self.type_id(parameter.ty))
+            .collect();
+        self.type_entry(TypeEntry::Function(return_type, parameters))
+    }
+
+    fn constant(&mut self, constant: Constant) -> u64 {
+        if let Some(&id) = self.constant_ids.get(&constant) {
+            return id;
+        }
+        let id = self.number_functions + self.constants.len() as u64;
+        self.constants.push(constant);
+        self.constant_ids.insert(constant, id);
+        id
+    }
+
+    /// Returns the module level value id of a constant, or None for local values.
+    fn constant_id(&mut self, value: &Value) -> Option<u64> {
+        let pointer_style = self.pointer_style;
+        let ty = self.type_id(value.ty());
+        match value {
+            Value::Qubit(0) | Value::Result(0) if pointer_style == PointerStyle::Opaque => {
+                Some(self.constant(Constant::Null(ty)))
+            }
+            Value::Qubit(index) | Value::Result(index) => {
+                let i64_type = self.type_id(Type::Integer(64));
+                let index = self.constant(Constant::Integer(i64_type, *index as i64));
+                Some(self.constant(Constant::IntToPtr(ty, index)))
+            }
+            Value::Double(value) => Some(self.constant(Constant::Double(value.to_bits()))),
+            Value::Integer { value, .. } => Some(self.constant(Constant::Integer(ty, *value))),
+            Value::Null(_) => Some(self.constant(Constant::Null(ty))),
+            Value::Local { .. } => None,
+        }
+    }
+
+    fn attribute_group(
+        &mut self,
+        index: u64,
+        attributes: Vec<Attribute>,
+        enums: Vec<String>,
+    ) -> u64 {
+        if let Some(position) = self
+            .attribute_groups
+            .iter()
+            .position(|group| group.0 == index && group.1 == attributes && group.2 == enums)
+        {
+            return position as u64 + 1;
+        }
+        self.attribute_groups.push((index, attributes, enums));
+        self.attribute_groups.len() as u64
+    }
+
+    fn attribute_list(&mut self, groups: Vec<u64>) -> u64 {
+        if groups.is_empty() {
+            return 0;
+        }
+        if let Some(position) = self.attribute_lists.iter().position(|list| *list == groups) {
+            return position as u64 + 1;
+        }
+        self.attribute_lists.push(groups);
+        self.attribute_lists.len() as u64
+    }
+
+    fn function_attributes(
+        &mut self,
+        module: &Module,
+        group: Option<u32>,
+    ) -> Result<Vec<u64>, RoqoqoBackendError> {
+        match group {
+            None => Ok(vec![]),
+            Some(id) => {
+                let attributes = module
+                    .attribute_groups
+                    .iter()
+                    .find(|group| group.id == id)
+                    .ok_or_else(|| {
+                        bitcode_error(format!("attribute group #{} is not defined", id))
+                    })?
+                    .attributes
+                    .clone();
+                Ok(vec![self.attribute_group(
+                    FUNCTION_INDEX,
+                    attributes,
+                    vec![],
+                )])
+            }
+        }
+    }
+}
+
+/// Returns the bitcode kind of a parameter attribute.
+fn enum_attribute_kind(name: &str) -> Result<u64, RoqoqoBackendError> {
+    match name {
+        "nocapture" => Ok(11),
+        "readnone" => Ok(20),
+        "readonly" => Ok(21),
+        "nonnull" => Ok(39),
+        "writeonly" => Ok(52),
+        _ => Err(bitcode_error(format!(
+            "parameter attribute '{}' is not supported",
+            name
+        ))),
+    }
+}
+
+fn produces_value(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::Call { return_type, .. } => *return_type != Type::Void,
+        Instruction::Phi { .. }
+        | Instruction::IntegerCompare { .. }
+        | Instruction::Binary { .. }
+        | Instruction::FloatNegate { .. }
+        | Instruction::FloatCompare { .. }
+        | Instruction::Select { .. } => true,
+        Instruction::Branch { .. }
+        | Instruction::ConditionalBranch { .. }
+        | Instruction::Return(_) => false,
+    }
+}
+
+/// Returns the absolute value id of a constant or local value.
+fn value_id(
+    tables: &mut Tables,
+    local_ids: &HashMap<&str, u64>,
+    value: &Value,
+) -> Result<u64, RoqoqoBackendError> {
+    match (tables.constant_id(value), value) {
+        (Some(id), _) => Ok(id),
+        (None, Value::Local { name, .. }) => local_ids
+            .get(name.as_str())
+            .copied()
+            .ok_or_else(|| bitcode_error(format!("value '%{}' is not defined", name))),
+        (None, _) => Err(bitcode_error(format!("value {:?} is not supported", value))),
+    }
+}
+
+fn is_numbered(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Writes the body of a function in a FUNCTION_BLOCK.
+fn write_function_body(
+    writer: &mut BitstreamWriter,
+    tables: &mut Tables,
+    module: &Module,
+    function: &Function,
+    function_ids: &HashMap<&str, u64>,
+    call_attributes: &HashMap<u32, u64>,
+) -> Result<(), RoqoqoBackendError> {
+    let first_local = tables.number_functions + tables.constants.len() as u64;
+    let mut local_ids: HashMap<&str, u64> = HashMap::new();
+    let mut local_names: Vec<(&str, u64)> = vec![];
+    let mut next_id = first_local;
+    for parameter in function.parameters.iter() {
+        if let Some(name) = &parameter.name {
+            local_ids.insert(name, next_id);
+            local_names.push((name, next_id));
+        }
+        next_id += 1;
+    }
+    for instruction in function
+        .blocks
+        .iter()
+        .flat_map(|block| block.instructions.iter())
+    {
+        if produces_value(instruction) {
+            if let Some(name) = instruction.result() {
+                local_ids.insert(name, next_id);
+                local_names.push((name, next_id));
+            }
+            next_id += 1;
+        }
+    }
+    let block_ids: HashMap<&str, u64> = function
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| (block.label.as_str(), index as u64))
+        .collect();
+    let block_id = |label: &String| {
+        block_ids
+            .get(label.as_str())
+            .copied()
+            .ok_or_else(|| bitcode_error(format!("label '{}' is not defined", label)))
+    };
+
+    writer.enter_block(FUNCTION_BLOCK_ID, 4);
+    writer.record(FUNC_CODE_DECLAREBLOCKS, &[function.blocks.len() as u64]);
+    let mut instruction_id = first_local + function.parameters.len() as u64;
+    for instruction in function
+        .blocks
+        .iter()
+        .flat_map(|block| block.instructions.iter())
+    {
+        // Operands are encoded relative to the id of the current instruction
+        let relative = |id: u64| (instruction_id as u32).wrapping_sub(id as u32) as u64;
+        match instruction {
+            Instruction::Call {
+                callee,
+                arguments,
+                attribute_group,
+                ..
+            } => {
+                let callee_id = *function_ids.get(callee.as_str()).ok_or_else(|| {
+                    bitcode_error(format!("function '@{}' is not declared", callee))
+                })?;
+                let callee_function = &module.functions[(callee_id) as usize];
+                let function_type = tables.function_type_id(callee_function);
+                let attributes = match attribute_group {
+                    Some(id) => *call_attributes.get(id).unwrap_or(&0),
+                    None => 0,
+                };
+                let mut operands = vec![
+                    attributes,
+                    CALL_EXPLICIT_TYPE,
+                    function_type,
+                    relative(callee_id),
+                ];
+                for argument in arguments {
+                    operands.push(relative(value_id(tables, &local_ids, argument)?));
+                }
+                writer.record(FUNC_CODE_INST_CALL, &operands);
+            }
+            Instruction::Branch { label } => {
+                writer.record(FUNC_CODE_INST_BR, &[block_id(label)?]);
+            }
+            Instruction::ConditionalBranch {
+                condition,
+                true_label,
+                false_label,
+            } => {
+                let condition = relative(value_id(tables, &local_ids, condition)?);
+                writer.record(
+                    FUNC_CODE_INST_BR,
+                    &[block_id(true_label)?, block_id(false_label)?, condition],
+                );
+            }
+            Instruction::Phi { ty, incoming, .. } => {
+                let mut operands = vec![tables.type_id(*ty)];
+                for (value, label) in incoming {
+                    let id = value_id(tables, &local_ids, value)?;
+                    operands.push(signed_vbr(instruction_id as i64 - id as i64));
+                    operands.push(block_id(label)?);
+                }
+                writer.record(FUNC_CODE_INST_PHI, &operands);
+            }
+            Instruction::IntegerCompare {
+                predicate,
+                lhs,
+                rhs,
+                ..
+            } => {
+                let predicate = match predicate {
+                    IntegerPredicate::Eq => 32,
+                    IntegerPredicate::Ne => 33,
+                    IntegerPredicate::Sgt => 38,
+                    IntegerPredicate::Sge => 39,
+                    IntegerPredicate::Slt => 40,
+                    IntegerPredicate::Sle => 41,
+                };
+                let lhs_id = value_id(tables, &local_ids, lhs)?;
+                let rhs_id = value_id(tables, &local_ids, rhs)?;
+                let mut operands = vec![relative(lhs_id)];
+                if lhs_id >= instruction_id {
+                    operands.push(tables.type_id(lhs.ty()));
+                }
+                operands.extend([relative(rhs_id), predicate]);
+                writer.record(FUNC_CODE_INST_CMP2, &operands);
+            }
+            Instruction::Binary {
+                operator, lhs, rhs, ..
+            } => {
+                let opcode = match operator {
+                    BinaryOperator::Add => 0,
+                    BinaryOperator::Sub => 1,
+                    BinaryOperator::Mul => 2,
+                    // LLVM derives the floating point variants from the operand type
+                    BinaryOperator::FAdd => 0,
+                    BinaryOperator::FSub => 1,
+                    BinaryOperator::FMul => 2,
+                    BinaryOperator::FDiv => 4,
+                };
+                let lhs_id = value_id(tables, &local_ids, lhs)?;
+                let rhs_id = value_id(tables, &local_ids, rhs)?;
+                let mut operands = vec![relative(lhs_id)];
+                if lhs_id >= instruction_id {
+                    operands.push(tables.type_id(lhs.ty()));
+                }
+                operands.extend([relative(rhs_id), opcode]);
+                writer.record(FUNC_CODE_INST_BINOP, &operands);
+            }
+            Instruction::FloatNegate { operand, .. } => {
+                let id = value_id(tables, &local_ids, operand)?;
+                let mut operands = vec![relative(id)];
+                if id >= instruction_id {
+                    operands.push(tables.type_id(operand.ty()));
+                }
+                // fneg is the only unary operator, with opcode 0
+                operands.push(0);
+                writer.record(FUNC_CODE_INST_UNOP, &operands);
+            }
+            Instruction::FloatCompare {
+                predicate,
+                lhs,
+                rhs,
+                ..
+            } => {
+                let predicate = match predicate {
+                    FloatPredicate::Oeq => 1,
+                    FloatPredicate::Ogt => 2,
+                    FloatPredicate::Oge => 3,
+                    FloatPredicate::Olt => 4,
+                    FloatPredicate::Ole => 5,
+                    FloatPredicate::One => 6,
+                };
+                let lhs_id = value_id(tables, &local_ids, lhs)?;
+                let rhs_id = value_id(tables, &local_ids, rhs)?;
+                let mut operands = vec![relative(lhs_id)];
+                if lhs_id >= instruction_id {
+                    operands.push(tables.type_id(lhs.ty()));
+                }
+                operands.extend([relative(rhs_id), predicate]);
+                writer.record(FUNC_CODE_INST_CMP2, &operands);
+            }
+            Instruction::Select {
+                condition,
+                true_value,
+                false_value,
+                ..
+            } => {
+                let true_id = value_id(tables, &local_ids, true_value)?;
+                let false_id = value_id(tables, &local_ids, false_value)?;
+                let condition_id = value_id(tables, &local_ids, condition)?;
+                let mut operands = vec![relative(true_id)];
+                if true_id >= instruction_id {
+                    operands.push(tables.type_id(true_value.ty()));
+                }
+                operands.extend([relative(false_id), relative(condition_id)]);
+                if condition_id >= instruction_id {
+                    operands.push(tables.type_id(condition.ty()));
+                }
+                writer.record(FUNC_CODE_INST_VSELECT, &operands);
+            }
+            Instruction::Return(None) => writer.record(FUNC_CODE_INST_RET, &[]),
+            Instruction::Return(Some(value)) => {
+                let id = value_id(tables, &local_ids, value)?;
+                writer.record(FUNC_CODE_INST_RET, &[relative(id)]);
+            }
+        }
+        if produces_value(instruction) {
+            instruction_id += 1;
+        }
+    }
+
+    writer.enter_block(VALUE_SYMTAB_BLOCK_ID, 4);
+    for (name, id) in local_names {
+        if !is_numbered(name) {
+            writer.string_record(VST_CODE_ENTRY, &[id], name);
+        }
+    }
+    for (index, block) in function.blocks.iter().enumerate() {
+        writer.string_record(VST_CODE_BBENTRY, &[index as u64], &block.label);
+    }
+    writer.exit_block();
+    writer.exit_block();
+    Ok(())
+}
+
+impl Module {
+    /// Writes the module as LLVM bitcode.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The bitcode of the module
+    /// * `RoqoqoBackendError::GenericError` - The module references undefined functions, labels, values or attributes
+    pub fn to_bitcode(&self) -> Result<Vec<u8>, RoqoqoBackendError> {
+        let mut tables = Tables {
+            pointer_style: self.pointer_style,
+            types: vec![],
+            type_ids: HashMap::new(),
+            constants: vec![],
+            constant_ids: HashMap::new(),
+            number_functions: self.functions.len() as u64,
+            attribute_groups: vec![],
+            attribute_lists: vec![],
+        };
+        let function_ids: HashMap<&str, u64> = self
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(index, function)| (function.name.as_str(), index as u64))
+            .collect();
+
+        // Numbering types, constants and attributes before writing anything
+        let mut function_types = vec![];
+        let mut function_attributes = vec![];
+        for function in self.functions.iter() {
+            function_types.push(tables.function_type_id(function));
+            let mut groups = tables.function_attributes(self, function.attribute_group)?;
+            for (index, parameter) in function.parameters.iter().enumerate() {
+                if !parameter.attributes.is_empty() {
+                    groups.push(tables.attribute_group(
+                        index as u64 + 1,
+                        vec![],
+                        parameter.attributes.clone(),
+                    ));
+                }
+            }
+            function_attributes.push(tables.attribute_list(groups));
+        }
+        let mut call_attributes: HashMap<u32, u64> = HashMap::new();
+        for function in self.functions.iter() {
+            for instruction in function
+                .blocks
+                .iter()
+                .flat_map(|block| block.instructions.iter())
+            {
+                let mut values: Vec<&Value> = vec![];
+                match instruction {
+                    Instruction::Call {
+                        arguments,
+                        attribute_group,
+                        ..
+                    } => {
+                        values.extend(arguments.iter());
+                        if let Some(id) = attribute_group {
+                            let groups = tables.function_attributes(self, Some(*id))?;
+                            let list = tables.attribute_list(groups);
+                            call_attributes.insert(*id, list);
+                        }
+                    }
+                    Instruction::ConditionalBranch { condition, .. } => values.push(condition),
+                    Instruction::Phi { ty, incoming, .. } => {
+                        tables.type_id(*ty);
+                        values.extend(incoming.iter().map(|(value, _)| value));
+                    }
+                    _ => values.extend(instruction.values()),
+                }
+                for value in values {
+                    tables.type_id(value.ty());
+                    tables.constant_id(value);
+                }
+            }
+        }
+        let i32_type = tables.type_id(Type::Integer(32));
+        let mut flag_constants = vec![];
+        for flag in self.module_flags.iter() {
+            let behavior = tables.constant(Constant::Integer(i32_type, flag.behavior as i64));
+            let value = match &flag.value {
+                ModuleFlagValue::Constant(value) => Some((
+                    tables.type_id(value.ty()),
+                    tables.constant_id(value).ok_or_else(|| {
+                        bitcode_error("module flags need constant values".to_owned())
+                    })?,
+                )),
+                ModuleFlagValue::Strings(_) => None,
+            };
+            flag_constants.push((behavior, value));
+        }
+
+        let mut writer = BitstreamWriter::new();
+        for byte in [b'B', b'C', 0xC0, 0xDE] {
+            writer.emit(byte as u64, 8);
+        }
+
+        writer.enter_block(IDENTIFICATION_BLOCK_ID, 5);
+        writer.string_record(IDENTIFICATION_CODE_STRING, &[], "roqoqo-qir");
+        writer.record(IDENTIFICATION_CODE_EPOCH, &[0]);
+        writer.exit_block();
+
+        writer.enter_block(MODULE_BLOCK_ID, 3);
+        // Version 2: relative value ids and names in the string table
+        writer.record(MODULE_CODE_VERSION, &[2]);
+
+        if !tables.attribute_groups.is_empty() {
+            writer.enter_block(PARAMATTR_GROUP_BLOCK_ID, 3);
+            for (group_id, (index, attributes, enums)) in tables.attribute_groups.iter().enumerate()
+            {
+                let mut operands = vec![group_id as u64 + 1, *index];
+                for name in enums {
+                    operands.extend([0, enum_attribute_kind(name)?]);
+                }
+                for attribute in attributes {
+                    match &attribute.value {
+                        None => operands.push(3),
+                        Some(_) => operands.push(4),
+                    }
+                    operands.extend(attribute.key.bytes().map(u64::from));
+                    operands.push(0);
+                    if let Some(value) = &attribute.value {
+                        operands.extend(value.bytes().map(u64::from));
+                        operands.push(0);
+                    }
+                }
+                writer.record(PARAMATTR_GRP_CODE_ENTRY, &operands);
+            }
+            writer.exit_block();
+
+            writer.enter_block(PARAMATTR_BLOCK_ID, 3);
+            for list in tables.attribute_lists.iter() {
+                writer.record(PARAMATTR_CODE_ENTRY, list);
+            }
+            writer.exit_block();
+        }
+
+        writer.enter_block(TYPE_BLOCK_ID, 4);
+        writer.record(TYPE_CODE_NUMENTRY, &[tables.types.len() as u64]);
+        for entry in tables.types.iter() {
+            match entry {
+                TypeEntry::Void => writer.record(TYPE_CODE_VOID, &[]),
+                TypeEntry::Integer(bits) => writer.record(TYPE_CODE_INTEGER, &[*bits as u64]),
+                TypeEntry::Double => writer.record(TYPE_CODE_DOUBLE, &[]),
+                TypeEntry::OpaqueStruct(name) => {
+                    writer.string_record(TYPE_CODE_STRUCT_NAME, &[], name);
+                    writer.record(TYPE_CODE_OPAQUE, &[0]);
+                }
+                TypeEntry::Pointer(pointee) => writer.record(TYPE_CODE_POINTER, &[*pointee, 0]),
+                TypeEntry::OpaquePointer => writer.record(TYPE_CODE_OPAQUE_POINTER, &[0]),
+                TypeEntry::Function(return_type, parameters) => {
+                    let mut operands = vec![0, *return_type];
+                    operands.extend(parameters);
+                    writer.record(TYPE_CODE_FUNCTION, &operands);
+                }
+            }
+        }
+        writer.exit_block();
+
+        let mut strtab: Vec<u8> = vec![];
+        for (index, function) in self.functions.iter().enumerate() {
+            let offset = strtab.len() as u64;
+            strtab.extend(function.name.bytes());
+            writer.record(
+                MODULE_CODE_FUNCTION,
+                &[
+                    offset,
+                    function.name.len() as u64,
+                    function_types[index],
+                    0,
+                    function.is_declaration() as u64,
+                    0,
+                    function_attributes[index],
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                ],
+            );
+        }
+
+        if !tables.constants.is_empty() {
+            writer.enter_block(CONSTANTS_BLOCK_ID, 4);
+            let mut current_type = None;
+            for constant in tables.constants.iter() {
+                let ty = match constant {
+                    Constant::Integer(ty, _) | Constant::Null(ty) | Constant::IntToPtr(ty, _) => {
+                        *ty
+                    }
+                    Constant::Double(_) => tables.type_ids[&TypeEntry::Double],
+                };
+                if current_type != Some(ty) {
+                    writer.record(CST_CODE_SETTYPE, &[ty]);
+                    current_type = Some(ty);
+                }
+                match constant {
+                    Constant::Integer(_, value) => {
+                        writer.record(CST_CODE_INTEGER, &[signed_vbr(*value)])
+                    }
+                    Constant::Double(bits) => writer.record(CST_CODE_FLOAT, &[*bits]),
+                    Constant::Null(_) => writer.record(CST_CODE_NULL, &[]),
+                    Constant::IntToPtr(_, operand) => {
+                        let i64_type = tables.type_ids[&TypeEntry::Integer(64)];
+                        writer.record(CST_CODE_CE_CAST, &[CAST_INTTOPTR, i64_type, *operand])
+                    }
+                }
+            }
+            writer.exit_block();
+        }
+
+        if !self.module_flags.is_empty() {
+            writer.enter_block(METADATA_BLOCK_ID, 3);
+            let mut metadata_id = 0;
+            let mut nodes = vec![];
+            for (flag, (behavior, value)) in self.module_flags.iter().zip(flag_constants.iter()) {
+                writer.record(METADATA_VALUE, &[i32_type, *behavior]);
+                writer.string_record(METADATA_STRING_OLD, &[], &flag.name);
+                // Operands of metadata nodes are encoded as metadata id + 1
+                let behavior_id = metadata_id;
+                let name_id = metadata_id + 1;
+                metadata_id += 2;
+                match (value, &flag.value) {
+                    (Some((value_type, value)), _) => {
+                        writer.record(METADATA_VALUE, &[*value_type, *value]);
+                    }
+                    (None, ModuleFlagValue::Strings(strings)) => {
+                        let mut operands = vec![];
+                        for string in strings {
+                            writer.string_record(METADATA_STRING_OLD, &[], string);
+                            operands.push(metadata_id + 1);
+                            metadata_id += 1;
+                        }
+                        writer.record(METADATA_NODE, &operands);
+                    }
+                    (None, ModuleFlagValue::Constant(_)) => unreachable!(),
+                }
+                writer.record(
+                    METADATA_NODE,
+                    &[behavior_id + 1, name_id + 1, metadata_id + 1],
+                );
+                nodes.push(metadata_id + 1);
+                metadata_id += 2;
+            }
+            writer.string_record(METADATA_NAME, &[], "llvm.module.flags");
+            writer.record(METADATA_NAMED_NODE, &nodes);
+            writer.exit_block();
+        }
+
+        for function in self
+            .functions
+            .iter()
+            .filter(|function| !function.is_declaration())
+        {
+            write_function_body(
+                &mut writer,
+                &mut tables,
+                self,
+                function,
+                &function_ids,
+                &call_attributes,
+            )?;
+        }
+        writer.exit_block();
+
+        writer.enter_block(STRTAB_BLOCK_ID, 3);
+        writer.blob_record(STRTAB_BLOB, &strtab);
+        writer.exit_block();
+
+        Ok(writer.buffer)
+    }
+}
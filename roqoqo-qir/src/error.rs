@@ -0,0 +1,304 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use roqoqo::{operations::*, RoqoqoBackendError};
+use std::{error::Error, fmt, io};
+
+/// Errors of the translation of a circuit to QIR.
+///
+/// `index` is the position of the failing operation in the translated circuit. Operations nested in
+/// GateDefinitions, PragmaConditionals and PragmaLoops report the index of the outermost operation
+/// together with their own `hqslang` name. Functions translating a single operation report index 0.
+///
+/// QirError is returned by the Backend, including its constructor and setters, and by the
+/// translation functions of the interface and the symbolic expressions. Only the functions not
+/// translating circuits, the gate synthesis ([crate::synthesize_gate]) and the bitcode writer
+/// (`Module::to_bitcode`), return a RoqoqoBackendError, which converts into `QirError::Backend`.
+#[derive(Debug, PartialEq)]
+pub enum QirError {
+    /// The operation has no translation to QIR.
+    UnsupportedOperation {
+        /// Position of the operation in the circuit.
+        index: usize,
+        /// Name of the operation.
+        hqslang: &'static str,
+    },
+    /// The operation is not allowed in the QIR profile of the backend.
+    ProfileViolation {
+        /// Position of the operation in the circuit.
+        index: usize,
+        /// Name of the operation.
+        hqslang: &'static str,
+        /// Name of the QIR profile.
+        profile: &'static str,
+    },
+    /// The operation uses a symbolic parameter that has no value where one is needed.
+    UnboundParameter {
+        /// Position of the operation in the circuit.
+        index: usize,
+        /// Name of the operation.
+        hqslang: &'static str,
+        /// Name of the symbolic parameter.
+        parameter: String,
+    },
+    /// The operation acts on a number of qubits it cannot be translated with.
+    InvalidQubitCount {
+        /// Position of the operation in the circuit.
+        index: usize,
+        /// Name of the operation.
+        hqslang: &'static str,
+        /// Description of the expected number of qubits.
+        msg: String,
+    },
+    /// The QIR output could not be written, this is not caused by an operation.
+    Io {
+        /// Path of the file that could not be written, None for other writers.
+        path: Option<String>,
+        /// Kind of the underlying IO error.
+        kind: io::ErrorKind,
+        /// Description of the underlying IO error.
+        msg: String,
+    },
+    /// A GateDefinition cannot be translated to a QIR function.
+    InvalidDefinition {
+        /// Position of the operation in the circuit.
+        index: usize,
+        /// Name of the operation.
+        hqslang: &'static str,
+        /// Description of the problem of the definition.
+        msg: String,
+    },
+    /// Any other error raised while translating an operation, such as an invalid expression.
+    OperationFailed {
+        /// Position of the operation in the circuit.
+        index: usize,
+        /// Name of the operation.
+        hqslang: &'static str,
+        /// The underlying error.
+        error: RoqoqoBackendError,
+    },
+    /// A setting of the backend or a name in the module is invalid, this is not caused by an operation.
+    InvalidConfiguration {
+        /// Description of the invalid setting.
+        msg: String,
+    },
+    /// A symbolic parameter cannot be an argument of the entry point function.
+    InvalidParameter {
+        /// Name of the symbolic parameter.
+        parameter: String,
+        /// Description of the problem of the parameter.
+        msg: String,
+    },
+    /// A QuantumProgram was given another number of values than it has input parameters.
+    WrongParameterCount {
+        /// Number of input parameters of the QuantumProgram.
+        expected: usize,
+        /// Number of values given.
+        given: usize,
+    },
+    /// The variant of the QuantumProgram has no translation to QIR.
+    UnsupportedQuantumProgram,
+    /// Any other error that is not caused by a single operation, such as an existing output file.
+    Backend(RoqoqoBackendError),
+}
+
+impl QirError {
+    /// Returns the position of the failing operation, if the error was caused by one.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            QirError::UnsupportedOperation { index, .. }
+            | QirError::ProfileViolation { index, .. }
+            | QirError::UnboundParameter { index, .. }
+            | QirError::InvalidQubitCount { index, .. }
+            | QirError::InvalidDefinition { index, .. }
+            | QirError::OperationFailed { index, .. } => Some(*index),
+            QirError::Io { .. }
+            | QirError::InvalidConfiguration { .. }
+            | QirError::InvalidParameter { .. }
+            | QirError::WrongParameterCount { .. }
+            | QirError::UnsupportedQuantumProgram
+            | QirError::Backend(_) => None,
+        }
+    }
+
+    /// Returns the name of the failing operation, if the error was caused by one.
+    pub fn hqslang(&self) -> Option<&'static str> {
+        match self {
+            QirError::UnsupportedOperation { hqslang, .. }
+            | QirError::ProfileViolation { hqslang, .. }
+            | QirError::UnboundParameter { hqslang, .. }
+            | QirError::InvalidQubitCount { hqslang, .. }
+            | QirError::InvalidDefinition { hqslang, .. }
+            | QirError::OperationFailed { hqslang, .. } => Some(hqslang),
+            QirError::Io { .. }
+            | QirError::InvalidConfiguration { .. }
+            | QirError::InvalidParameter { .. }
+            | QirError::WrongParameterCount { .. }
+            | QirError::UnsupportedQuantumProgram
+            | QirError::Backend(_) => None,
+        }
+    }
+
+    /// Attributes the error to the operation at `index` of a circuit.
+    ///
+    /// Errors of nested operations keep their name and take the index of the enclosing operation.
+    /// Unattributed backend errors become errors of `operation`.
+    pub(crate) fn locate(self, index: usize, operation: &Operation) -> Self {
+        match self {
+            QirError::UnsupportedOperation { hqslang, .. } => {
+                QirError::UnsupportedOperation { index, hqslang }
+            }
+            QirError::ProfileViolation {
+                hqslang, profile, ..
+            } => QirError::ProfileViolation {
+                index,
+                hqslang,
+                profile,
+            },
+            QirError::UnboundParameter {
+                hqslang, parameter, ..
+            } => QirError::UnboundParameter {
+                index,
+                hqslang,
+                parameter,
+            },
+            QirError::InvalidQubitCount { hqslang, msg, .. } => QirError::InvalidQubitCount {
+                index,
+                hqslang,
+                msg,
+            },
+            QirError::InvalidDefinition { hqslang, msg, .. } => QirError::InvalidDefinition {
+                index,
+                hqslang,
+                msg,
+            },
+            QirError::OperationFailed { hqslang, error, .. } => QirError::OperationFailed {
+                index,
+                hqslang,
+                error,
+            },
+            QirError::Backend(RoqoqoBackendError::OperationNotInBackend { hqslang, .. }) => {
+                QirError::UnsupportedOperation { index, hqslang }
+            }
+            QirError::Backend(error) => QirError::OperationFailed {
+                index,
+                hqslang: operation.hqslang(),
+                error,
+            },
+            error @ (QirError::Io { .. }
+            | QirError::InvalidConfiguration { .. }
+            | QirError::InvalidParameter { .. }
+            | QirError::WrongParameterCount { .. }
+            | QirError::UnsupportedQuantumProgram) => error,
+        }
+    }
+}
+
+impl fmt::Display for QirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QirError::UnsupportedOperation { index, hqslang } => write!(
+                f,
+                "Operation {index} ({hqslang}) is not supported by the QIR backend"
+            ),
+            QirError::ProfileViolation {
+                index,
+                hqslang,
+                profile,
+            } => write!(
+                f,
+                "Operation {index} ({hqslang}) is not allowed in the QIR profile '{profile}'"
+            ),
+            QirError::UnboundParameter {
+                index,
+                hqslang,
+                parameter,
+            } => write!(
+                f,
+                "Operation {index} ({hqslang}) uses the unbound parameter '{parameter}'"
+            ),
+            QirError::InvalidQubitCount {
+                index,
+                hqslang,
+                msg,
+            } => write!(
+                f,
+                "Operation {index} ({hqslang}) acts on an invalid number of qubits: {msg}"
+            ),
+            QirError::Io {
+                path: Some(path),
+                msg,
+                ..
+            } => write!(f, "Unable to write {path}: {msg}"),
+            QirError::Io {
+                path: None, msg, ..
+            } => write!(f, "Unable to write the QIR output: {msg}"),
+            QirError::InvalidDefinition {
+                index,
+                hqslang,
+                msg,
+            } => write!(f, "Operation {index} ({hqslang}) is invalid: {msg}"),
+            QirError::OperationFailed {
+                index,
+                hqslang,
+                error,
+            } => write!(
+                f,
+                "Operation {index} ({hqslang}) could not be translated: {error}"
+            ),
+            QirError::InvalidConfiguration { msg } => write!(f, "Invalid configuration: {msg}"),
+            QirError::InvalidParameter { parameter, msg } => write!(
+                f,
+                "Symbolic parameter '{parameter}' cannot be an argument of the entry point: {msg}"
+            ),
+            QirError::WrongParameterCount { expected, given } => write!(
+                f,
+                "Wrong number of parameters: {expected} parameters expected, {given} parameters given"
+            ),
+            QirError::UnsupportedQuantumProgram => {
+                write!(f, "QuantumProgram type is not supported by the QIR backend")
+            }
+            QirError::Backend(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for QirError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QirError::OperationFailed { error, .. } | QirError::Backend(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<RoqoqoBackendError> for QirError {
+    fn from(error: RoqoqoBackendError) -> Self {
+        QirError::Backend(error)
+    }
+}
+
+/// Converts the error for the roqoqo backend traits.
+///
+/// Errors located at an operation become a `GenericError` with the message of the QirError, so
+/// the index and name of the operation are kept. Unlocated `QirError::Backend` errors are
+/// returned unchanged.
+impl From<QirError> for RoqoqoBackendError {
+    fn from(error: QirError) -> Self {
+        match error {
+            QirError::Backend(error) => error,
+            _ => RoqoqoBackendError::GenericError {
+                msg: error.to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,562 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use qoqo_calculator::{Calculator, CalculatorError};
+use roqoqo::RoqoqoBackendError;
+use std::f64::consts::PI;
+
+use crate::ir::{BinaryOperator, FloatPredicate, Fragment, Function, Instruction, Type, Value};
+use crate::{QirError, TranslationContext};
+
+/// Functions of qoqo_calculator with the called LLVM intrinsic or C math library function
+/// and their number of arguments.
+///
+/// `fract`, `sign`, `delta` and `theta` are lowered to several instructions instead.
+const MATH_FUNCTIONS: &[(&str, &str, usize)] = &[
+    ("sin", "llvm.sin.f64", 1),
+    ("cos", "llvm.cos.f64", 1),
+    ("abs", "llvm.fabs.f64", 1),
+    ("exp", "llvm.exp.f64", 1),
+    ("exp2", "llvm.exp2.f64", 1),
+    ("log", "llvm.log.f64", 1),
+    ("log10", "llvm.log10.f64", 1),
+    ("sqrt", "llvm.sqrt.f64", 1),
+    ("ceil", "llvm.ceil.f64", 1),
+    ("floor", "llvm.floor.f64", 1),
+    ("round", "llvm.round.f64", 1),
+    ("pow", "llvm.pow.f64", 2),
+    ("max", "llvm.maxnum.f64", 2),
+    ("min", "llvm.minnum.f64", 2),
+    ("tan", "tan", 1),
+    ("acos", "acos", 1),
+    ("asin", "asin", 1),
+    ("atan", "atan", 1),
+    ("cosh", "cosh", 1),
+    ("sinh", "sinh", 1),
+    ("tanh", "tanh", 1),
+    ("acosh", "acosh", 1),
+    ("asinh", "asinh", 1),
+    ("atanh", "atanh", 1),
+    ("arcosh", "acosh", 1),
+    ("arsinh", "asinh", 1),
+    ("artanh", "atanh", 1),
+    ("expm1", "expm1", 1),
+    ("cbrt", "cbrt", 1),
+    ("atan2", "atan2", 2),
+    ("hypot", "hypot", 2),
+];
+
+/// LLVM intrinsics used when lowering `fract` and `sign`.
+const HELPER_INTRINSICS: &[&str] = &["llvm.trunc.f64", "llvm.copysign.f64"];
+
+/// Returns true if the function is called by lowered expressions and needs a declaration.
+fn is_math_function(callee: &str) -> bool {
+    MATH_FUNCTIONS.iter().any(|(_, name, _)| *name == callee) || HELPER_INTRINSICS.contains(&callee)
+}
+
+/// Returns the declarations of the math functions called in the given functions.
+///
+/// The declarations are returned in order of first use.
+///
+/// # Arguments
+///
+/// * `functions` - The functions whose bodies are searched
+///
+/// # Returns
+///
+/// * `Vec<Function>` - The declarations of the called LLVM intrinsics and C math library functions
+pub fn math_declarations(functions: &[Function]) -> Vec<Function> {
+    let mut declarations: Vec<Function> = vec![];
+    for instruction in functions
+        .iter()
+        .flat_map(|function| function.blocks.iter())
+        .flat_map(|block| block.instructions.iter())
+    {
+        if let Instruction::Call {
+            callee, arguments, ..
+        } = instruction
+        {
+            if is_math_function(callee)
+                && !declarations
+                    .iter()
+                    .any(|declaration| &declaration.name == callee)
+            {
+                declarations.push(Function::declaration(
+                    callee,
+                    Type::Double,
+                    &vec![Type::Double; arguments.len()],
+                ));
+            }
+        }
+    }
+    declarations
+}
+
+/// Emits a floating point operation, or evaluates it if both operands are constant.
+///
+/// # Arguments
+///
+/// * `operator` - The floating point operator, one of `FAdd`, `FSub`, `FMul` and `FDiv`
+/// * `lhs` - The left hand side `double` operand
+/// * `rhs` - The right hand side `double` operand
+/// * `fragment` - The fragment the instruction is appended to
+/// * `context` - The numbering of the function the fragment is part of
+///
+/// # Returns
+///
+/// * `Ok(Value)` - The constant result or the local value holding the result
+/// * `QirError::Backend` - The divisor is the constant zero, a `RoqoqoBackendError::CalculatorError`
+pub fn float_operation(
+    operator: BinaryOperator,
+    lhs: Value,
+    rhs: Value,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<Value, QirError> {
+    emit_float_operation(operator, lhs, rhs, fragment, context).map_err(calculator_error)
+}
+
+/// Wraps an error of a symbolic expression as an error of the translation.
+fn calculator_error(error: CalculatorError) -> QirError {
+    QirError::Backend(RoqoqoBackendError::CalculatorError(error))
+}
+
+/// Implements [float_operation] with the errors of qoqo_calculator.
+fn emit_float_operation(
+    operator: BinaryOperator,
+    lhs: Value,
+    rhs: Value,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<Value, CalculatorError> {
+    if operator == BinaryOperator::FDiv && rhs == Value::Double(0.0) {
+        return Err(CalculatorError::DivisionByZero);
+    }
+    if let (Value::Double(lhs), Value::Double(rhs)) = (&lhs, &rhs) {
+        return Ok(Value::Double(match operator {
+            BinaryOperator::FAdd => lhs + rhs,
+            BinaryOperator::FSub => lhs - rhs,
+            BinaryOperator::FMul => lhs * rhs,
+            _ => lhs / rhs,
+        }));
+    }
+    let result = context.new_value();
+    fragment.push(Instruction::Binary {
+        result: result.clone(),
+        operator,
+        lhs,
+        rhs,
+    });
+    Ok(Value::local(result, Type::Double))
+}
+
+/// Emits a floating point negation, or evaluates it if the operand is constant.
+///
+/// # Arguments
+///
+/// * `operand` - The negated `double` operand
+/// * `fragment` - The fragment the instruction is appended to
+/// * `context` - The numbering of the function the fragment is part of
+///
+/// # Returns
+///
+/// * `Value` - The constant result or the local value holding the result
+pub fn float_negation(
+    operand: Value,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Value {
+    match operand {
+        Value::Double(value) => Value::Double(-value),
+        _ => {
+            let result = context.new_value();
+            fragment.push(Instruction::FloatNegate {
+                result: result.clone(),
+                operand,
+            });
+            Value::local(result, Type::Double)
+        }
+    }
+}
+
+/// Token of a qoqo_calculator expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Variable(String),
+    /// Name of a function, including the opening bracket of its arguments.
+    Function(String),
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Power,
+    Comma,
+    BracketOpen,
+    BracketClose,
+    End,
+}
+
+/// Splits an expression into tokens with the lexical rules of qoqo_calculator.
+fn tokenize(expression: &str) -> Result<Vec<Token>, CalculatorError> {
+    let characters: Vec<char> = expression.chars().collect();
+    let mut tokens: Vec<Token> = vec![];
+    let mut position = 0;
+    let parsing_error = |msg: &'static str| CalculatorError::ParsingError { msg };
+    while position < characters.len() {
+        let character = characters[position];
+        let start = position;
+        position += 1;
+        let token = match character {
+            c if c.is_whitespace() => continue,
+            c if c.is_alphabetic() => {
+                while position < characters.len()
+                    && (characters[position].is_alphanumeric() || characters[position] == '_')
+                {
+                    position += 1;
+                }
+                let name: String = characters[start..position].iter().collect();
+                let mut next = position;
+                while next < characters.len() && characters[next].is_whitespace() {
+                    next += 1;
+                }
+                match characters.get(next) {
+                    Some('(') => {
+                        position = next + 1;
+                        Token::Function(name)
+                    }
+                    Some('=') => {
+                        return Err(CalculatorError::ForbiddenAssign {
+                            variable_name: name,
+                        })
+                    }
+                    _ => Token::Variable(name),
+                }
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                while position < characters.len()
+                    && (characters[position].is_ascii_digit() || characters[position] == '.')
+                {
+                    position += 1;
+                }
+                if matches!(characters.get(position), Some('e' | 'E')) {
+                    let mut end = position + 1;
+                    if matches!(characters.get(end), Some('+' | '-')) {
+                        end += 1;
+                    }
+                    while end < characters.len() && characters[end].is_ascii_digit() {
+                        end += 1;
+                    }
+                    position = end;
+                }
+                let number: String = characters[start..position].iter().collect();
+                Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| parsing_error("Unrecognized number"))?,
+                )
+            }
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' if characters.get(position) == Some(&'*') => {
+                position += 1;
+                Token::Power
+            }
+            '*' => Token::Multiply,
+            '/' => Token::Divide,
+            '^' => Token::Power,
+            ',' => Token::Comma,
+            '(' => Token::BracketOpen,
+            ')' => Token::BracketClose,
+            '!' => return Err(CalculatorError::NotImplementedError { fct: "Factorial" }),
+            _ => return Err(parsing_error("Unrecognized token")),
+        };
+        tokens.push(token);
+    }
+    tokens.push(Token::End);
+    Ok(tokens)
+}
+
+/// Lowers a symbolic expression to floating point instructions.
+///
+/// The expression is parsed with the grammar of qoqo_calculator. `pi` is replaced by its
+/// numerical value and every other variable is referenced as a local `double` value of the same
+/// name. Subexpressions without variables are evaluated at translation time, all others are
+/// computed by instructions appended to `fragment`.
+///
+/// # Arguments
+///
+/// * `expression` - The expression that is lowered
+/// * `fragment` - The fragment the instructions computing the expression are appended to
+/// * `context` - The numbering of the function the fragment is part of
+///
+/// # Returns
+///
+/// * `Ok(Value)` - The constant value of the expression or the local value holding its result
+/// * `QirError::Backend` - The expression can not be parsed or evaluated, a
+///   `RoqoqoBackendError::CalculatorError`
+pub fn lower_expression(
+    expression: &str,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<Value, QirError> {
+    lower_calculator_expression(expression, fragment, context).map_err(calculator_error)
+}
+
+/// Implements [lower_expression] with the errors of qoqo_calculator.
+fn lower_calculator_expression(
+    expression: &str,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<Value, CalculatorError> {
+    let mut lowering = ExpressionLowering {
+        tokens: tokenize(expression)?.into_iter(),
+        current_token: Token::End,
+        fragment,
+        context,
+    };
+    lowering.next_token();
+    let value = lowering.lower_sum()?;
+    match lowering.current_token {
+        Token::End => Ok(value),
+        _ => Err(CalculatorError::ParsingError {
+            msg: "Expected end of expression",
+        }),
+    }
+}
+
+/// Recursive descent parser emitting the instructions of an expression while parsing it.
+struct ExpressionLowering<'a> {
+    tokens: std::vec::IntoIter<Token>,
+    current_token: Token,
+    fragment: &'a mut Fragment,
+    context: &'a mut TranslationContext,
+}
+
+impl ExpressionLowering<'_> {
+    fn next_token(&mut self) {
+        self.current_token = self.tokens.next().unwrap_or(Token::End);
+    }
+
+    /// Lowers sums and differences, the operations with the lowest precedence.
+    fn lower_sum(&mut self) -> Result<Value, CalculatorError> {
+        let mut value = self.lower_product()?;
+        loop {
+            let operator = match self.current_token {
+                Token::Plus => BinaryOperator::FAdd,
+                Token::Minus => BinaryOperator::FSub,
+                _ => return Ok(value),
+            };
+            self.next_token();
+            let rhs = self.lower_product()?;
+            value = self.binary(operator, value, rhs)?;
+        }
+    }
+
+    /// Lowers products and quotients.
+    fn lower_product(&mut self) -> Result<Value, CalculatorError> {
+        let mut value = self.lower_power()?;
+        loop {
+            let operator = match self.current_token {
+                Token::Multiply => BinaryOperator::FMul,
+                Token::Divide => BinaryOperator::FDiv,
+                _ => return Ok(value),
+            };
+            self.next_token();
+            let rhs = self.lower_power()?;
+            value = self.binary(operator, value, rhs)?;
+        }
+    }
+
+    /// Lowers a single, not chained power.
+    fn lower_power(&mut self) -> Result<Value, CalculatorError> {
+        let base = self.lower_unary()?;
+        match self.current_token {
+            Token::Power => {
+                self.next_token();
+                let exponent = self.lower_unary()?;
+                self.function("pow", vec![base, exponent])
+            }
+            _ => Ok(base),
+        }
+    }
+
+    /// Lowers an operand with optional signs.
+    ///
+    /// Unlike qoqo_calculator, repeated signs such as `--pi` are accepted, as they are created
+    /// when negating a negative CalculatorFloat.
+    fn lower_unary(&mut self) -> Result<Value, CalculatorError> {
+        match self.current_token {
+            Token::Minus => {
+                self.next_token();
+                let operand = self.lower_unary()?;
+                Ok(float_negation(operand, self.fragment, self.context))
+            }
+            Token::Plus => {
+                self.next_token();
+                self.lower_unary()
+            }
+            _ => self.lower_operand(),
+        }
+    }
+
+    /// Lowers numbers, variables, function calls and bracketed expressions.
+    fn lower_operand(&mut self) -> Result<Value, CalculatorError> {
+        match self.current_token.clone() {
+            Token::Number(value) => {
+                self.next_token();
+                Ok(Value::Double(value))
+            }
+            Token::Variable(name) => {
+                self.next_token();
+                Ok(match name.as_str() {
+                    "pi" => Value::Double(PI),
+                    _ => Value::local(name, Type::Double),
+                })
+            }
+            Token::BracketOpen => {
+                self.next_token();
+                let value = self.lower_sum()?;
+                self.expect_bracket_close()?;
+                Ok(value)
+            }
+            Token::Function(name) => {
+                self.next_token();
+                let mut arguments = vec![self.lower_sum()?];
+                while self.current_token == Token::Comma {
+                    self.next_token();
+                    arguments.push(self.lower_sum()?);
+                }
+                self.expect_bracket_close()?;
+                self.function(&name, arguments)
+            }
+            _ => Err(CalculatorError::ParsingError {
+                msg: "Bad_Position",
+            }),
+        }
+    }
+
+    fn expect_bracket_close(&mut self) -> Result<(), CalculatorError> {
+        if self.current_token == Token::BracketClose {
+            self.next_token();
+            Ok(())
+        } else {
+            Err(CalculatorError::ParsingError {
+                msg: "Expected Braket close",
+            })
+        }
+    }
+
+    fn binary(
+        &mut self,
+        operator: BinaryOperator,
+        lhs: Value,
+        rhs: Value,
+    ) -> Result<Value, CalculatorError> {
+        emit_float_operation(operator, lhs, rhs, self.fragment, self.context)
+    }
+
+    /// Emits a call to a function returning a `double`.
+    fn call(&mut self, callee: &str, arguments: Vec<Value>) -> Value {
+        let result = self.context.new_value();
+        self.fragment.push(Instruction::Call {
+            result: Some(result.clone()),
+            return_type: Type::Double,
+            callee: callee.to_owned(),
+            arguments,
+            attribute_group: None,
+        });
+        Value::local(result, Type::Double)
+    }
+
+    /// Emits a floating point comparison.
+    fn compare(&mut self, predicate: FloatPredicate, lhs: Value, rhs: Value) -> Value {
+        let result = self.context.new_value();
+        self.fragment.push(Instruction::FloatCompare {
+            result: result.clone(),
+            predicate,
+            lhs,
+            rhs,
+        });
+        Value::local(result, Type::Integer(1))
+    }
+
+    /// Emits a selection between two constants.
+    fn select(&mut self, condition: Value, true_value: Value, false_value: Value) -> Value {
+        let result = self.context.new_value();
+        self.fragment.push(Instruction::Select {
+            result: result.clone(),
+            condition,
+            true_value,
+            false_value,
+        });
+        Value::local(result, Type::Double)
+    }
+
+    /// Emits a qoqo_calculator function, or evaluates it if all arguments are constant.
+    fn function(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, CalculatorError> {
+        let constants: Vec<f64> = arguments
+            .iter()
+            .filter_map(|argument| match argument {
+                Value::Double(value) => Some(*value),
+                _ => None,
+            })
+            .collect();
+        if constants.len() == arguments.len() {
+            return evaluate_function(name, &constants).map(Value::Double);
+        }
+        match (name, arguments.as_slice()) {
+            ("fract", [x]) => {
+                let truncated = self.call("llvm.trunc.f64", vec![x.clone()]);
+                self.binary(BinaryOperator::FSub, x.clone(), truncated)
+            }
+            ("sign", [x]) => {
+                Ok(self.call("llvm.copysign.f64", vec![Value::Double(1.0), x.clone()]))
+            }
+            ("delta", [x]) => {
+                let absolute = self.call("llvm.fabs.f64", vec![x.clone()]);
+                let is_zero =
+                    self.compare(FloatPredicate::Olt, absolute, Value::Double(f64::EPSILON));
+                Ok(self.select(is_zero, Value::Double(1.0), Value::Double(0.0)))
+            }
+            ("theta", [x]) => {
+                let is_zero = self.compare(FloatPredicate::Oeq, x.clone(), Value::Double(0.0));
+                let is_negative = self.compare(FloatPredicate::Olt, x.clone(), Value::Double(0.0));
+                let step = self.select(is_negative, Value::Double(0.0), Value::Double(1.0));
+                Ok(self.select(is_zero, Value::Double(0.5), step))
+            }
+            _ => match MATH_FUNCTIONS
+                .iter()
+                .find(|(function, _, _)| *function == name)
+            {
+                Some((_, callee, number_arguments)) if *number_arguments == arguments.len() => {
+                    Ok(self.call(callee, arguments))
+                }
+                Some(_) => Err(CalculatorError::NotEnoughFunctionArguments),
+                None => Err(CalculatorError::FunctionNotFound {
+                    fct: name.to_owned(),
+                }),
+            },
+        }
+    }
+}
+
+/// Evaluates a function with constant arguments with qoqo_calculator.
+fn evaluate_function(name: &str, arguments: &[f64]) -> Result<f64, CalculatorError> {
+    let mut calculator = Calculator::new();
+    let mut names: Vec<String> = vec![];
+    for (index, argument) in arguments.iter().enumerate() {
+        names.push(format!("x{}", index));
+        calculator.set_variable(&names[index], *argument);
+    }
+    calculator.parse_str(&format!("{}({})", name, names.join(", ")))
+}
@@ -10,14 +10,51 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
-use lazy_static::lazy_static;
-use qoqo_calculator::{CalculatorError, CalculatorFloat};
-use roqoqo::{operations::*, Circuit, RoqoqoBackendError};
-use std::{f64::consts::FRAC_PI_2, sync::Mutex};
+use qoqo_calculator::CalculatorFloat;
+use roqoqo::{operations::*, Circuit};
+use std::f64::consts::{FRAC_PI_2, PI};
 
-lazy_static! {
-    pub static ref NUMBER_LABEL: Mutex<u32> = Mutex::new(0);
-    pub static ref NUMBER_VARS: Mutex<u32> = Mutex::new(0);
+use crate::ir::{
+    BinaryOperator, Fragment, Function, Instruction, IntegerPredicate, Parameter, Type, Value,
+};
+use crate::{
+    float_negation, float_operation, is_synthesizable, lower_expression, synthesize_gate, QirError,
+};
+
+/// Numbering of the local values and labels of the function operations are translated into.
+///
+/// Numbered values and labels restart in every function: the entry point and every gate
+/// definition are translated with a context of their own. Translations do not share any state
+/// and can run in parallel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TranslationContext {
+    number_vars: u32,
+    number_label: u32,
+}
+
+impl TranslationContext {
+    /// Creates the context of a new function, numbering starts at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the name of a new numbered local value.
+    pub fn new_value(&mut self) -> String {
+        self.reserve_values(1).to_string()
+    }
+
+    /// Reserves `count` consecutive numbered local values and returns the first of them.
+    pub fn reserve_values(&mut self, count: u32) -> u32 {
+        let first = self.number_vars;
+        self.number_vars += count;
+        first
+    }
+
+    /// Reserves the number of the labels of a new conditional or loop.
+    pub fn new_label(&mut self) -> u32 {
+        self.number_label += 1;
+        self.number_label - 1
+    }
 }
 
 pub(crate) const NO_CALL_OPERATIONS: &[&str; 6] = &[
@@ -29,7 +66,7 @@ pub(crate) const NO_CALL_OPERATIONS: &[&str; 6] = &[
     "Identity",
 ];
 
-pub(crate) const NO_DECLARATION_OPERATIONS: &[&str; 30] = &[
+pub(crate) const NO_DECLARATION_OPERATIONS: &[&str; 49] = &[
     "Identity",
     "CallDefinedGate",
     "DefinitionFloat",
@@ -60,44 +97,429 @@ pub(crate) const NO_DECLARATION_OPERATIONS: &[&str; 30] = &[
     "RotateXY",
     "ControlledControlledPauliZ",
     "ControlledControlledPhaseShift",
+    "SqrtPauliY",
+    "InvSqrtPauliY",
+    "PhaseShiftState0",
+    "RotateAroundSphericalAxis",
+    "SingleQubitGate",
+    "GPi",
+    "GPi2",
+    "SpinInteraction",
+    "Qsim",
+    "Fsim",
+    "Bogoliubov",
+    "ComplexPMInteraction",
+    "ControlledRotateX",
+    "ControlledRotateXY",
+    "EchoCrossResonance",
+    "ControlledSWAP",
+    "TripleControlledPauliX",
+    "TripleControlledPauliZ",
+    "TripleControlledPhaseShift",
 ];
 
-pub(crate) fn format_arg(arg: &usize, arg_type: &str) -> String {
-    format!("inttoptr (i64 {} to %{}*)", arg, arg_type)
+/// Syntax of the pointer types in the emitted QIR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerStyle {
+    /// Typed pointers such as `%Qubit*`, used by QIR versions before 2.0.
+    #[default]
+    Typed,
+    /// Opaque `ptr` pointers, as required by LLVM 15 and newer.
+    Opaque,
 }
 
-fn format_calculator(calculator: &CalculatorFloat) -> String {
+/// Converts a CalculatorFloat to a `double` operand.
+///
+/// Symbolic expressions are lowered with [lower_expression], the instructions computing them
+/// are appended to `fragment`.
+fn calculator_value(
+    calculator: &CalculatorFloat,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<Value, QirError> {
     match calculator {
-        CalculatorFloat::Float(float_value) => {
-            if float_value.fract() == 0.0 {
-                format!("{:.1}", float_value)
-            } else {
-                format!("{}", float_value)
+        CalculatorFloat::Float(float_value) => Ok(Value::Double(*float_value)),
+        CalculatorFloat::Str(expression) => lower_expression(expression, fragment, context),
+    }
+}
+
+/// Multiplies a `double` operand by a constant factor, as needed for derived gate arguments.
+fn scaled(
+    value: Value,
+    factor: f64,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<Value, QirError> {
+    float_operation(
+        BinaryOperator::FMul,
+        value,
+        Value::Double(factor),
+        fragment,
+        context,
+    )
+}
+
+/// Creates a call with `double` arguments computed from CalculatorFloats followed by qubits.
+fn parametrized_call(
+    callee: &str,
+    parameters: &[&CalculatorFloat],
+    qubits: &[usize],
+    context: &mut TranslationContext,
+) -> Result<Fragment, QirError> {
+    let mut fragment = Fragment::default();
+    let mut arguments: Vec<Value> = vec![];
+    for parameter in parameters {
+        arguments.push(calculator_value(parameter, &mut fragment, context)?);
+    }
+    arguments.extend(qubits.iter().map(|qubit| Value::Qubit(*qubit)));
+    fragment.push(Instruction::call(callee, arguments));
+    Ok(fragment)
+}
+
+/// Creates a call to a function returning void.
+fn call(callee: &str, arguments: Vec<Value>) -> Fragment {
+    Fragment::from(Instruction::call(callee, arguments))
+}
+
+/// Creates a call to an irreversible function, using attribute group #1.
+fn irreversible_call(callee: &str, arguments: Vec<Value>) -> Fragment {
+    Fragment::from(Instruction::Call {
+        result: None,
+        return_type: Type::Void,
+        callee: callee.to_owned(),
+        arguments,
+        attribute_group: Some(1),
+    })
+}
+
+/// Adds the rotation `exp(-i theta/2 P_0 P_1)` to the circuit of a two-qubit helper gate.
+///
+/// The Pauli axes `'x'`, `'y'` or `'z'` of qubits 0 and 1 are rotated onto the z axis,
+/// where the rotation is a CNOT conjugated rz on qubit 1.
+fn add_pauli_rotation(circ: &mut Circuit, axes: [char; 2], theta: CalculatorFloat) {
+    for (qubit, axis) in axes.iter().enumerate() {
+        match axis {
+            'x' => circ.add_operation(RotateY::new(qubit, -CalculatorFloat::FRAC_PI_2)),
+            'y' => circ.add_operation(RotateX::new(qubit, -CalculatorFloat::FRAC_PI_2)),
+            _ => (),
+        }
+    }
+    circ.add_operation(CNOT::new(0, 1));
+    circ.add_operation(RotateZ::new(1, theta));
+    circ.add_operation(CNOT::new(0, 1));
+    for (qubit, axis) in axes.iter().enumerate() {
+        match axis {
+            'x' => circ.add_operation(RotateY::new(qubit, CalculatorFloat::FRAC_PI_2)),
+            'y' => circ.add_operation(RotateX::new(qubit, CalculatorFloat::FRAC_PI_2)),
+            _ => (),
+        }
+    }
+}
+
+/// Adds the rotation of qubit 1 around the x axis controlled by qubit 0 to a helper gate circuit.
+fn add_controlled_rotate_x(circ: &mut Circuit) {
+    circ.add_operation(RotateY::new(1, -CalculatorFloat::FRAC_PI_2));
+    circ.add_operation(RotateZ::new(1, CalculatorFloat::from("half_theta")));
+    circ.add_operation(CNOT::new(0, 1));
+    circ.add_operation(RotateZ::new(1, CalculatorFloat::from("minus_half_theta")));
+    circ.add_operation(CNOT::new(0, 1));
+    circ.add_operation(RotateY::new(1, CalculatorFloat::FRAC_PI_2));
+}
+
+/// Creates a call to the `spinint` or `qsim` helper gate with the doubled coefficients as arguments.
+fn spin_interaction_call(
+    callee: &str,
+    coefficients: [&CalculatorFloat; 3],
+    qubits: [usize; 2],
+    context: &mut TranslationContext,
+) -> Result<Fragment, QirError> {
+    let mut fragment = Fragment::default();
+    let mut arguments: Vec<Value> = vec![];
+    for coefficient in coefficients {
+        let value = calculator_value(coefficient, &mut fragment, context)?;
+        arguments.push(scaled(value, 2.0, &mut fragment, context)?);
+    }
+    arguments.extend(qubits.iter().map(|qubit| Value::Qubit(*qubit)));
+    fragment.push(Instruction::call(callee, arguments));
+    Ok(fragment)
+}
+
+/// Returns the absolute value and the argument of a complex number given by its real and imaginary parts.
+fn polar_values(
+    real: &CalculatorFloat,
+    imaginary: &CalculatorFloat,
+    fragment: &mut Fragment,
+    context: &mut TranslationContext,
+) -> Result<(Value, Value), QirError> {
+    let absolute = (real.clone() * real + imaginary.clone() * imaginary).sqrt();
+    let argument = imaginary.atan2(real);
+    Ok((
+        calculator_value(&absolute, fragment, context)?,
+        calculator_value(&argument, fragment, context)?,
+    ))
+}
+
+/// Adds the rotation `exp(-i theta/2 Z...Z)` on the given qubits as a CNOT ladder around an rz.
+fn add_zz_ladder(circ: &mut Circuit, qubits: &[usize], theta: CalculatorFloat) {
+    let Some(last) = qubits.last() else {
+        return;
+    };
+    for pair in qubits.windows(2) {
+        circ.add_operation(CNOT::new(pair[0], pair[1]));
+    }
+    circ.add_operation(RotateZ::new(*last, theta));
+    for pair in qubits.windows(2).rev() {
+        circ.add_operation(CNOT::new(pair[0], pair[1]));
+    }
+}
+
+/// Adds the phase `theta x_0 x_1 ... x_n` of a controlled phase on all given qubits to a circuit.
+///
+/// The product of the qubit values is expanded into the parities of all subsets of the qubits,
+/// `x_0 ... x_n = 2^-n sum_S (-1)^(|S| - 1) parity(S)`. Each parity is rotated with `angle`
+/// (`theta / 2^n`) for subsets of odd size and with `minus_angle` for subsets of even size,
+/// which is exact up to a global phase.
+fn add_parity_phases(
+    circ: &mut Circuit,
+    qubits: &[usize],
+    angle: CalculatorFloat,
+    minus_angle: CalculatorFloat,
+) {
+    for subset in 1_usize..(1 << qubits.len()) {
+        let parity_qubits: Vec<usize> = qubits
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| (subset >> index) & 1 == 1)
+            .map(|(_, qubit)| *qubit)
+            .collect();
+        let parity_angle = if parity_qubits.len() % 2 == 1 {
+            angle.clone()
+        } else {
+            minus_angle.clone()
+        };
+        add_zz_ladder(circ, &parity_qubits, parity_angle);
+    }
+}
+
+/// Returns an error for a multi-qubit operation without any qubit.
+fn check_qubit_count(operation: &Operation) -> Result<(), QirError> {
+    let number_qubits = match operation {
+        Operation::MultiQubitZZ(op) => op.qubits().len(),
+        Operation::MultiQubitMS(op) => op.qubits().len(),
+        Operation::MultiQubitCNOT(op) => op.qubits().len(),
+        _ => return Ok(()),
+    };
+    if number_qubits == 0 {
+        return Err(QirError::InvalidQubitCount {
+            index: 0,
+            hqslang: operation.hqslang(),
+            msg: "At least one qubit is needed".to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the decomposition of a multi-qubit operation into single-qubit rotations and CNOTs.
+///
+/// MultiQubitZZ on two qubits is not decomposed as it is translated to `__quantum__qis__rzz__body`.
+/// MultiQubitCNOT with more than two controls is a controlled Z on all qubits, conjugated with
+/// Hadamard gates on the target.
+fn multi_qubit_decomposition(operation: &Operation) -> Option<Circuit> {
+    let mut circ = Circuit::new();
+    match operation {
+        Operation::MultiQubitZZ(op) if op.qubits().len() != 2 => {
+            add_zz_ladder(&mut circ, op.qubits(), op.theta().clone());
+        }
+        Operation::MultiQubitMS(op) => {
+            for qubit in op.qubits() {
+                circ.add_operation(RotateY::new(*qubit, -CalculatorFloat::FRAC_PI_2));
+            }
+            add_zz_ladder(&mut circ, op.qubits(), op.theta().clone());
+            for qubit in op.qubits() {
+                circ.add_operation(RotateY::new(*qubit, CalculatorFloat::FRAC_PI_2));
             }
         }
-        CalculatorFloat::Str(str_value) => match str_value.as_str() {
-            "pi" => std::f64::consts::PI.to_string(),
-            "-pi" => format!("-{}", std::f64::consts::PI),
-            "pi/2" => std::f64::consts::FRAC_PI_2.to_string(),
-            "pi/4" => std::f64::consts::FRAC_PI_4.to_string(),
-            "-pi/2" => format!("-{}", std::f64::consts::FRAC_PI_2),
-            "-pi/4" => format!("-{}", std::f64::consts::FRAC_PI_4),
-            _ => format!("%{}", str_value),
+        Operation::MultiQubitCNOT(op) => match op.qubits().as_slice() {
+            [] => (),
+            [target] => circ.add_operation(PauliX::new(*target)),
+            [control, target] => circ.add_operation(CNOT::new(*control, *target)),
+            [control_0, control_1, target] => {
+                circ.add_operation(Toffoli::new(*control_0, *control_1, *target))
+            }
+            qubits => {
+                let target = qubits[qubits.len() - 1];
+                let angle = PI / 2_f64.powi(qubits.len() as i32 - 1);
+                circ.add_operation(Hadamard::new(target));
+                add_parity_phases(
+                    &mut circ,
+                    qubits,
+                    CalculatorFloat::from(angle),
+                    CalculatorFloat::from(-angle),
+                );
+                circ.add_operation(Hadamard::new(target));
+            }
         },
+        _ => return None,
+    }
+    Some(circ)
+}
+
+/// Returns the definition of the `rxy` helper gate, a rotation around an axis in the x-y plane.
+fn rotate_xy_definition() -> Operation {
+    let mut circ = Circuit::new();
+    circ.add_operation(RotateZ::new(0, CalculatorFloat::from("minus_phi")));
+    circ.add_operation(RotateX::new(0, CalculatorFloat::from("theta")));
+    circ.add_operation(RotateZ::new(0, CalculatorFloat::from("phi")));
+    Operation::from(GateDefinition::new(
+        circ,
+        "rxy".to_owned(),
+        vec![0],
+        vec!["theta".to_owned(), "phi".to_owned(), "minus_phi".to_owned()],
+    ))
+}
+
+/// Creates the rz, ry, rz calls of the ZYZ decomposition of a SingleQubitGate.
+///
+/// The gate `[[alpha, -conj(beta)], [beta, conj(alpha)]]` is `rz(a) ry(b) rz(c)` up to a global
+/// phase, with `b = 2 atan2(|beta|, |alpha|)`, `a = arg(beta) - arg(alpha)` and
+/// `c = -arg(alpha) - arg(beta)`.
+fn single_qubit_gate_calls(
+    op: &SingleQubitGate,
+    context: &mut TranslationContext,
+) -> Result<Fragment, QirError> {
+    let alpha_r = op.alpha_r();
+    let alpha_i = op.alpha_i();
+    let beta_r = op.beta_r();
+    let beta_i = op.beta_i();
+    let abs_alpha = (alpha_r.clone() * alpha_r.clone() + alpha_i.clone() * alpha_i.clone()).sqrt();
+    let abs_beta = (beta_r.clone() * beta_r.clone() + beta_i.clone() * beta_i.clone()).sqrt();
+    let arg_alpha = alpha_i.atan2(alpha_r);
+    let arg_beta = beta_i.atan2(beta_r);
+    let theta_y = abs_beta.atan2(abs_alpha) * 2.0;
+    let theta_z_first = -arg_alpha.clone() - arg_beta.clone();
+    let theta_z_last = arg_beta - arg_alpha;
+    let qubit = [*op.qubit()];
+    let mut fragment = parametrized_call(
+        "__quantum__qis__rz__body",
+        &[&theta_z_first],
+        &qubit,
+        context,
+    )?;
+    fragment.append(parametrized_call(
+        "__quantum__qis__ry__body",
+        &[&theta_y],
+        &qubit,
+        context,
+    )?);
+    fragment.append(parametrized_call(
+        "__quantum__qis__rz__body",
+        &[&theta_z_last],
+        &qubit,
+        context,
+    )?);
+    Ok(fragment)
+}
+
+/// Checks that the qubits of a GateDefinition are distinct and cover all qubits of its circuit.
+///
+/// A CallDefinedGate replaces the qubits of the definition in the order they are listed, every
+/// qubit used by the circuit therefore needs to be one of them.
+fn check_definition_qubits(gate_definition: &GateDefinition) -> Result<(), QirError> {
+    let qubits = gate_definition.qubits();
+    for (position, qubit) in qubits.iter().enumerate() {
+        if qubits[..position].contains(qubit) {
+            return Err(QirError::InvalidDefinition {
+                index: 0,
+                hqslang: "GateDefinition",
+                msg: format!(
+                    "Qubit {} is listed more than once in the qubits of gate definition '{}'",
+                    qubit,
+                    gate_definition.name()
+                ),
+            });
+        }
+    }
+    // Nested GateDefinitions only define gates and do not act on any qubit
+    for operation in gate_definition
+        .circuit()
+        .iter()
+        .filter(|operation| !matches!(operation, Operation::GateDefinition(_)))
+    {
+        if let InvolvedQubits::Set(involved_qubits) = operation.involved_qubits() {
+            if let Some(qubit) = involved_qubits
+                .into_iter()
+                .filter(|qubit| !qubits.contains(qubit))
+                .min()
+            {
+                return Err(QirError::InvalidDefinition {
+                    index: 0,
+                    hqslang: "GateDefinition",
+                    msg: format!(
+                        "Qubit {} is used by {} in gate definition '{}' but is not one of its qubits",
+                        qubit,
+                        operation.hqslang(),
+                        gate_definition.name()
+                    ),
+                });
+            }
+        }
     }
+    Ok(())
 }
 
-pub fn pre_process_circuit(circuit: &Circuit) -> Result<Circuit, RoqoqoBackendError> {
+/// Checks that the free parameters of a GateDefinition can be arguments of its function.
+///
+/// The arguments share the local names of the function with the qubit arguments and the labels
+/// of its basic blocks, none of which a parameter may repeat.
+fn check_definition_parameters(
+    gate_definition: &GateDefinition,
+    function: &Function,
+) -> Result<(), QirError> {
+    let free_parameters = gate_definition.free_parameters();
+    for (position, parameter) in free_parameters.iter().enumerate() {
+        let collision = if free_parameters[..position].contains(parameter) {
+            Some("another free parameter")
+        } else if gate_definition
+            .qubits()
+            .iter()
+            .any(|index| *parameter == format!("qubit{}", index))
+        {
+            Some("the argument of a qubit")
+        } else if function
+            .blocks
+            .iter()
+            .any(|block| block.label == *parameter)
+        {
+            Some("the label of a basic block")
+        } else {
+            None
+        };
+        if let Some(collision) = collision {
+            return Err(QirError::InvalidDefinition {
+                index: 0,
+                hqslang: "GateDefinition",
+                msg: format!(
+                    "Free parameter '{}' of gate definition '{}' has the name of {}",
+                    parameter,
+                    gate_definition.name(),
+                    collision
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn pre_process_circuit(circuit: &Circuit) -> Result<Circuit, QirError> {
     let mut new_circuit = Circuit::new();
-    for operation in circuit.iter() {
+    for (index, operation) in circuit.iter().enumerate() {
+        check_qubit_count(operation).map_err(|error| error.locate(index, operation))?;
         match operation {
             Operation::XY(_) => {
+                // XY(theta) = exp(-i theta/2 XX) exp(-i theta/2 YY) called with -theta/2
                 let mut circ = Circuit::new();
-                circ.add_operation(RotateX::new(0, CalculatorFloat::FRAC_PI_2));
-                circ.add_operation(CNOT::new(0, 1));
-                circ.add_operation(RotateX::new(1, CalculatorFloat::from("theta")));
-                circ.add_operation(CNOT::new(0, 1));
-                circ.add_operation(RotateX::new(0, -CalculatorFloat::FRAC_PI_2));
+                add_pauli_rotation(&mut circ, ['x', 'x'], CalculatorFloat::from("theta"));
+                add_pauli_rotation(&mut circ, ['y', 'y'], CalculatorFloat::from("theta"));
                 new_circuit.add_operation(Operation::from(GateDefinition::new(
                     circ,
                     "xy".to_owned(),
@@ -278,18 +700,27 @@ pub fn pre_process_circuit(circuit: &Circuit) -> Result<Circuit, RoqoqoBackendEr
                 new_circuit.add_operation(operation.clone());
             }
             Operation::MolmerSorensenXX(_) | Operation::VariableMSXX(_) => {
+                // The rotation exp(-i theta/2 XX), MolmerSorensenXX has the angle pi/2
                 let mut circ = Circuit::new();
-                circ.add_operation(RotateX::new(0, CalculatorFloat::from("half_theta")));
-                circ.add_operation(RotateX::new(1, CalculatorFloat::from("half_theta")));
-                circ.add_operation(CNOT::new(0, 1));
-                circ.add_operation(RotateX::new(0, CalculatorFloat::from("minus_half_theta")));
-                circ.add_operation(RotateX::new(1, CalculatorFloat::from("minus_half_theta")));
-                circ.add_operation(CNOT::new(0, 1));
+                add_pauli_rotation(&mut circ, ['x', 'x'], CalculatorFloat::from("theta"));
                 new_circuit.add_operation(Operation::from(GateDefinition::new(
                     circ,
                     "rxx".to_owned(),
                     vec![0, 1],
-                    vec!["half_theta".to_owned(), "minus_half_theta".to_owned()],
+                    vec!["theta".to_owned()],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::ControlledPauliY(_) => {
+                let mut circ = Circuit::new();
+                circ.add_operation(InvSGate::new(1));
+                circ.add_operation(CNOT::new(0, 1));
+                circ.add_operation(SGate::new(1));
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "cy".to_owned(),
+                    vec![0, 1],
+                    vec![],
                 )));
                 new_circuit.add_operation(operation.clone());
             }
@@ -308,16 +739,34 @@ pub fn pre_process_circuit(circuit: &Circuit) -> Result<Circuit, RoqoqoBackendEr
                 )));
                 new_circuit.add_operation(operation.clone());
             }
-            Operation::RotateXY(_) => {
+            Operation::RotateXY(_) | Operation::GPi2(_) => {
+                new_circuit.add_operation(rotate_xy_definition());
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::RotateAroundSphericalAxis(_) => {
                 let mut circ = Circuit::new();
-                circ.add_operation(RotateZ::new(0, CalculatorFloat::from("minus_phi")));
-                circ.add_operation(RotateX::new(0, CalculatorFloat::from("theta")));
-                circ.add_operation(RotateZ::new(0, CalculatorFloat::from("phi")));
+                circ.add_operation(RotateZ::new(
+                    0,
+                    CalculatorFloat::from("minus_spherical_phi"),
+                ));
+                circ.add_operation(RotateY::new(
+                    0,
+                    CalculatorFloat::from("minus_spherical_theta"),
+                ));
+                circ.add_operation(RotateZ::new(0, CalculatorFloat::from("theta")));
+                circ.add_operation(RotateY::new(0, CalculatorFloat::from("spherical_theta")));
+                circ.add_operation(RotateZ::new(0, CalculatorFloat::from("spherical_phi")));
                 new_circuit.add_operation(Operation::from(GateDefinition::new(
                     circ,
-                    "rxy".to_owned(),
+                    "rsph".to_owned(),
                     vec![0],
-                    vec!["theta".to_owned(), "phi".to_owned(), "minus_phi".to_owned()],
+                    vec![
+                        "theta".to_owned(),
+                        "spherical_theta".to_owned(),
+                        "minus_spherical_theta".to_owned(),
+                        "spherical_phi".to_owned(),
+                        "minus_spherical_phi".to_owned(),
+                    ],
                 )));
                 new_circuit.add_operation(operation.clone());
             }
@@ -375,419 +824,866 @@ pub fn pre_process_circuit(circuit: &Circuit) -> Result<Circuit, RoqoqoBackendEr
                 )));
                 new_circuit.add_operation(operation.clone());
             }
+            Operation::SpinInteraction(_) | Operation::Qsim(_) => {
+                let mut circ = Circuit::new();
+                add_pauli_rotation(&mut circ, ['x', 'x'], CalculatorFloat::from("x_angle"));
+                add_pauli_rotation(&mut circ, ['y', 'y'], CalculatorFloat::from("y_angle"));
+                add_pauli_rotation(&mut circ, ['z', 'z'], CalculatorFloat::from("z_angle"));
+                let name = if let Operation::Qsim(_) = operation {
+                    circ.add_operation(CNOT::new(0, 1));
+                    circ.add_operation(CNOT::new(1, 0));
+                    circ.add_operation(CNOT::new(0, 1));
+                    "qsim"
+                } else {
+                    "spinint"
+                };
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    name.to_owned(),
+                    vec![0, 1],
+                    vec![
+                        "x_angle".to_owned(),
+                        "y_angle".to_owned(),
+                        "z_angle".to_owned(),
+                    ],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::Fsim(_) => {
+                let mut circ = Circuit::new();
+                add_pauli_rotation(
+                    &mut circ,
+                    ['x', 'x'],
+                    CalculatorFloat::from("t_minus_delta"),
+                );
+                add_pauli_rotation(&mut circ, ['y', 'y'], CalculatorFloat::from("t_plus_delta"));
+                circ.add_operation(CNOT::new(0, 1));
+                circ.add_operation(CNOT::new(1, 0));
+                circ.add_operation(CNOT::new(0, 1));
+                circ.add_operation(RotateZ::new(0, CalculatorFloat::from("half_phase")));
+                circ.add_operation(CNOT::new(0, 1));
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("minus_half_phase")));
+                circ.add_operation(CNOT::new(0, 1));
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("half_phase")));
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "fsim".to_owned(),
+                    vec![0, 1],
+                    vec![
+                        "t_minus_delta".to_owned(),
+                        "t_plus_delta".to_owned(),
+                        "half_phase".to_owned(),
+                        "minus_half_phase".to_owned(),
+                    ],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::Bogoliubov(_) => {
+                let mut circ = Circuit::new();
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("phi")));
+                add_pauli_rotation(
+                    &mut circ,
+                    ['x', 'x'],
+                    CalculatorFloat::from("minus_abs_delta"),
+                );
+                add_pauli_rotation(&mut circ, ['y', 'y'], CalculatorFloat::from("abs_delta"));
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("minus_phi")));
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "bogoliubov".to_owned(),
+                    vec![0, 1],
+                    vec![
+                        "abs_delta".to_owned(),
+                        "minus_abs_delta".to_owned(),
+                        "phi".to_owned(),
+                        "minus_phi".to_owned(),
+                    ],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::ComplexPMInteraction(_) => {
+                let mut circ = Circuit::new();
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("phi")));
+                add_pauli_rotation(&mut circ, ['x', 'x'], CalculatorFloat::from("abs_t"));
+                add_pauli_rotation(&mut circ, ['y', 'y'], CalculatorFloat::from("abs_t"));
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("minus_phi")));
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "cpmint".to_owned(),
+                    vec![0, 1],
+                    vec!["abs_t".to_owned(), "phi".to_owned(), "minus_phi".to_owned()],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::ControlledRotateX(_) => {
+                let mut circ = Circuit::new();
+                add_controlled_rotate_x(&mut circ);
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "crx".to_owned(),
+                    vec![0, 1],
+                    vec!["half_theta".to_owned(), "minus_half_theta".to_owned()],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::ControlledRotateXY(_) => {
+                let mut circ = Circuit::new();
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("minus_phi")));
+                add_controlled_rotate_x(&mut circ);
+                circ.add_operation(RotateZ::new(1, CalculatorFloat::from("phi")));
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "crxy".to_owned(),
+                    vec![0, 1],
+                    vec![
+                        "half_theta".to_owned(),
+                        "minus_half_theta".to_owned(),
+                        "phi".to_owned(),
+                        "minus_phi".to_owned(),
+                    ],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::EchoCrossResonance(_) => {
+                let mut circ = Circuit::new();
+                // ECR = exp(i pi/4 Z_0 X_1) X_0 up to a global phase
+                circ.add_operation(PauliX::new(0));
+                add_pauli_rotation(&mut circ, ['z', 'x'], -CalculatorFloat::FRAC_PI_2);
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "ecr".to_owned(),
+                    vec![0, 1],
+                    vec![],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::ControlledSWAP(_) => {
+                let mut circ = Circuit::new();
+                circ.add_operation(CNOT::new(2, 1));
+                circ.add_operation(Toffoli::new(0, 1, 2));
+                circ.add_operation(CNOT::new(2, 1));
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "cswap".to_owned(),
+                    vec![0, 1, 2],
+                    vec![],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::TripleControlledPauliX(_) | Operation::TripleControlledPauliZ(_) => {
+                let is_pauli_x = matches!(operation, Operation::TripleControlledPauliX(_));
+                let mut circ = Circuit::new();
+                if is_pauli_x {
+                    circ.add_operation(Hadamard::new(3));
+                }
+                add_parity_phases(
+                    &mut circ,
+                    &[0, 1, 2, 3],
+                    CalculatorFloat::from(PI / 8.0),
+                    CalculatorFloat::from(-PI / 8.0),
+                );
+                if is_pauli_x {
+                    circ.add_operation(Hadamard::new(3));
+                }
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    if is_pauli_x { "c3x" } else { "c3z" }.to_owned(),
+                    vec![0, 1, 2, 3],
+                    vec![],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::TripleControlledPhaseShift(_) => {
+                let mut circ = Circuit::new();
+                add_parity_phases(
+                    &mut circ,
+                    &[0, 1, 2, 3],
+                    CalculatorFloat::from("frac_theta_8"),
+                    CalculatorFloat::from("minus_frac_theta_8"),
+                );
+                new_circuit.add_operation(Operation::from(GateDefinition::new(
+                    circ,
+                    "c3p".to_owned(),
+                    vec![0, 1, 2, 3],
+                    vec!["frac_theta_8".to_owned(), "minus_frac_theta_8".to_owned()],
+                )));
+                new_circuit.add_operation(operation.clone());
+            }
+            Operation::MultiQubitZZ(_)
+            | Operation::MultiQubitMS(_)
+            | Operation::MultiQubitCNOT(_) => match multi_qubit_decomposition(operation) {
+                Some(decomposition) => {
+                    for decomposed_operation in decomposition {
+                        new_circuit.add_operation(decomposed_operation);
+                    }
+                }
+                None => new_circuit.add_operation(operation.clone()),
+            },
+            _ if is_synthesizable(operation)
+                && matches!(
+                    gate_declaration(operation),
+                    Err(QirError::UnsupportedOperation { .. })
+                ) =>
+            {
+                let synthesized_circuit = synthesize_gate(operation)
+                    .map_err(|error| QirError::from(error).locate(index, operation))?;
+                for synthesized_operation in synthesized_circuit {
+                    new_circuit.add_operation(synthesized_operation);
+                }
+            }
             _ => new_circuit.add_operation(operation.clone()),
         }
     }
     Ok(new_circuit)
 }
 
-pub fn call_operation(operation: &Operation) -> Result<String, RoqoqoBackendError> {
+/// Translates an operation to the instructions calling the QIR functions implementing it.
+///
+/// # Arguments
+///
+/// * `operation` - The operation that is translated
+/// * `context` - The numbering of the function the instructions are part of
+///
+/// # Returns
+///
+/// * `Ok(Fragment)` - The instructions of the operation
+/// * `QirError::UnsupportedOperation` - The operation is not available on the backend
+/// * `QirError::UnboundParameter` - A PragmaLoop has a symbolic number of repetitions
+/// * `QirError::InvalidQubitCount` - A multi-qubit operation acts on no qubits
+pub fn call_operation(
+    operation: &Operation,
+    context: &mut TranslationContext,
+) -> Result<Fragment, QirError> {
+    check_qubit_count(operation)?;
+    operation_calls(operation, context).map_err(|error| error.locate(0, operation))
+}
+
+/// Returns the instructions of an operation, see [call_operation].
+fn operation_calls(
+    operation: &Operation,
+    context: &mut TranslationContext,
+) -> Result<Fragment, QirError> {
     match operation {
-        Operation::RotateX(op) => Ok(format!(
-            "  call void @__quantum__qis__rx__body(double {}, %Qubit* {})",
-            format_calculator(op.theta()),
-            format_arg(op.qubit(), "Qubit")
-        )),
-        Operation::RotateY(op) => Ok(format!(
-            "  call void @__quantum__qis__ry__body(double {}, %Qubit* {})",
-            format_calculator(op.theta()),
-            format_arg(op.qubit(), "Qubit")
-        )),
-        Operation::RotateZ(op) => Ok(format!(
-            "  call void @__quantum__qis__rz__body(double {}, %Qubit* {})",
-            format_calculator(op.theta()),
-            format_arg(op.qubit(), "Qubit")
+        Operation::RotateX(op) => parametrized_call(
+            "__quantum__qis__rx__body",
+            &[op.theta()],
+            &[*op.qubit()],
+            context,
+        ),
+        Operation::RotateY(op) => parametrized_call(
+            "__quantum__qis__ry__body",
+            &[op.theta()],
+            &[*op.qubit()],
+            context,
+        ),
+        Operation::RotateZ(op) => parametrized_call(
+            "__quantum__qis__rz__body",
+            &[op.theta()],
+            &[*op.qubit()],
+            context,
+        ),
+        Operation::PauliX(op) => Ok(call(
+            "__quantum__qis__x__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::PauliX(op) => Ok(format!(
-            "  call void @__quantum__qis__x__body(%Qubit* {})",
-            format_arg(op.qubit(), "Qubit")
+        Operation::PauliY(op) => Ok(call(
+            "__quantum__qis__y__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::PauliY(op) => Ok(format!(
-            "  call void @__quantum__qis__y__body(%Qubit* {})",
-            format_arg(op.qubit(), "Qubit")
+        Operation::PauliZ(op) => Ok(call(
+            "__quantum__qis__z__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::PauliZ(op) => Ok(format!(
-            "  call void @__quantum__qis__z__body(%Qubit* {})",
-            format_arg(op.qubit(), "Qubit")
+        Operation::Hadamard(op) => Ok(call(
+            "__quantum__qis__h__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::Hadamard(op) => Ok(format!(
-            "  call void @__quantum__qis__h__body(%Qubit* {})",
-            format_arg(op.qubit(), "Qubit")
+        Operation::SGate(op) => Ok(call(
+            "__quantum__qis__s__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::SGate(op) => Ok(format!(
-            "  call void @__quantum__qis__s__body(%Qubit* {})",
-            format_arg(op.qubit(), "Qubit")
+        Operation::TGate(op) => Ok(call(
+            "__quantum__qis__t__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::TGate(op) => Ok(format!(
-            "  call void @__quantum__qis__t__body(%Qubit* {})",
-            format_arg(op.qubit(), "Qubit")
+        Operation::CNOT(op) => Ok(call(
+            "__quantum__qis__cnot__body",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::CNOT(op) => Ok(format!(
-            "  call void @__quantum__qis__cnot__body(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::ControlledPauliZ(op) => Ok(call(
+            "__quantum__qis__cz__body",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::ControlledPauliZ(op) => Ok(format!(
-            "  call void @__quantum__qis__cz__body(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::SWAP(op) => Ok(call(
+            "swap",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::SWAP(op) => Ok(format!(
-            "  call void @swap(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::ISwap(op) => Ok(call(
+            "iswap",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::ISwap(op) => Ok(format!(
-            "  call void @iswap(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::SqrtISwap(op) => Ok(call(
+            "siswap",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::SqrtISwap(op) => Ok(format!(
-            "  call void @siswap(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::InvSqrtISwap(op) => Ok(call(
+            "siswap_adj",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::InvSqrtISwap(op) => Ok(format!(
-            "  call void @siswap_adj(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::FSwap(op) => Ok(call(
+            "fswap",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
-        Operation::FSwap(op) => Ok(format!(
-            "  call void @fswap(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::Toffoli(op) => Ok(call(
+            "__quantum__qis__ccx__body",
+            vec![
+                Value::Qubit(*op.control_0()),
+                Value::Qubit(*op.control_1()),
+                Value::Qubit(*op.target()),
+            ],
         )),
-        Operation::Toffoli(op) => Ok(format!(
-            "  call void @__quantum__qis__ccx__body(%Qubit* {}, %Qubit* {}, %Qubit* {})",
-            format_arg(op.control_0(), "Qubit"),
-            format_arg(op.control_1(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::MeasureQubit(op) => Ok(irreversible_call(
+            "__quantum__qis__mz__body",
+            vec![
+                Value::Qubit(*op.qubit()),
+                Value::Result(*op.readout_index()),
+            ],
         )),
-        Operation::MeasureQubit(op) => Ok(format!(
-            "  call void @__quantum__qis__mz__body(%Qubit* {}, %Result* {}) #1",
-            format_arg(op.qubit(), "Qubit"),
-            format_arg(op.readout_index(), "Result"),
+        Operation::PragmaActiveReset(op) => Ok(irreversible_call(
+            "__quantum__qis__reset__body",
+            vec![Value::Qubit(*op.qubit())],
         )),
-        Operation::CallDefinedGate(op) => Ok(format!(
-            "  call void @{}({}{}{})",
+        Operation::CallDefinedGate(op) => parametrized_call(
             op.gate_name(),
-            op.free_parameters()
+            &op.free_parameters()
                 .iter()
-                .map(|param| format!("double {}", format_calculator(param)))
-                .collect::<Vec<String>>()
-                .join(", "),
-            if !op.free_parameters().is_empty() && !op.qubits().is_empty() {
-                ", "
-            } else {
-                ""
-            },
-            op.qubits()
-                .iter()
-                .map(|qubit| format!("%Qubit* {}", format_arg(qubit, "Qubit")))
-                .collect::<Vec<String>>()
-                .join(", ")
-        )),
+                .collect::<Vec<&CalculatorFloat>>(),
+            op.qubits(),
+            context,
+        ),
         Operation::PragmaConditional(op) => {
-            let mut nb_vars = NUMBER_VARS.lock().unwrap();
-            let mut nb_conditional = NUMBER_LABEL.lock().unwrap();
-            let mut output_str = format!(
-                "  %{} = call i1 @__quantum__qis__read_result__body(%Result* {})\n",
-                *nb_vars,
-                format_arg(op.condition_index(), "Result")
-            );
-            output_str.push_str(&format!(
-                "  br i1 %{}, label %then{}, label %continue{}\n\nthen{}:\n",
-                *nb_vars, *nb_conditional, *nb_conditional, *nb_conditional,
-            ));
+            let condition = context.reserve_values(1);
+            let label = context.new_label();
+            let mut fragment = Fragment::from(Instruction::Call {
+                result: Some(condition.to_string()),
+                return_type: Type::Integer(1),
+                callee: "__quantum__qis__read_result__body".to_owned(),
+                arguments: vec![Value::Result(*op.condition_index())],
+                attribute_group: None,
+            });
+            fragment.push(Instruction::ConditionalBranch {
+                condition: Value::local(condition.to_string(), Type::Integer(1)),
+                true_label: format!("then{}", label),
+                false_label: format!("continue{}", label),
+            });
+            fragment.start_block(format!("then{}", label));
             for operation in op.circuit().iter() {
-                output_str.push_str(&(call_operation(operation)? + "\n"));
+                fragment.append(call_operation(operation, context)?);
             }
-            output_str.push_str(&format!(
-                "  br label %continue{}\n\ncontinue{}:",
-                *nb_conditional, *nb_conditional,
-            ));
-            *nb_conditional += 1;
-            *nb_vars += 1;
-            Ok(output_str)
+            fragment.push(Instruction::Branch {
+                label: format!("continue{}", label),
+            });
+            fragment.start_block(format!("continue{}", label));
+            Ok(fragment)
         }
         Operation::PragmaLoop(op) => match op.repetitions() {
             CalculatorFloat::Float(rep) => {
-                let mut nb_loop = NUMBER_LABEL.lock().unwrap();
-                let mut nb_var = NUMBER_VARS.lock().unwrap();
-                let mut output_str =
-                    format!("  br label %header{}\n\nheader{}:\n", *nb_loop, *nb_loop);
-                output_str.push_str(&format!(
-                    "  %{} = phi i64 [ 1, %{} ], [ %{}, %loop{} ]\n",
-                    *nb_var,
-                    if *nb_loop == 0 {
-                        "entry".to_owned()
-                    } else {
-                        format!("continue{}", *nb_loop - 1)
-                    },
-                    *nb_var + 2,
-                    *nb_loop
-                ));
-                output_str.push_str(&format!(
-                        "  %{} = icmp slt i64 %{}, {}\n  br i1 %{}, label %loop{}, label %continue{}\n\nloop{}:\n",
-                        *nb_var + 1,
-                        *nb_var,
-                        rep.floor() as i32 + 1,
-                        *nb_var + 1,
-                        *nb_loop,
-                        *nb_loop,
-                        *nb_loop,
-                    ));
+                let var = context.reserve_values(2);
+                let label = context.new_label();
+                // The loop body is translated first, the counter increment is numbered after it
+                let mut body = Fragment::default();
                 for operation in op.circuit().iter() {
-                    output_str.push_str(&(call_operation(operation)? + "\n"));
+                    body.append(call_operation(operation, context)?);
                 }
-                output_str.push_str(&format!(
-                    "  %{} = add i64 %{}, 1\n  br label %header{}\n\ncontinue{}:",
-                    *nb_var + 2,
-                    *nb_var,
-                    *nb_loop,
-                    *nb_loop
-                ));
-                *nb_var += 3;
-                *nb_loop += 1;
-                Ok(output_str)
+                let increment = context.reserve_values(1);
+                let counter = Value::local(var.to_string(), Type::Integer(64));
+                let mut fragment = Fragment::from(Instruction::Branch {
+                    label: format!("header{}", label),
+                });
+                fragment.start_block(format!("header{}", label));
+                fragment.push(Instruction::Phi {
+                    result: var.to_string(),
+                    ty: Type::Integer(64),
+                    incoming: vec![
+                        (
+                            Value::Integer { bits: 64, value: 1 },
+                            if label == 0 {
+                                "entry".to_owned()
+                            } else {
+                                format!("continue{}", label - 1)
+                            },
+                        ),
+                        (
+                            Value::local(increment.to_string(), Type::Integer(64)),
+                            format!("loop{}", label),
+                        ),
+                    ],
+                });
+                fragment.push(Instruction::IntegerCompare {
+                    result: (var + 1).to_string(),
+                    predicate: IntegerPredicate::Slt,
+                    lhs: counter.clone(),
+                    rhs: Value::Integer {
+                        bits: 64,
+                        value: rep.floor() as i64 + 1,
+                    },
+                });
+                fragment.push(Instruction::ConditionalBranch {
+                    condition: Value::local((var + 1).to_string(), Type::Integer(1)),
+                    true_label: format!("loop{}", label),
+                    false_label: format!("continue{}", label),
+                });
+                fragment.start_block(format!("loop{}", label));
+                fragment.append(body);
+                fragment.push(Instruction::Binary {
+                    result: increment.to_string(),
+                    operator: BinaryOperator::Add,
+                    lhs: counter,
+                    rhs: Value::Integer { bits: 64, value: 1 },
+                });
+                fragment.push(Instruction::Branch {
+                    label: format!("header{}", label),
+                });
+                fragment.start_block(format!("continue{}", label));
+                Ok(fragment)
             }
-            CalculatorFloat::Str(s) => Err(RoqoqoBackendError::GenericError {
-                msg: format!("Used PragmaLoop with an unset parameter: {}", s),
+            CalculatorFloat::Str(s) => Err(QirError::UnboundParameter {
+                index: 0,
+                hqslang: operation.hqslang(),
+                parameter: s.to_owned(),
             }),
         },
-        Operation::MultiQubitZZ(op) => {
-            if op.qubits().len() != 2 {
-                Err(RoqoqoBackendError::GenericError {
-                    msg: format!(
-                        "MultiQubitZZ is only supported for 2 qubits, there are {}.",
-                        op.qubits().len()
-                    ),
-                })
-            } else {
-                Ok(format!(
-                    "  call void @__quantum__qis__rzz__body(double {}, %Qubit* {}, %Qubit* {})",
-                    format_calculator(op.theta()),
-                    format_arg(op.qubits().first().unwrap(), "Qubit"),
-                    format_arg(op.qubits().get(1).unwrap(), "Qubit"),
-                ))
+        Operation::MultiQubitZZ(op) if op.qubits().len() == 2 => parametrized_call(
+            "__quantum__qis__rzz__body",
+            &[op.theta()],
+            op.qubits(),
+            context,
+        ),
+        Operation::MultiQubitZZ(_) | Operation::MultiQubitMS(_) | Operation::MultiQubitCNOT(_) => {
+            let mut fragment = Fragment::default();
+            for decomposed_operation in multi_qubit_decomposition(operation)
+                .unwrap_or_default()
+                .iter()
+            {
+                fragment.append(call_operation(decomposed_operation, context)?);
             }
+            Ok(fragment)
         }
         Operation::XY(op) => {
-            let minus_half_theta = match CalculatorFloat::from(format_calculator(op.theta())) {
-                CalculatorFloat::Float(theta) => CalculatorFloat::from(-0.5 * theta),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            Ok(format!(
-                "  call void @xy(double {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&minus_half_theta),
-                format_arg(op.control(), "Qubit"),
-                format_arg(op.target(), "Qubit")
-            ))
-        }
-        Operation::SqrtPauliX(op) => Ok(format!(
-            "  call void @__quantum__qis__rx__body(double {}, %Qubit* {})",
-            format_calculator(&CalculatorFloat::FRAC_PI_2),
-            format_arg(op.qubit(), "Qubit")
-        )),
-        Operation::InvSqrtPauliX(op) => Ok(format!(
-            "  call void @__quantum__qis__rx__body(double {}, %Qubit* {})",
-            format_calculator(&CalculatorFloat::from(-std::f64::consts::FRAC_PI_2)),
-            format_arg(op.qubit(), "Qubit")
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let minus_half_theta = scaled(theta, -0.5, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "xy",
+                vec![
+                    minus_half_theta,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::SqrtPauliX(op) => Ok(call(
+            "__quantum__qis__rx__body",
+            vec![Value::Double(FRAC_PI_2), Value::Qubit(*op.qubit())],
         )),
-        Operation::PMInteraction(op) => Ok(format!(
-            "  call void @pmint(double {}, %Qubit* {}, %Qubit* {})",
-            format_calculator(op.t()),
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+        Operation::InvSqrtPauliX(op) => Ok(call(
+            "__quantum__qis__rx__body",
+            vec![Value::Double(-FRAC_PI_2), Value::Qubit(*op.qubit())],
         )),
+        Operation::PMInteraction(op) => {
+            parametrized_call("pmint", &[op.t()], &[*op.control(), *op.target()], context)
+        }
         Operation::GivensRotation(op) => {
-            let minus_theta = match CalculatorFloat::from(format_calculator(op.theta())) {
-                CalculatorFloat::Float(theta) => CalculatorFloat::from(-theta),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            let phi_pi_over_2 = match CalculatorFloat::from(format_calculator(op.phi())) {
-                CalculatorFloat::Float(phi) => CalculatorFloat::from(phi + FRAC_PI_2),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            Ok(format!(
-                "  call void @gvnsrot(double {}, double {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&minus_theta),
-                format_calculator(&phi_pi_over_2),
-                format_arg(op.control(), "Qubit"),
-                format_arg(op.target(), "Qubit")
-            ))
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let minus_theta = float_negation(theta, &mut fragment, context);
+            let phi = calculator_value(op.phi(), &mut fragment, context)?;
+            let phi_pi_over_2 = float_operation(
+                BinaryOperator::FAdd,
+                phi,
+                Value::Double(FRAC_PI_2),
+                &mut fragment,
+                context,
+            )?;
+            fragment.push(Instruction::call(
+                "gvnsrot",
+                vec![
+                    minus_theta,
+                    phi_pi_over_2,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
         }
         Operation::GivensRotationLittleEndian(op) => {
-            let phi_pi_over_2 = match CalculatorFloat::from(format_calculator(op.phi())) {
-                CalculatorFloat::Float(phi) => CalculatorFloat::from(phi + FRAC_PI_2),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            let minus_theta = match CalculatorFloat::from(format_calculator(op.theta())) {
-                CalculatorFloat::Float(phi) => CalculatorFloat::from(-phi),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            Ok(format!(
-                "  call void @gvnsrotle(double {}, double {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&minus_theta),
-                format_calculator(&phi_pi_over_2),
-                format_arg(op.control(), "Qubit"),
-                format_arg(op.target(), "Qubit")
-            ))
-        }
-        Operation::PhaseShiftedControlledZ(op) => Ok(format!(
-            "  call void @pscz(double {}, %Qubit* {}, %Qubit* {})",
-            format_calculator(op.phi()),
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
-        )),
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let minus_theta = float_negation(theta, &mut fragment, context);
+            let phi = calculator_value(op.phi(), &mut fragment, context)?;
+            let phi_pi_over_2 = float_operation(
+                BinaryOperator::FAdd,
+                phi,
+                Value::Double(FRAC_PI_2),
+                &mut fragment,
+                context,
+            )?;
+            fragment.push(Instruction::call(
+                "gvnsrotle",
+                vec![
+                    minus_theta,
+                    phi_pi_over_2,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::PhaseShiftedControlledZ(op) => {
+            parametrized_call("pscz", &[op.phi()], &[*op.control(), *op.target()], context)
+        }
         Operation::PhaseShiftedControlledPhase(op) => {
-            let (minus_half_theta, half_theta) =
-                match CalculatorFloat::from(format_calculator(op.theta())) {
-                    CalculatorFloat::Float(theta) => (
-                        CalculatorFloat::from(-0.5 * theta),
-                        CalculatorFloat::from(0.5 * theta),
-                    ),
-                    CalculatorFloat::Str(s) => {
-                        return Err(RoqoqoBackendError::CalculatorError(
-                            CalculatorError::VariableNotSet { name: s.to_owned() },
-                        ))
-                    }
-                };
-            Ok(format!(
-                "  call void @pscp(double {}, double {}, double {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&half_theta),
-                format_calculator(&minus_half_theta),
-                format_calculator(op.phi()),
-                format_arg(op.control(), "Qubit"),
-                format_arg(op.target(), "Qubit"),
-            ))
-        }
-        Operation::VariableMSXX(op) => {
-            let half_theta = match CalculatorFloat::from(format_calculator(op.theta())) {
-                CalculatorFloat::Float(theta) => CalculatorFloat::from(0.5 * theta),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            Ok(format!(
-                "  call void @rxx(double {}, double {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&half_theta),
-                format_calculator(&-half_theta),
-                format_arg(op.control(), "Qubit"),
-                format_arg(op.target(), "Qubit"),
-            ))
-        }
-        Operation::MolmerSorensenXX(op) => Ok(format!(
-            "  call void @rxx(double {}, double {}, %Qubit* {}, %Qubit* {})",
-            format_calculator(&CalculatorFloat::ZERO),
-            format_calculator(&CalculatorFloat::ZERO),
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
-        )),
-        Operation::PhaseShiftState1(op) => Ok(format!(
-            "  call void @__quantum__qis__rz__body(double {}, %Qubit* {})",
-            format_calculator(op.theta()),
-            format_arg(op.qubit(), "Qubit")
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let half_theta = scaled(theta.clone(), 0.5, &mut fragment, context)?;
+            let minus_half_theta = scaled(theta, -0.5, &mut fragment, context)?;
+            let phi = calculator_value(op.phi(), &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "pscp",
+                vec![
+                    half_theta,
+                    minus_half_theta,
+                    phi,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::VariableMSXX(op) => parametrized_call(
+            "rxx",
+            &[op.theta()],
+            &[*op.control(), *op.target()],
+            context,
+        ),
+        Operation::MolmerSorensenXX(op) => Ok(call(
+            "rxx",
+            vec![
+                Value::Double(FRAC_PI_2),
+                Value::Qubit(*op.control()),
+                Value::Qubit(*op.target()),
+            ],
         )),
-        Operation::ControlledPauliY(op) => Ok(format!(
-            "  call void @cy(%Qubit* {}, %Qubit* {})",
-            format_arg(op.control(), "Qubit"),
-            format_arg(op.target(), "Qubit")
+        Operation::PhaseShiftState1(op) => parametrized_call(
+            "__quantum__qis__rz__body",
+            &[op.theta()],
+            &[*op.qubit()],
+            context,
+        ),
+        Operation::ControlledPauliY(op) => Ok(call(
+            "cy",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
         )),
         Operation::ControlledPhaseShift(op) => {
-            let (minus_half_theta, half_theta) =
-                match CalculatorFloat::from(format_calculator(op.theta())) {
-                    CalculatorFloat::Float(theta) => (
-                        CalculatorFloat::from(-0.5 * theta),
-                        CalculatorFloat::from(0.5 * theta),
-                    ),
-                    CalculatorFloat::Str(s) => {
-                        return Err(RoqoqoBackendError::CalculatorError(
-                            CalculatorError::VariableNotSet { name: s.to_owned() },
-                        ))
-                    }
-                };
-            Ok(format!(
-                "  call void @cp(double {}, double {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&half_theta),
-                format_calculator(&minus_half_theta),
-                format_arg(op.control(), "Qubit"),
-                format_arg(op.target(), "Qubit"),
-            ))
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let half_theta = scaled(theta.clone(), 0.5, &mut fragment, context)?;
+            let minus_half_theta = scaled(theta, -0.5, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "cp",
+                vec![
+                    half_theta,
+                    minus_half_theta,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
         }
         Operation::RotateXY(op) => {
-            let minus_phi = match CalculatorFloat::from(format_calculator(op.phi())) {
-                CalculatorFloat::Float(phi) => CalculatorFloat::from(-phi),
-                CalculatorFloat::Str(s) => {
-                    return Err(RoqoqoBackendError::CalculatorError(
-                        CalculatorError::VariableNotSet { name: s.to_owned() },
-                    ))
-                }
-            };
-            Ok(format!(
-                "  call void @rxy(double {}, double {}, double {}, %Qubit* {})",
-                format_calculator(op.theta()),
-                format_calculator(op.phi()),
-                format_calculator(&minus_phi),
-                format_arg(op.qubit(), "Qubit"),
-            ))
-        }
-        Operation::ControlledControlledPauliZ(op) => Ok(format!(
-            "  call void @ccz(%Qubit* {}, %Qubit* {}, %Qubit* {})",
-            format_arg(op.control_0(), "Qubit"),
-            format_arg(op.control_1(), "Qubit"),
-            format_arg(op.target(), "Qubit"),
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let phi = calculator_value(op.phi(), &mut fragment, context)?;
+            let minus_phi = float_negation(phi.clone(), &mut fragment, context);
+            fragment.push(Instruction::call(
+                "rxy",
+                vec![theta, phi, minus_phi, Value::Qubit(*op.qubit())],
+            ));
+            Ok(fragment)
+        }
+        Operation::ControlledControlledPauliZ(op) => Ok(call(
+            "ccz",
+            vec![
+                Value::Qubit(*op.control_0()),
+                Value::Qubit(*op.control_1()),
+                Value::Qubit(*op.target()),
+            ],
         )),
         Operation::ControlledControlledPhaseShift(op) => {
-            let (minus_frac_theta_4, frac_theta_4) =
-                match CalculatorFloat::from(format_calculator(op.theta())) {
-                    CalculatorFloat::Float(theta) => (
-                        CalculatorFloat::from(-0.25 * theta),
-                        CalculatorFloat::from(0.25 * theta),
-                    ),
-                    CalculatorFloat::Str(s) => {
-                        return Err(RoqoqoBackendError::CalculatorError(
-                            CalculatorError::VariableNotSet { name: s.to_owned() },
-                        ))
-                    }
-                };
-            Ok(format!(
-                "  call void @ccp(double {}, double {}, %Qubit* {}, %Qubit* {}, %Qubit* {})",
-                format_calculator(&frac_theta_4),
-                format_calculator(&minus_frac_theta_4),
-                format_arg(op.control_0(), "Qubit"),
-                format_arg(op.control_1(), "Qubit"),
-                format_arg(op.target(), "Qubit"),
-            ))
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let quarter_theta = scaled(theta.clone(), 0.25, &mut fragment, context)?;
+            let minus_quarter_theta = scaled(theta, -0.25, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "ccp",
+                vec![
+                    quarter_theta,
+                    minus_quarter_theta,
+                    Value::Qubit(*op.control_0()),
+                    Value::Qubit(*op.control_1()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::SqrtPauliY(op) => Ok(call(
+            "__quantum__qis__ry__body",
+            vec![Value::Double(FRAC_PI_2), Value::Qubit(*op.qubit())],
+        )),
+        Operation::InvSqrtPauliY(op) => Ok(call(
+            "__quantum__qis__ry__body",
+            vec![Value::Double(-FRAC_PI_2), Value::Qubit(*op.qubit())],
+        )),
+        Operation::InvSGate(op) => Ok(call(
+            "__quantum__qis__s__adj",
+            vec![Value::Qubit(*op.qubit())],
+        )),
+        Operation::InvTGate(op) => Ok(call(
+            "__quantum__qis__t__adj",
+            vec![Value::Qubit(*op.qubit())],
+        )),
+        Operation::PhaseShiftState0(op) => {
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let minus_theta = float_negation(theta, &mut fragment, context);
+            fragment.push(Instruction::call(
+                "__quantum__qis__rz__body",
+                vec![minus_theta, Value::Qubit(*op.qubit())],
+            ));
+            Ok(fragment)
+        }
+        Operation::RotateAroundSphericalAxis(op) => {
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let spherical_theta = calculator_value(op.spherical_theta(), &mut fragment, context)?;
+            let minus_spherical_theta =
+                float_negation(spherical_theta.clone(), &mut fragment, context);
+            let spherical_phi = calculator_value(op.spherical_phi(), &mut fragment, context)?;
+            let minus_spherical_phi = float_negation(spherical_phi.clone(), &mut fragment, context);
+            fragment.push(Instruction::call(
+                "rsph",
+                vec![
+                    theta,
+                    spherical_theta,
+                    minus_spherical_theta,
+                    spherical_phi,
+                    minus_spherical_phi,
+                    Value::Qubit(*op.qubit()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::SingleQubitGate(op) => single_qubit_gate_calls(op, context),
+        Operation::GPi(op) => {
+            let mut fragment = call("__quantum__qis__x__body", vec![Value::Qubit(*op.qubit())]);
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let two_theta = scaled(theta, 2.0, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "__quantum__qis__rz__body",
+                vec![two_theta, Value::Qubit(*op.qubit())],
+            ));
+            Ok(fragment)
+        }
+        Operation::GPi2(op) => {
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let minus_theta = float_negation(theta.clone(), &mut fragment, context);
+            fragment.push(Instruction::call(
+                "rxy",
+                vec![
+                    Value::Double(FRAC_PI_2),
+                    theta,
+                    minus_theta,
+                    Value::Qubit(*op.qubit()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::SpinInteraction(op) => spin_interaction_call(
+            "spinint",
+            [op.x(), op.y(), op.z()],
+            [*op.control(), *op.target()],
+            context,
+        ),
+        Operation::Qsim(op) => spin_interaction_call(
+            "qsim",
+            [op.x(), op.y(), op.z()],
+            [*op.control(), *op.target()],
+            context,
+        ),
+        Operation::Fsim(op) => {
+            let mut fragment = Fragment::default();
+            let t = calculator_value(op.t(), &mut fragment, context)?;
+            let delta = calculator_value(op.delta(), &mut fragment, context)?;
+            let t_minus_delta = float_operation(
+                BinaryOperator::FSub,
+                t.clone(),
+                delta.clone(),
+                &mut fragment,
+                context,
+            )?;
+            let t_plus_delta =
+                float_operation(BinaryOperator::FAdd, t, delta, &mut fragment, context)?;
+            let u = calculator_value(op.u(), &mut fragment, context)?;
+            let phase = float_operation(
+                BinaryOperator::FSub,
+                Value::Double(PI),
+                u,
+                &mut fragment,
+                context,
+            )?;
+            let half_phase = scaled(phase.clone(), 0.5, &mut fragment, context)?;
+            let minus_half_phase = scaled(phase, -0.5, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "fsim",
+                vec![
+                    t_minus_delta,
+                    t_plus_delta,
+                    half_phase,
+                    minus_half_phase,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::Bogoliubov(op) => {
+            let mut fragment = Fragment::default();
+            let (abs_delta, phi) =
+                polar_values(op.delta_real(), op.delta_imag(), &mut fragment, context)?;
+            let minus_abs_delta = float_negation(abs_delta.clone(), &mut fragment, context);
+            let minus_phi = float_negation(phi.clone(), &mut fragment, context);
+            fragment.push(Instruction::call(
+                "bogoliubov",
+                vec![
+                    abs_delta,
+                    minus_abs_delta,
+                    phi,
+                    minus_phi,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::ComplexPMInteraction(op) => {
+            let mut fragment = Fragment::default();
+            let (abs_t, phi) = polar_values(op.t_real(), op.t_imag(), &mut fragment, context)?;
+            let minus_phi = float_negation(phi.clone(), &mut fragment, context);
+            fragment.push(Instruction::call(
+                "cpmint",
+                vec![
+                    abs_t,
+                    phi,
+                    minus_phi,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::ControlledRotateX(op) => {
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let half_theta = scaled(theta.clone(), 0.5, &mut fragment, context)?;
+            let minus_half_theta = scaled(theta, -0.5, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "crx",
+                vec![
+                    half_theta,
+                    minus_half_theta,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::ControlledRotateXY(op) => {
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let half_theta = scaled(theta.clone(), 0.5, &mut fragment, context)?;
+            let minus_half_theta = scaled(theta, -0.5, &mut fragment, context)?;
+            let phi = calculator_value(op.phi(), &mut fragment, context)?;
+            let minus_phi = float_negation(phi.clone(), &mut fragment, context);
+            fragment.push(Instruction::call(
+                "crxy",
+                vec![
+                    half_theta,
+                    minus_half_theta,
+                    phi,
+                    minus_phi,
+                    Value::Qubit(*op.control()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
+        }
+        Operation::EchoCrossResonance(op) => Ok(call(
+            "ecr",
+            vec![Value::Qubit(*op.control()), Value::Qubit(*op.target())],
+        )),
+        // The control qubit of ControlledSWAP is only exposed as control_0 of OperateThreeQubit
+        Operation::ControlledSWAP(op) => Ok(call(
+            "cswap",
+            vec![
+                Value::Qubit(*op.control_0()),
+                Value::Qubit(*op.target_0()),
+                Value::Qubit(*op.target_1()),
+            ],
+        )),
+        Operation::TripleControlledPauliX(op) => Ok(call(
+            "c3x",
+            vec![
+                Value::Qubit(*op.control_0()),
+                Value::Qubit(*op.control_1()),
+                Value::Qubit(*op.control_2()),
+                Value::Qubit(*op.target()),
+            ],
+        )),
+        Operation::TripleControlledPauliZ(op) => Ok(call(
+            "c3z",
+            vec![
+                Value::Qubit(*op.control_0()),
+                Value::Qubit(*op.control_1()),
+                Value::Qubit(*op.control_2()),
+                Value::Qubit(*op.target()),
+            ],
+        )),
+        Operation::TripleControlledPhaseShift(op) => {
+            let mut fragment = Fragment::default();
+            let theta = calculator_value(op.theta(), &mut fragment, context)?;
+            let eighth_theta = scaled(theta.clone(), 0.125, &mut fragment, context)?;
+            let minus_eighth_theta = scaled(theta, -0.125, &mut fragment, context)?;
+            fragment.push(Instruction::call(
+                "c3p",
+                vec![
+                    eighth_theta,
+                    minus_eighth_theta,
+                    Value::Qubit(*op.control_0()),
+                    Value::Qubit(*op.control_1()),
+                    Value::Qubit(*op.control_2()),
+                    Value::Qubit(*op.target()),
+                ],
+            ));
+            Ok(fragment)
         }
         _ => {
             if NO_CALL_OPERATIONS.contains(&operation.hqslang()) {
-                Ok("".to_owned())
+                Ok(Fragment::default())
             } else {
-                Err(RoqoqoBackendError::OperationNotInBackend {
-                    backend: "QirBackend",
+                Err(QirError::UnsupportedOperation {
+                    index: 0,
                     hqslang: operation.hqslang(),
                 })
             }
@@ -795,98 +1691,129 @@ pub fn call_operation(operation: &Operation) -> Result<String, RoqoqoBackendErro
     }
 }
 
-pub fn gate_declaration(operation: &Operation) -> Result<String, RoqoqoBackendError> {
+/// Returns the declaration or definition of the function called by an operation.
+///
+/// # Arguments
+///
+/// * `operation` - The operation whose function is declared
+///
+/// # Returns
+///
+/// * `Ok(Some(Function))` - The declaration of an intrinsic or the definition of a helper gate
+/// * `Ok(None)` - The operation does not need a declaration of its own
+/// * `QirError::UnsupportedOperation` - The operation is not available on the backend
+/// * `QirError::InvalidDefinition` - The qubits of a GateDefinition do not match its circuit
+pub fn gate_declaration(operation: &Operation) -> Result<Option<Function>, QirError> {
+    let declaration = |name: &str, parameters: &[Type]| {
+        Ok(Some(Function::declaration(name, Type::Void, parameters)))
+    };
     match operation {
         Operation::RotateX(_) => {
-            Ok("declare void @__quantum__qis__rx__body(double, %Qubit*)".to_owned())
+            declaration("__quantum__qis__rx__body", &[Type::Double, Type::QUBIT])
         }
         Operation::RotateY(_) => {
-            Ok("declare void @__quantum__qis__ry__body(double, %Qubit*)".to_owned())
+            declaration("__quantum__qis__ry__body", &[Type::Double, Type::QUBIT])
         }
         Operation::RotateZ(_) => {
-            Ok("declare void @__quantum__qis__rz__body(double, %Qubit*)".to_owned())
-        }
-        Operation::PauliX(_) => Ok("declare void @__quantum__qis__x__body(%Qubit*)".to_owned()),
-        Operation::PauliY(_) => Ok("declare void @__quantum__qis__y__body(%Qubit*)".to_owned()),
-        Operation::PauliZ(_) => Ok("declare void @__quantum__qis__z__body(%Qubit*)".to_owned()),
-        Operation::Hadamard(_) => Ok("declare void @__quantum__qis__h__body(%Qubit*)".to_owned()),
-        Operation::SGate(_) => Ok("declare void @__quantum__qis__s__body(%Qubit*)".to_owned()),
-        Operation::TGate(_) => Ok("declare void @__quantum__qis__t__body(%Qubit*)".to_owned()),
-        Operation::CNOT(_) => {
-            Ok("declare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)".to_owned())
+            declaration("__quantum__qis__rz__body", &[Type::Double, Type::QUBIT])
         }
-        Operation::Toffoli(_) => {
-            Ok("declare void @__quantum__qis__ccx__body(%Qubit*, %Qubit*, %Qubit*)".to_owned())
+        Operation::PauliX(_) => declaration("__quantum__qis__x__body", &[Type::QUBIT]),
+        Operation::PauliY(_) => declaration("__quantum__qis__y__body", &[Type::QUBIT]),
+        Operation::PauliZ(_) => declaration("__quantum__qis__z__body", &[Type::QUBIT]),
+        Operation::Hadamard(_) => declaration("__quantum__qis__h__body", &[Type::QUBIT]),
+        Operation::SGate(_) => declaration("__quantum__qis__s__body", &[Type::QUBIT]),
+        Operation::TGate(_) => declaration("__quantum__qis__t__body", &[Type::QUBIT]),
+        Operation::InvSGate(_) => declaration("__quantum__qis__s__adj", &[Type::QUBIT]),
+        Operation::InvTGate(_) => declaration("__quantum__qis__t__adj", &[Type::QUBIT]),
+        Operation::CNOT(_) => {
+            declaration("__quantum__qis__cnot__body", &[Type::QUBIT, Type::QUBIT])
         }
+        Operation::Toffoli(_) => declaration(
+            "__quantum__qis__ccx__body",
+            &[Type::QUBIT, Type::QUBIT, Type::QUBIT],
+        ),
         Operation::ControlledPauliZ(_) => {
-            Ok("declare void @__quantum__qis__cz__body(%Qubit*, %Qubit*)".to_owned())
+            declaration("__quantum__qis__cz__body", &[Type::QUBIT, Type::QUBIT])
         }
         Operation::MeasureQubit(_) => {
-            Ok("declare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1".to_owned())
+            let mut function = Function::declaration(
+                "__quantum__qis__mz__body",
+                Type::Void,
+                &[Type::QUBIT, Type::RESULT],
+            );
+            function.parameters[1]
+                .attributes
+                .push("writeonly".to_owned());
+            function.attribute_group = Some(1);
+            Ok(Some(function))
+        }
+        Operation::PragmaActiveReset(_) => {
+            let mut function =
+                Function::declaration("__quantum__qis__reset__body", Type::Void, &[Type::QUBIT]);
+            function.attribute_group = Some(1);
+            Ok(Some(function))
         }
         Operation::GateDefinition(gate_definition) => {
-            let mut definition_str = format!(
-                "\ndefine void @{}({}{}{}) {}{{\nentry:\n",
-                gate_definition.name(),
-                gate_definition
-                    .free_parameters()
-                    .iter()
-                    .map(|param| format!("double %{}", param))
-                    .collect::<Vec<String>>()
-                    .join(", "),
-                if !gate_definition.free_parameters().is_empty() && !gate_definition.qubits().is_empty() { ", " } else { "" },
+            let mut parameters: Vec<Parameter> = gate_definition
+                .free_parameters()
+                .iter()
+                .map(|param| Parameter::named(Type::Double, param))
+                .collect();
+            parameters.extend(
                 gate_definition
                     .qubits()
                     .iter()
-                    .map(|&qubit| format!("%Qubit* %qubit{}", qubit).to_owned())
-                    .collect::<Vec<String>>()
-                    .join(", "),
-                if gate_definition
-                    .circuit()
-                    .iter()
-                    .filter(|&op| matches!(op, Operation::MeasureQubit(_)))
-                    .collect::<Vec<&Operation>>()
-                    .is_empty() { "" } else { "#1 " }
-            );
-            for operation in gate_definition.circuit().iter() {
-                definition_str.push_str(&call_operation(operation)?);
-                definition_str.push('\n');
-            }
-            definition_str = definition_str.replace(
-                "null",
-                &format!(
-                    "%qubit{}",
-                    gate_definition.qubits().first().unwrap_or(&0_usize)
-                ),
+                    .map(|index| Parameter::named(Type::QUBIT, format!("qubit{}", index))),
             );
-            for (index, qubit) in gate_definition.qubits().iter().enumerate() {
-                definition_str = definition_str.replace(
-                    &format!("inttoptr (i64 {} to %Qubit*)", index),
-                    &format!("%qubit{}", qubit),
-                );
+            let mut function = Function::definition(gate_definition.name(), Type::Void, parameters);
+            if gate_definition.circuit().iter().any(|op| {
+                matches!(
+                    op,
+                    Operation::MeasureQubit(_) | Operation::PragmaActiveReset(_)
+                )
+            }) {
+                function.attribute_group = Some(1);
+            }
+            check_definition_qubits(gate_definition)?;
+            // Numbered values and labels restart in every function
+            let mut context = TranslationContext::new();
+            let qubits = gate_definition.qubits();
+            let body: Result<Vec<Fragment>, QirError> = gate_definition
+                .circuit()
+                .iter()
+                .map(|operation| call_operation(operation, &mut context))
+                .collect();
+            for mut fragment in body? {
+                // The qubits of the definition are passed as arguments named after their index
+                fragment.map_values(&|value| match value {
+                    Value::Qubit(index) if qubits.contains(index) => {
+                        Some(Value::local(format!("qubit{}", index), Type::QUBIT))
+                    }
+                    _ => None,
+                });
+                function.append(fragment);
             }
-            definition_str.push_str("  ret void\n}\n");
-            Ok(definition_str)
+            function.push(Instruction::Return(None));
+            check_definition_parameters(gate_definition, &function)?;
+            Ok(Some(function))
         }
-        Operation::MultiQubitZZ(op) => {
-            if op.qubits().len() != 2 {
-                Err(RoqoqoBackendError::GenericError {
-                    msg: format!(
-                        "MultiQubitZZ is only supported for 2 qubits, there are {}.",
-                        op.qubits().len()
-                    ),
-                })
+        Operation::MultiQubitZZ(op) if op.qubits().len() == 2 => declaration(
+            "__quantum__qis__rzz__body",
+            &[Type::Double, Type::QUBIT, Type::QUBIT],
+        ),
+        // Decomposed into the gates of their CNOT ladders by pre_process_circuit
+        Operation::MultiQubitZZ(_) | Operation::MultiQubitMS(_) | Operation::MultiQubitCNOT(_) => {
+            Ok(None)
+        }
+        _ => {
+            if NO_DECLARATION_OPERATIONS.contains(&operation.hqslang()) {
+                Ok(None)
             } else {
-                Ok("declare void @__quantum__qis__rzz__body(double, %Qubit*, %Qubit*)".to_owned())
+                Err(QirError::UnsupportedOperation {
+                    index: 0,
+                    hqslang: operation.hqslang(),
+                })
             }
         }
-        Operation::ControlledPauliY(_) => {
-            Ok("\ndefine void @cy(%Qubit* qubit0, %Qubit* qubit1) {\nentry:\n  call void @__quantum__qis__s__adj(%Qubit* qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* qubit0, %Qubit* qubit1)\n  call void @__quantum__qis__s__body(%Qubit* qubit1)\n  ret void\n}\n".to_owned())
-        }
-        _ => if NO_DECLARATION_OPERATIONS
-            .contains(&operation.hqslang()) { Ok("".to_owned()) } else { Err(RoqoqoBackendError::OperationNotInBackend {
-                backend: "QirBackend",
-                hqslang: operation.hqslang(),
-            }) },
     }
 }
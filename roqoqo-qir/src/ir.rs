@@ -0,0 +1,1058 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured representation of a QIR module.
+//!
+//! The backend builds a [Module] out of functions, basic blocks and instructions, which can be
+//! inspected or transformed before it is printed as LLVM IR text with its [std::fmt::Display]
+//! implementation.
+
+use std::fmt;
+
+use crate::PointerStyle;
+
+/// Type pointed to by a QIR pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pointee {
+    /// The opaque `%Qubit` type.
+    Qubit,
+    /// The opaque `%Result` type.
+    Result,
+    /// An `i8`, used for output labels.
+    I8,
+}
+
+/// Type of a value, parameter or return value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    /// `void`
+    Void,
+    /// Integer with the given number of bits, e.g. `i1` or `i64`.
+    Integer(u32),
+    /// `double`
+    Double,
+    /// Pointer to a qubit, a result or a label.
+    Pointer(Pointee),
+}
+
+impl Type {
+    /// Pointer to a `%Qubit`.
+    pub const QUBIT: Type = Type::Pointer(Pointee::Qubit);
+    /// Pointer to a `%Result`.
+    pub const RESULT: Type = Type::Pointer(Pointee::Result);
+    /// Pointer to an `i8` label.
+    pub const LABEL: Type = Type::Pointer(Pointee::I8);
+
+    /// Returns the LLVM syntax of the type in the given pointer style.
+    pub fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        match (self, pointer_style) {
+            (Type::Void, _) => "void".to_owned(),
+            (Type::Integer(bits), _) => format!("i{}", bits),
+            (Type::Double, _) => "double".to_owned(),
+            (Type::Pointer(_), PointerStyle::Opaque) => "ptr".to_owned(),
+            (Type::Pointer(Pointee::Qubit), PointerStyle::Typed) => "%Qubit*".to_owned(),
+            (Type::Pointer(Pointee::Result), PointerStyle::Typed) => "%Result*".to_owned(),
+            (Type::Pointer(Pointee::I8), PointerStyle::Typed) => "i8*".to_owned(),
+        }
+    }
+}
+
+/// Operand of an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Statically allocated qubit with the given index.
+    Qubit(usize),
+    /// Statically allocated result with the given index.
+    Result(usize),
+    /// Floating point constant.
+    Double(f64),
+    /// Integer constant with the given number of bits.
+    Integer {
+        /// Number of bits of the integer type.
+        bits: u32,
+        /// Value of the constant.
+        value: i64,
+    },
+    /// Null pointer.
+    Null(Pointee),
+    /// Local value, i.e. a function parameter or the result of an instruction.
+    Local {
+        /// Name of the value without the `%` sigil.
+        name: String,
+        /// Type of the value.
+        ty: Type,
+    },
+}
+
+impl Value {
+    /// Creates a local value of the given type.
+    pub fn local(name: impl Into<String>, ty: Type) -> Self {
+        Value::Local {
+            name: name.into(),
+            ty,
+        }
+    }
+
+    /// Returns the type of the value.
+    pub fn ty(&self) -> Type {
+        match self {
+            Value::Qubit(_) => Type::QUBIT,
+            Value::Result(_) => Type::RESULT,
+            Value::Double(_) => Type::Double,
+            Value::Integer { bits, .. } => Type::Integer(*bits),
+            Value::Null(pointee) => Type::Pointer(*pointee),
+            Value::Local { ty, .. } => *ty,
+        }
+    }
+
+    /// Returns the LLVM syntax of the value without its type.
+    ///
+    /// With opaque pointers, the qubit and result with index 0 are written as `null`.
+    pub fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        match self {
+            Value::Qubit(index) | Value::Result(index) => match (pointer_style, index) {
+                (PointerStyle::Opaque, 0) => "null".to_owned(),
+                _ => {
+                    let ty = self.ty().to_qir(pointer_style);
+                    format!("inttoptr (i64 {} to {})", index, ty)
+                }
+            },
+            Value::Double(value) => {
+                if !value.is_finite() {
+                    // Infinities and NaNs only have a hexadecimal representation
+                    format!("0x{:016X}", value.to_bits())
+                } else if value.fract() == 0.0 {
+                    format!("{:.1}", value)
+                } else {
+                    format!("{}", value)
+                }
+            }
+            Value::Integer { bits: 1, value } => (*value != 0).to_string(),
+            Value::Integer { value, .. } => value.to_string(),
+            Value::Null(_) => "null".to_owned(),
+            Value::Local { name, .. } => format!("%{}", name),
+        }
+    }
+
+    /// Returns the LLVM syntax of the value preceded by its type.
+    pub fn to_typed_qir(&self, pointer_style: PointerStyle) -> String {
+        format!(
+            "{} {}",
+            self.ty().to_qir(pointer_style),
+            self.to_qir(pointer_style)
+        )
+    }
+}
+
+/// Binary operators of arithmetic instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    /// Integer addition.
+    Add,
+    /// Integer subtraction.
+    Sub,
+    /// Integer multiplication.
+    Mul,
+    /// Floating point addition.
+    FAdd,
+    /// Floating point subtraction.
+    FSub,
+    /// Floating point multiplication.
+    FMul,
+    /// Floating point division.
+    FDiv,
+}
+
+impl BinaryOperator {
+    fn to_qir(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "add",
+            BinaryOperator::Sub => "sub",
+            BinaryOperator::Mul => "mul",
+            BinaryOperator::FAdd => "fadd",
+            BinaryOperator::FSub => "fsub",
+            BinaryOperator::FMul => "fmul",
+            BinaryOperator::FDiv => "fdiv",
+        }
+    }
+}
+
+/// Predicates of integer comparisons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegerPredicate {
+    /// Equal.
+    Eq,
+    /// Not equal.
+    Ne,
+    /// Signed less than.
+    Slt,
+    /// Signed less or equal.
+    Sle,
+    /// Signed greater than.
+    Sgt,
+    /// Signed greater or equal.
+    Sge,
+}
+
+impl IntegerPredicate {
+    fn to_qir(self) -> &'static str {
+        match self {
+            IntegerPredicate::Eq => "eq",
+            IntegerPredicate::Ne => "ne",
+            IntegerPredicate::Slt => "slt",
+            IntegerPredicate::Sle => "sle",
+            IntegerPredicate::Sgt => "sgt",
+            IntegerPredicate::Sge => "sge",
+        }
+    }
+}
+
+/// Predicates of ordered floating point comparisons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatPredicate {
+    /// Ordered and equal.
+    Oeq,
+    /// Ordered and not equal.
+    One,
+    /// Ordered and less than.
+    Olt,
+    /// Ordered and less or equal.
+    Ole,
+    /// Ordered and greater than.
+    Ogt,
+    /// Ordered and greater or equal.
+    Oge,
+}
+
+impl FloatPredicate {
+    fn to_qir(self) -> &'static str {
+        match self {
+            FloatPredicate::Oeq => "oeq",
+            FloatPredicate::One => "one",
+            FloatPredicate::Olt => "olt",
+            FloatPredicate::Ole => "ole",
+            FloatPredicate::Ogt => "ogt",
+            FloatPredicate::Oge => "oge",
+        }
+    }
+}
+
+/// Instruction inside a basic block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// Function call, optionally assigning its return value.
+    Call {
+        /// Name of the local value the return value is assigned to.
+        result: Option<String>,
+        /// Return type of the called function.
+        return_type: Type,
+        /// Name of the called function without the `@` sigil.
+        callee: String,
+        /// Arguments of the call.
+        arguments: Vec<Value>,
+        /// Attribute group of the call site.
+        attribute_group: Option<u32>,
+    },
+    /// Unconditional branch.
+    Branch {
+        /// Label of the target block.
+        label: String,
+    },
+    /// Conditional branch on an `i1` value.
+    ConditionalBranch {
+        /// Condition of the branch.
+        condition: Value,
+        /// Label of the block executed if the condition is true.
+        true_label: String,
+        /// Label of the block executed if the condition is false.
+        false_label: String,
+    },
+    /// Phi node selecting a value depending on the predecessor block.
+    Phi {
+        /// Name of the local value that is assigned.
+        result: String,
+        /// Type of the incoming values.
+        ty: Type,
+        /// Incoming values with the labels of their predecessor blocks.
+        incoming: Vec<(Value, String)>,
+    },
+    /// Integer comparison.
+    IntegerCompare {
+        /// Name of the local value that is assigned.
+        result: String,
+        /// Comparison predicate.
+        predicate: IntegerPredicate,
+        /// Left hand side of the comparison.
+        lhs: Value,
+        /// Right hand side of the comparison.
+        rhs: Value,
+    },
+    /// Binary arithmetic operation.
+    Binary {
+        /// Name of the local value that is assigned.
+        result: String,
+        /// Arithmetic operator.
+        operator: BinaryOperator,
+        /// Left hand side operand.
+        lhs: Value,
+        /// Right hand side operand.
+        rhs: Value,
+    },
+    /// Floating point negation.
+    FloatNegate {
+        /// Name of the local value that is assigned.
+        result: String,
+        /// Negated operand.
+        operand: Value,
+    },
+    /// Floating point comparison.
+    FloatCompare {
+        /// Name of the local value that is assigned.
+        result: String,
+        /// Comparison predicate.
+        predicate: FloatPredicate,
+        /// Left hand side of the comparison.
+        lhs: Value,
+        /// Right hand side of the comparison.
+        rhs: Value,
+    },
+    /// Selection of one of two values depending on an `i1` condition.
+    Select {
+        /// Name of the local value that is assigned.
+        result: String,
+        /// Condition of the selection.
+        condition: Value,
+        /// Value selected if the condition is true.
+        true_value: Value,
+        /// Value selected if the condition is false.
+        false_value: Value,
+    },
+    /// Return from the function, with an optional return value.
+    Return(Option<Value>),
+}
+
+impl Instruction {
+    /// Creates a call to a function returning void.
+    pub fn call(callee: impl Into<String>, arguments: Vec<Value>) -> Self {
+        Instruction::Call {
+            result: None,
+            return_type: Type::Void,
+            callee: callee.into(),
+            arguments,
+            attribute_group: None,
+        }
+    }
+
+    /// Returns the operands of the instruction.
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Instruction::Call { arguments, .. } => arguments.iter().collect(),
+            Instruction::ConditionalBranch { condition, .. } => vec![condition],
+            Instruction::Phi { incoming, .. } => incoming.iter().map(|(value, _)| value).collect(),
+            Instruction::IntegerCompare { lhs, rhs, .. }
+            | Instruction::Binary { lhs, rhs, .. }
+            | Instruction::FloatCompare { lhs, rhs, .. } => vec![lhs, rhs],
+            Instruction::FloatNegate { operand, .. } => vec![operand],
+            Instruction::Select {
+                condition,
+                true_value,
+                false_value,
+                ..
+            } => vec![condition, true_value, false_value],
+            Instruction::Return(Some(value)) => vec![value],
+            Instruction::Branch { .. } | Instruction::Return(None) => vec![],
+        }
+    }
+
+    /// Returns the name of the local value defined by the instruction, if any.
+    pub fn result(&self) -> Option<&str> {
+        match self {
+            Instruction::Call { result, .. } => result.as_deref(),
+            Instruction::Phi { result, .. }
+            | Instruction::IntegerCompare { result, .. }
+            | Instruction::Binary { result, .. }
+            | Instruction::FloatNegate { result, .. }
+            | Instruction::FloatCompare { result, .. }
+            | Instruction::Select { result, .. } => Some(result),
+            Instruction::Branch { .. }
+            | Instruction::ConditionalBranch { .. }
+            | Instruction::Return(_) => None,
+        }
+    }
+
+    /// Applies `map` to every operand, replacing the operands for which it returns a new value.
+    pub fn map_values(&mut self, map: &dyn Fn(&Value) -> Option<Value>) {
+        let values: Vec<&mut Value> = match self {
+            Instruction::Call { arguments, .. } => arguments.iter_mut().collect(),
+            Instruction::ConditionalBranch { condition, .. } => vec![condition],
+            Instruction::Phi { incoming, .. } => {
+                incoming.iter_mut().map(|(value, _)| value).collect()
+            }
+            Instruction::IntegerCompare { lhs, rhs, .. }
+            | Instruction::Binary { lhs, rhs, .. }
+            | Instruction::FloatCompare { lhs, rhs, .. } => vec![lhs, rhs],
+            Instruction::FloatNegate { operand, .. } => vec![operand],
+            Instruction::Select {
+                condition,
+                true_value,
+                false_value,
+                ..
+            } => vec![condition, true_value, false_value],
+            Instruction::Return(Some(value)) => vec![value],
+            Instruction::Branch { .. } | Instruction::Return(None) => vec![],
+        };
+        for value in values {
+            if let Some(new_value) = map(value) {
+                *value = new_value;
+            }
+        }
+    }
+
+    /// Returns the LLVM syntax of the instruction without indentation.
+    pub fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        match self {
+            Instruction::Call {
+                result,
+                return_type,
+                callee,
+                arguments,
+                attribute_group,
+            } => format!(
+                "{}call {} @{}({}){}",
+                result
+                    .as_ref()
+                    .map(|name| format!("%{} = ", name))
+                    .unwrap_or_default(),
+                return_type.to_qir(pointer_style),
+                callee,
+                arguments
+                    .iter()
+                    .map(|argument| argument.to_typed_qir(pointer_style))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                attribute_group
+                    .map(|id| format!(" #{}", id))
+                    .unwrap_or_default()
+            ),
+            Instruction::Branch { label } => format!("br label %{}", label),
+            Instruction::ConditionalBranch {
+                condition,
+                true_label,
+                false_label,
+            } => format!(
+                "br {}, label %{}, label %{}",
+                condition.to_typed_qir(pointer_style),
+                true_label,
+                false_label
+            ),
+            Instruction::Phi {
+                result,
+                ty,
+                incoming,
+            } => format!(
+                "%{} = phi {} {}",
+                result,
+                ty.to_qir(pointer_style),
+                incoming
+                    .iter()
+                    .map(|(value, label)| format!(
+                        "[ {}, %{} ]",
+                        value.to_qir(pointer_style),
+                        label
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Instruction::IntegerCompare {
+                result,
+                predicate,
+                lhs,
+                rhs,
+            } => format!(
+                "%{} = icmp {} {}, {}",
+                result,
+                predicate.to_qir(),
+                lhs.to_typed_qir(pointer_style),
+                rhs.to_qir(pointer_style)
+            ),
+            Instruction::Binary {
+                result,
+                operator,
+                lhs,
+                rhs,
+            } => format!(
+                "%{} = {} {}, {}",
+                result,
+                operator.to_qir(),
+                lhs.to_typed_qir(pointer_style),
+                rhs.to_qir(pointer_style)
+            ),
+            Instruction::FloatNegate { result, operand } => {
+                format!("%{} = fneg {}", result, operand.to_typed_qir(pointer_style))
+            }
+            Instruction::FloatCompare {
+                result,
+                predicate,
+                lhs,
+                rhs,
+            } => format!(
+                "%{} = fcmp {} {}, {}",
+                result,
+                predicate.to_qir(),
+                lhs.to_typed_qir(pointer_style),
+                rhs.to_qir(pointer_style)
+            ),
+            Instruction::Select {
+                result,
+                condition,
+                true_value,
+                false_value,
+            } => format!(
+                "%{} = select {}, {}, {}",
+                result,
+                condition.to_typed_qir(pointer_style),
+                true_value.to_typed_qir(pointer_style),
+                false_value.to_typed_qir(pointer_style)
+            ),
+            Instruction::Return(None) => "ret void".to_owned(),
+            Instruction::Return(Some(value)) => {
+                format!("ret {}", value.to_typed_qir(pointer_style))
+            }
+        }
+    }
+}
+
+/// Labelled sequence of instructions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    /// Label of the block.
+    pub label: String,
+    /// Instructions of the block.
+    pub instructions: Vec<Instruction>,
+}
+
+impl BasicBlock {
+    /// Creates an empty basic block.
+    pub fn new(label: impl Into<String>) -> Self {
+        BasicBlock {
+            label: label.into(),
+            instructions: vec![],
+        }
+    }
+}
+
+/// Code emitted for one or several operations, possibly spanning several basic blocks.
+///
+/// The leading `instructions` continue the basic block the fragment is appended to, the
+/// following `blocks` start new basic blocks.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Fragment {
+    /// Instructions appended to the current basic block.
+    pub instructions: Vec<Instruction>,
+    /// Basic blocks started by the fragment.
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl From<Instruction> for Fragment {
+    fn from(instruction: Instruction) -> Self {
+        Fragment {
+            instructions: vec![instruction],
+            blocks: vec![],
+        }
+    }
+}
+
+impl Fragment {
+    /// Returns true if the fragment contains no instructions and no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty() && self.blocks.is_empty()
+    }
+
+    /// Appends an instruction to the last basic block of the fragment.
+    pub fn push(&mut self, instruction: Instruction) {
+        match self.blocks.last_mut() {
+            Some(block) => block.instructions.push(instruction),
+            None => self.instructions.push(instruction),
+        }
+    }
+
+    /// Starts a new basic block, following instructions are appended to it.
+    pub fn start_block(&mut self, label: impl Into<String>) {
+        self.blocks.push(BasicBlock::new(label));
+    }
+
+    /// Appends another fragment at the end of this fragment.
+    pub fn append(&mut self, other: Fragment) {
+        for instruction in other.instructions {
+            self.push(instruction);
+        }
+        self.blocks.extend(other.blocks);
+    }
+
+    /// Applies `map` to the operands of all instructions, see [Instruction::map_values].
+    pub fn map_values(&mut self, map: &dyn Fn(&Value) -> Option<Value>) {
+        for instruction in self.instructions.iter_mut().chain(
+            self.blocks
+                .iter_mut()
+                .flat_map(|block| block.instructions.iter_mut()),
+        ) {
+            instruction.map_values(map);
+        }
+    }
+
+    /// Returns the LLVM syntax of the fragment, with indented instructions.
+    pub fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        let mut output = self
+            .instructions
+            .iter()
+            .map(|instruction| format!("  {}", instruction.to_qir(pointer_style)))
+            .collect::<Vec<String>>()
+            .join("\n");
+        for block in self.blocks.iter() {
+            output.push_str(&format!("\n\n{}:", block.label));
+            for instruction in block.instructions.iter() {
+                output.push_str(&format!("\n  {}", instruction.to_qir(pointer_style)));
+            }
+        }
+        output
+    }
+}
+
+/// Parameter of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    /// Type of the parameter.
+    pub ty: Type,
+    /// Name of the parameter without the `%` sigil, omitted in declarations.
+    pub name: Option<String>,
+    /// Parameter attributes such as `writeonly`.
+    pub attributes: Vec<String>,
+}
+
+impl Parameter {
+    /// Creates an unnamed parameter without attributes.
+    pub fn new(ty: Type) -> Self {
+        Parameter {
+            ty,
+            name: None,
+            attributes: vec![],
+        }
+    }
+
+    /// Creates a named parameter without attributes.
+    pub fn named(ty: Type, name: impl Into<String>) -> Self {
+        Parameter {
+            ty,
+            name: Some(name.into()),
+            attributes: vec![],
+        }
+    }
+
+    /// Returns the parameter as a local value that can be used in the function body.
+    pub fn value(&self) -> Option<Value> {
+        self.name.as_ref().map(|name| Value::local(name, self.ty))
+    }
+
+    fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        let mut output = self.ty.to_qir(pointer_style);
+        for attribute in self.attributes.iter() {
+            output.push(' ');
+            output.push_str(attribute);
+        }
+        if let Some(name) = &self.name {
+            output.push_str(&format!(" %{}", name));
+        }
+        output
+    }
+}
+
+/// Function definition or, if it has no basic blocks, function declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name of the function without the `@` sigil.
+    pub name: String,
+    /// Return type of the function.
+    pub return_type: Type,
+    /// Parameters of the function.
+    pub parameters: Vec<Parameter>,
+    /// Attribute group of the function.
+    pub attribute_group: Option<u32>,
+    /// Basic blocks of the function body, empty for declarations.
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Function {
+    /// Creates a declaration of an external function with unnamed parameters.
+    pub fn declaration(name: impl Into<String>, return_type: Type, parameters: &[Type]) -> Self {
+        Function {
+            name: name.into(),
+            return_type,
+            parameters: parameters.iter().map(|&ty| Parameter::new(ty)).collect(),
+            attribute_group: None,
+            blocks: vec![],
+        }
+    }
+
+    /// Creates a function definition with an empty `entry` block.
+    pub fn definition(
+        name: impl Into<String>,
+        return_type: Type,
+        parameters: Vec<Parameter>,
+    ) -> Self {
+        Function {
+            name: name.into(),
+            return_type,
+            parameters,
+            attribute_group: None,
+            blocks: vec![BasicBlock::new("entry")],
+        }
+    }
+
+    /// Returns true if the function is only declared and has no body.
+    pub fn is_declaration(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the local values used in the body that are neither parameters nor defined by an instruction.
+    ///
+    /// The values are returned in order of first use.
+    pub fn free_values(&self) -> Vec<(String, Type)> {
+        let instructions = || {
+            self.blocks
+                .iter()
+                .flat_map(|block| block.instructions.iter())
+        };
+        let mut free_values: Vec<(String, Type)> = vec![];
+        for value in instructions().flat_map(|instruction| instruction.values()) {
+            if let Value::Local { name, ty } = value {
+                let is_defined = self
+                    .parameters
+                    .iter()
+                    .any(|parameter| parameter.name.as_ref() == Some(name))
+                    || instructions().any(|instruction| instruction.result() == Some(name));
+                if !is_defined && !free_values.iter().any(|(free, _)| free == name) {
+                    free_values.push((name.clone(), *ty));
+                }
+            }
+        }
+        free_values
+    }
+
+    /// Appends a fragment to the end of the function body.
+    pub fn append(&mut self, fragment: Fragment) {
+        if self.blocks.is_empty() {
+            self.blocks.push(BasicBlock::new("entry"));
+        }
+        if let Some(block) = self.blocks.last_mut() {
+            block.instructions.extend(fragment.instructions);
+        }
+        self.blocks.extend(fragment.blocks);
+    }
+
+    /// Appends an instruction to the last basic block of the function.
+    pub fn push(&mut self, instruction: Instruction) {
+        self.append(Fragment::from(instruction));
+    }
+
+    /// Returns the LLVM syntax of the declaration or definition.
+    pub fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        let mut output = String::new();
+        // Writing to a String cannot fail
+        let _ = self.write_qir(&mut output, pointer_style);
+        output
+    }
+
+    /// Writes the LLVM syntax of the declaration or definition one instruction at a time.
+    pub fn write_qir<W: fmt::Write>(
+        &self,
+        output: &mut W,
+        pointer_style: PointerStyle,
+    ) -> fmt::Result {
+        write!(
+            output,
+            "{} {} @{}({}){}",
+            if self.is_declaration() {
+                "declare"
+            } else {
+                "define"
+            },
+            self.return_type.to_qir(pointer_style),
+            self.name,
+            self.parameters
+                .iter()
+                .map(|parameter| parameter.to_qir(pointer_style))
+                .collect::<Vec<String>>()
+                .join(", "),
+            self.attribute_group
+                .map(|id| format!(" #{}", id))
+                .unwrap_or_default()
+        )?;
+        if self.is_declaration() {
+            return Ok(());
+        }
+        output.write_str(" {")?;
+        for (index, block) in self.blocks.iter().enumerate() {
+            if index > 0 {
+                output.write_char('\n')?;
+            }
+            write!(output, "\n{}:", block.label)?;
+            for instruction in block.instructions.iter() {
+                write!(output, "\n  {}", instruction.to_qir(pointer_style))?;
+            }
+        }
+        output.write_str("\n}")
+    }
+}
+
+/// Function attribute, either a plain key or a key-value pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    /// Key of the attribute.
+    pub key: String,
+    /// Optional value of the attribute.
+    pub value: Option<String>,
+}
+
+impl Attribute {
+    /// Creates an attribute consisting only of a key.
+    pub fn key(key: impl Into<String>) -> Self {
+        Attribute {
+            key: key.into(),
+            value: None,
+        }
+    }
+
+    /// Creates a key-value attribute.
+    pub fn key_value(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Attribute {
+            key: key.into(),
+            value: Some(value.into()),
+        }
+    }
+
+    fn to_qir(&self) -> String {
+        match &self.value {
+            Some(value) => format!("\"{}\"=\"{}\"", self.key, value),
+            None => format!("\"{}\"", self.key),
+        }
+    }
+}
+
+/// Attribute group referenced by functions and call sites as `#id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeGroup {
+    /// Number of the attribute group.
+    pub id: u32,
+    /// Attributes in the group.
+    pub attributes: Vec<Attribute>,
+}
+
+impl AttributeGroup {
+    /// Returns the LLVM syntax of the attribute group.
+    pub fn to_qir(&self) -> String {
+        format!(
+            "attributes #{} = {{ {} }}",
+            self.id,
+            self.attributes
+                .iter()
+                .map(Attribute::to_qir)
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    }
+}
+
+/// Module flag in the `!llvm.module.flags` metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleFlag {
+    /// Behavior when linking modules with the same flag, e.g. 1 (error) or 7 (max).
+    pub behavior: u32,
+    /// Name of the flag.
+    pub name: String,
+    /// Value of the flag.
+    pub value: ModuleFlagValue,
+}
+
+/// Value of a [ModuleFlag].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModuleFlagValue {
+    /// Constant value, e.g. `i1 true`.
+    Constant(Value),
+    /// Metadata list of strings, e.g. `!{!"double"}`.
+    Strings(Vec<String>),
+}
+
+impl From<Value> for ModuleFlagValue {
+    fn from(value: Value) -> Self {
+        ModuleFlagValue::Constant(value)
+    }
+}
+
+impl ModuleFlag {
+    /// Creates a new module flag.
+    pub fn new(behavior: u32, name: impl Into<String>, value: impl Into<ModuleFlagValue>) -> Self {
+        ModuleFlag {
+            behavior,
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Creates a module flag with a list of strings and behavior 5 (append unique).
+    pub fn strings<S: Into<String>>(
+        name: impl Into<String>,
+        values: impl IntoIterator<Item = S>,
+    ) -> Self {
+        ModuleFlag::new(
+            5,
+            name,
+            ModuleFlagValue::Strings(values.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    /// Creates a module flag with a boolean `i1` value and behavior 1 (error).
+    pub fn boolean(name: impl Into<String>, value: bool) -> Self {
+        ModuleFlag::new(
+            1,
+            name,
+            Value::Integer {
+                bits: 1,
+                value: value as i64,
+            },
+        )
+    }
+
+    fn to_qir(&self, pointer_style: PointerStyle) -> String {
+        format!(
+            "!{{i32 {}, !\"{}\", {}}}",
+            self.behavior,
+            self.name,
+            match &self.value {
+                ModuleFlagValue::Constant(value) => value.to_typed_qir(pointer_style),
+                ModuleFlagValue::Strings(values) => format!(
+                    "!{{{}}}",
+                    values
+                        .iter()
+                        .map(|value| format!("!\"{value}\""))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+            }
+        )
+    }
+}
+
+/// QIR module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// Pointer style used when printing the module.
+    pub pointer_style: PointerStyle,
+    /// Names of the opaque struct types used by the module, e.g. `Qubit`.
+    ///
+    /// They are only defined when printing typed pointers.
+    pub opaque_types: Vec<String>,
+    /// Function definitions and declarations, printed in order.
+    pub functions: Vec<Function>,
+    /// Attribute groups of functions and call sites.
+    pub attribute_groups: Vec<AttributeGroup>,
+    /// Module flags.
+    pub module_flags: Vec<ModuleFlag>,
+}
+
+impl Module {
+    /// Creates an empty module.
+    pub fn new(pointer_style: PointerStyle) -> Self {
+        Module {
+            pointer_style,
+            opaque_types: vec![],
+            functions: vec![],
+            attribute_groups: vec![],
+            module_flags: vec![],
+        }
+    }
+
+    /// Returns the function with the given name, if it is defined or declared in the module.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl fmt::Display for Module {
+    /// Prints the module as LLVM IR text.
+    ///
+    /// Sections (type definitions, each function definition, each run of consecutive
+    /// declarations, the attribute groups and the module flags) are separated by one blank line.
+    /// The module is written line by line, so printing it to an [std::io::Write] with `write!`
+    /// does not collect the whole text into a String.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer_style = self.pointer_style;
+        let mut is_first_section = true;
+        let mut start_section = |f: &mut fmt::Formatter<'_>| {
+            if is_first_section {
+                is_first_section = false;
+                Ok(())
+            } else {
+                f.write_str("\n\n")
+            }
+        };
+        if pointer_style == PointerStyle::Typed && !self.opaque_types.is_empty() {
+            start_section(f)?;
+            for (index, name) in self.opaque_types.iter().enumerate() {
+                if index > 0 {
+                    f.write_str("\n")?;
+                }
+                write!(f, "%{} = type opaque", name)?;
+            }
+        }
+        let mut previous_was_declaration = false;
+        for function in self.functions.iter() {
+            if previous_was_declaration && function.is_declaration() {
+                f.write_str("\n")?;
+            } else {
+                start_section(f)?;
+            }
+            function.write_qir(f, pointer_style)?;
+            previous_was_declaration = function.is_declaration();
+        }
+        if !self.attribute_groups.is_empty() {
+            start_section(f)?;
+            for (index, attribute_group) in self.attribute_groups.iter().enumerate() {
+                if index > 0 {
+                    f.write_str("\n")?;
+                }
+                f.write_str(&attribute_group.to_qir())?;
+            }
+        }
+        if !self.module_flags.is_empty() {
+            start_section(f)?;
+            write!(
+                f,
+                "!llvm.module.flags = !{{{}}}",
+                (0..self.module_flags.len())
+                    .map(|index| format!("!{}", index))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?;
+        }
+        for (index, flag) in self.module_flags.iter().enumerate() {
+            let separator = if index == 0 { "\n\n" } else { "\n" };
+            write!(
+                f,
+                "{}!{} = {}",
+                separator,
+                index,
+                flag.to_qir(pointer_style)
+            )?;
+        }
+        Ok(())
+    }
+}
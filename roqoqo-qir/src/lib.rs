@@ -25,5 +25,16 @@
 
 mod backend;
 pub use backend::*;
+#[cfg(feature = "bitcode")]
+mod bitcode;
+mod error;
+pub use error::*;
+mod expression;
+pub use expression::*;
 mod interface;
 pub use interface::*;
+pub mod ir;
+mod profile;
+pub use profile::*;
+mod synthesis;
+pub use synthesis::*;
@@ -0,0 +1,191 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use qoqo_calculator::{Calculator, CalculatorError, CalculatorFloat};
+use roqoqo::{operations::*, Circuit, RoqoqoError};
+
+use crate::{profile_name, QirError, QirProfile};
+
+/// Operations that can not be expressed in the QIR Base Profile.
+const BASE_PROFILE_FORBIDDEN_OPERATIONS: &[&str; 2] = &["PragmaConditional", "PragmaActiveReset"];
+
+/// Checks that a circuit conforms to a QIR profile and prepares it for translation.
+///
+/// Neither profile allows backwards branching, every PragmaLoop with a fixed number of
+/// repetitions is therefore unrolled. In the Base Profile, operations requiring mid-circuit
+/// measurement results or qubit reuse are rejected, as are free symbolic parameters.
+///
+/// # Arguments
+///
+/// * `circuit` - The Circuit that is checked
+/// * `qir_profile` - The QIR profile the circuit has to conform to
+///
+/// # Returns
+///
+/// * `Ok(Circuit)` - The circuit with all PragmaLoops unrolled
+/// * `QirError::ProfileViolation` - An operation is not allowed in the profile
+/// * `QirError::UnboundParameter` - A PragmaLoop has a symbolic number of repetitions, or an
+///   operation uses a free symbolic parameter in the Base Profile
+pub fn prepare_circuit_for_profile(
+    circuit: &Circuit,
+    qir_profile: QirProfile,
+) -> Result<Circuit, QirError> {
+    if let QirProfile::BaseProfile = qir_profile {
+        check_base_profile(circuit)?;
+        check_bound_parameters(circuit)?;
+    }
+    unroll_loops(circuit)
+}
+
+/// Returns an error for the first operation that is not allowed in the QIR Base Profile.
+fn check_base_profile(circuit: &Circuit) -> Result<(), QirError> {
+    for (index, operation) in circuit.iter().enumerate() {
+        if BASE_PROFILE_FORBIDDEN_OPERATIONS.contains(&operation.hqslang()) {
+            return Err(QirError::ProfileViolation {
+                index,
+                hqslang: operation.hqslang(),
+                profile: profile_name(QirProfile::BaseProfile),
+            });
+        }
+        let inner_circuit = match operation {
+            Operation::PragmaLoop(pragma_loop) => pragma_loop.circuit(),
+            Operation::GateDefinition(gate_definition) => gate_definition.circuit(),
+            _ => continue,
+        };
+        check_base_profile(inner_circuit).map_err(|error| error.locate(index, operation))?;
+    }
+    Ok(())
+}
+
+/// Returns an error for the first operation using a symbolic parameter without a value.
+///
+/// The Base Profile allows neither float computations nor entry point parameters, so every
+/// symbol has to be substituted before translation or set by an InputSymbolic operation. The
+/// circuits of GateDefinitions are skipped, their free parameters are bound by the CallDefinedGate
+/// operations using them.
+fn check_bound_parameters(circuit: &Circuit) -> Result<(), QirError> {
+    let mut calculator = Calculator::new();
+    for (index, operation) in circuit.iter().enumerate() {
+        match operation {
+            Operation::InputSymbolic(input_symbolic) => {
+                calculator.set_variable(input_symbolic.name(), *input_symbolic.input());
+                continue;
+            }
+            Operation::GateDefinition(_) => continue,
+            _ => (),
+        }
+        if let Err(RoqoqoError::CalculatorError(CalculatorError::VariableNotSet { name })) =
+            substitute_operation(operation, &calculator)
+        {
+            return Err(QirError::UnboundParameter {
+                index,
+                hqslang: operation.hqslang(),
+                parameter: name,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Substitutes the symbolic parameters of an operation with the values of a calculator.
+///
+/// roqoqo panics when a symbol in the circuit of a PragmaConditional, PragmaControlledCircuit or
+/// PragmaGetPauliProduct has no value, so the circuits of these operations and of PragmaLoops are
+/// substituted here and the error is returned instead. GateDefinitions are kept as they are,
+/// their free parameters are bound by the CallDefinedGate operations using them.
+pub(crate) fn substitute_operation(
+    operation: &Operation,
+    calculator: &Calculator,
+) -> Result<Operation, RoqoqoError> {
+    match operation {
+        Operation::GateDefinition(_) => Ok(operation.clone()),
+        Operation::PragmaConditional(op) => Ok(Operation::from(PragmaConditional::new(
+            op.condition_register().clone(),
+            *op.condition_index(),
+            substitute_circuit(op.circuit(), calculator)?,
+        ))),
+        Operation::PragmaControlledCircuit(op) => {
+            Ok(Operation::from(PragmaControlledCircuit::new(
+                *op.controlling_qubit(),
+                substitute_circuit(op.circuit(), calculator)?,
+            )))
+        }
+        Operation::PragmaGetPauliProduct(op) => Ok(Operation::from(PragmaGetPauliProduct::new(
+            op.qubit_paulis().clone(),
+            op.readout().clone(),
+            substitute_circuit(op.circuit(), calculator)?,
+        ))),
+        Operation::PragmaLoop(op) => Ok(Operation::from(PragmaLoop::new(
+            calculator.parse_get(op.repetitions().clone())?.into(),
+            substitute_circuit(op.circuit(), calculator)?,
+        ))),
+        _ => operation.substitute_parameters(calculator),
+    }
+}
+
+/// Substitutes the symbolic parameters of a circuit, including the symbols set by InputSymbolic.
+fn substitute_circuit(circuit: &Circuit, calculator: &Calculator) -> Result<Circuit, RoqoqoError> {
+    let mut calculator = calculator.clone();
+    let mut new_circuit = Circuit::new();
+    for operation in circuit.iter() {
+        let substituted = substitute_operation(operation, &calculator)?;
+        if let Operation::InputSymbolic(input_symbolic) = &substituted {
+            calculator.set_variable(input_symbolic.name(), *input_symbolic.input());
+        }
+        new_circuit.add_operation(substituted);
+    }
+    Ok(new_circuit)
+}
+
+/// Replaces every PragmaLoop in the circuit by its repeated body.
+///
+/// The circuits of GateDefinitions and PragmaConditionals are unrolled recursively.
+fn unroll_loops(circuit: &Circuit) -> Result<Circuit, QirError> {
+    let mut new_circuit = Circuit::new();
+    for (index, operation) in circuit.iter().enumerate() {
+        let locate = |error: QirError| error.locate(index, operation);
+        match operation {
+            Operation::PragmaLoop(pragma_loop) => match pragma_loop.repetitions() {
+                CalculatorFloat::Float(repetitions) => {
+                    let unrolled_body = unroll_loops(pragma_loop.circuit()).map_err(locate)?;
+                    for _ in 0..(repetitions.floor() as usize) {
+                        new_circuit += unrolled_body.clone();
+                    }
+                }
+                CalculatorFloat::Str(s) => {
+                    return Err(QirError::UnboundParameter {
+                        index,
+                        hqslang: operation.hqslang(),
+                        parameter: s.to_owned(),
+                    })
+                }
+            },
+            Operation::PragmaConditional(pragma_conditional) => {
+                new_circuit.add_operation(PragmaConditional::new(
+                    pragma_conditional.condition_register().to_owned(),
+                    *pragma_conditional.condition_index(),
+                    unroll_loops(pragma_conditional.circuit()).map_err(locate)?,
+                ))
+            }
+            Operation::GateDefinition(gate_definition) => {
+                new_circuit.add_operation(GateDefinition::new(
+                    unroll_loops(gate_definition.circuit()).map_err(locate)?,
+                    gate_definition.name().to_owned(),
+                    gate_definition.qubits().to_owned(),
+                    gate_definition.free_parameters().to_owned(),
+                ))
+            }
+            _ => new_circuit.add_operation(operation.clone()),
+        }
+    }
+    Ok(new_circuit)
+}
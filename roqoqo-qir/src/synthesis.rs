@@ -0,0 +1,440 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ndarray::Array2;
+use num_complex::Complex64;
+use qoqo_calculator::CalculatorFloat;
+use roqoqo::{operations::*, Circuit, RoqoqoBackendError};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+type Matrix2 = [[Complex64; 2]; 2];
+type Matrix4 = [[Complex64; 4]; 4];
+
+/// Rotations with an angle below this threshold are left out of synthesized circuits.
+const ANGLE_TOLERANCE: f64 = 1e-12;
+
+/// Tolerance of the unitarity check and of the diagonalization in the magic basis.
+const MATRIX_TOLERANCE: f64 = 1e-8;
+
+/// Real weights of the imaginary part in the symmetric matrix diagonalized by the KAK decomposition.
+///
+/// Any weight works for a generic unitary, the following ones are only tried when the
+/// eigenvalues of the previous combination are degenerate.
+const DIAGONALIZATION_WEIGHTS: [f64; 3] = [
+    0.577_215_664_901_532_9,
+    1.324_717_957_244_746,
+    2.502_907_875_095_893,
+];
+
+const ZERO: Complex64 = Complex64::new(0.0, 0.0);
+const SQRT_HALF: Complex64 = Complex64::new(FRAC_1_SQRT_2, 0.0);
+const MINUS_SQRT_HALF: Complex64 = Complex64::new(-FRAC_1_SQRT_2, 0.0);
+const I_SQRT_HALF: Complex64 = Complex64::new(0.0, FRAC_1_SQRT_2);
+const MINUS_I_SQRT_HALF: Complex64 = Complex64::new(0.0, -FRAC_1_SQRT_2);
+
+/// Magic basis in which local two-qubit gates are real orthogonal matrices.
+const MAGIC_BASIS: Matrix4 = [
+    [SQRT_HALF, ZERO, ZERO, I_SQRT_HALF],
+    [ZERO, I_SQRT_HALF, SQRT_HALF, ZERO],
+    [ZERO, I_SQRT_HALF, MINUS_SQRT_HALF, ZERO],
+    [SQRT_HALF, ZERO, ZERO, MINUS_I_SQRT_HALF],
+];
+
+/// Eigenvalues of XX, YY and ZZ for the vectors of the magic basis.
+const MAGIC_BASIS_EIGENVALUES: [[f64; 4]; 3] = [
+    [1.0, 1.0, -1.0, -1.0],
+    [-1.0, 1.0, -1.0, 1.0],
+    [1.0, -1.0, -1.0, 1.0],
+];
+
+/// Synthesizes a single- or two-qubit gate from its unitary matrix.
+///
+/// Gates without a translation of their own are lowered this way to RotateZ, RotateY and CNOT
+/// operations. The synthesized circuit is equal to the gate up to a global phase.
+///
+/// # Arguments
+///
+/// * `operation` - The gate that is synthesized
+///
+/// # Returns
+///
+/// * `Ok(Circuit)` - The circuit implementing the gate
+/// * `RoqoqoBackendError::OperationNotInBackend` - The operation is not a single- or two-qubit gate
+/// * `RoqoqoBackendError::RoqoqoError` - The unitary matrix of the gate could not be constructed,
+///   e.g. because of symbolic parameters
+pub fn synthesize_gate(operation: &Operation) -> Result<Circuit, RoqoqoBackendError> {
+    if let Ok(gate) = SingleQubitGateOperation::try_from(operation.clone()) {
+        return synthesize_single_qubit_unitary(&gate.unitary_matrix()?, *gate.qubit());
+    }
+    if let Ok(gate) = TwoQubitGateOperation::try_from(operation.clone()) {
+        return synthesize_two_qubit_unitary(
+            &gate.unitary_matrix()?,
+            *gate.control(),
+            *gate.target(),
+        );
+    }
+    Err(RoqoqoBackendError::OperationNotInBackend {
+        backend: "QirBackend",
+        hqslang: operation.hqslang(),
+    })
+}
+
+/// Returns true if the operation is a gate that can be passed to [synthesize_gate].
+pub(crate) fn is_synthesizable(operation: &Operation) -> bool {
+    SingleQubitGateOperation::try_from(operation.clone()).is_ok()
+        || TwoQubitGateOperation::try_from(operation.clone()).is_ok()
+}
+
+/// Synthesizes a single-qubit unitary matrix as RotateZ, RotateY, RotateZ.
+///
+/// # Arguments
+///
+/// * `matrix` - The 2x2 unitary matrix
+/// * `qubit` - The qubit the unitary acts on
+///
+/// # Returns
+///
+/// * `Ok(Circuit)` - The circuit implementing the unitary up to a global phase
+/// * `RoqoqoBackendError::GenericError` - The matrix is not a 2x2 unitary matrix
+pub fn synthesize_single_qubit_unitary(
+    matrix: &Array2<Complex64>,
+    qubit: usize,
+) -> Result<Circuit, RoqoqoBackendError> {
+    check_unitary(matrix, 2)?;
+    let mut circuit = Circuit::new();
+    add_zyz_rotations(
+        &mut circuit,
+        &[
+            [matrix[[0, 0]], matrix[[0, 1]]],
+            [matrix[[1, 0]], matrix[[1, 1]]],
+        ],
+        qubit,
+    );
+    Ok(circuit)
+}
+
+/// Synthesizes a two-qubit unitary matrix with the KAK decomposition.
+///
+/// The matrix is given in the basis `|control, target>`, with the control qubit as the most
+/// significant one, like the unitary matrices of roqoqo two-qubit gates. It is decomposed into
+/// `(A1 ⊗ B1) exp(i (a XX + b YY + c ZZ)) (A0 ⊗ B0)`. The single-qubit gates are synthesized as
+/// RotateZ, RotateY, RotateZ and each of the three commuting rotations with two CNOTs.
+///
+/// # Arguments
+///
+/// * `matrix` - The 4x4 unitary matrix
+/// * `control` - The qubit of the most significant index of the matrix
+/// * `target` - The qubit of the least significant index of the matrix
+///
+/// # Returns
+///
+/// * `Ok(Circuit)` - The circuit implementing the unitary up to a global phase
+/// * `RoqoqoBackendError::GenericError` - The matrix is not a 4x4 unitary matrix
+pub fn synthesize_two_qubit_unitary(
+    matrix: &Array2<Complex64>,
+    control: usize,
+    target: usize,
+) -> Result<Circuit, RoqoqoBackendError> {
+    check_unitary(matrix, 4)?;
+    let mut unitary = [[ZERO; 4]; 4];
+    for (row, unitary_row) in unitary.iter_mut().enumerate() {
+        for (column, entry) in unitary_row.iter_mut().enumerate() {
+            *entry = matrix[[row, column]];
+        }
+    }
+    // Moving to SU(4) and into the magic basis, where U^T U is diagonalized by a real rotation
+    let normalization = determinant(&unitary).powf(0.25);
+    let special_unitary = unitary.map(|row| row.map(|entry| entry / normalization));
+    let magic = multiply(
+        &multiply(&adjoint(&MAGIC_BASIS), &special_unitary),
+        &MAGIC_BASIS,
+    );
+    let symmetric = multiply(&transpose(&magic), &magic);
+    let (mut rotation, diagonal) = diagonalize_symmetric_unitary(&symmetric)?;
+    if determinant(&rotation).re < 0.0 {
+        for row in rotation.iter_mut() {
+            row[0] = -row[0];
+        }
+    }
+    let mut square_roots = diagonal.map(|eigenvalue| eigenvalue.sqrt());
+    if square_roots.iter().product::<Complex64>().re < 0.0 {
+        square_roots[0] = -square_roots[0];
+    }
+    let mut inverse_square_roots = [[ZERO; 4]; 4];
+    for (index, root) in square_roots.iter().enumerate() {
+        inverse_square_roots[index][index] = root.inv();
+    }
+    let local_after = multiply(
+        &multiply(
+            &MAGIC_BASIS,
+            &multiply(&multiply(&magic, &rotation), &inverse_square_roots),
+        ),
+        &adjoint(&MAGIC_BASIS),
+    );
+    let local_before = multiply(
+        &multiply(&MAGIC_BASIS, &transpose(&rotation)),
+        &adjoint(&MAGIC_BASIS),
+    );
+    // Solving arg(sqrt(d_k)) = g + a xx_k + b yy_k + c zz_k exactly, the global phase g is dropped
+    let phases = square_roots.map(|root| root.arg());
+    let coefficients = MAGIC_BASIS_EIGENVALUES
+        .map(|eigenvalues| (0..4).map(|k| eigenvalues[k] * phases[k]).sum::<f64>() / 4.0);
+
+    let mut circuit = Circuit::new();
+    let (before_control, before_target) = factor_tensor_product(&local_before);
+    add_zyz_rotations(&mut circuit, &before_control, control);
+    add_zyz_rotations(&mut circuit, &before_target, target);
+    for (axis, coefficient) in ['x', 'y', 'z'].iter().zip(coefficients) {
+        add_pauli_pair_rotation(&mut circuit, *axis, -2.0 * coefficient, control, target);
+    }
+    let (after_control, after_target) = factor_tensor_product(&local_after);
+    add_zyz_rotations(&mut circuit, &after_control, control);
+    add_zyz_rotations(&mut circuit, &after_target, target);
+    Ok(circuit)
+}
+
+/// Returns an error if the matrix is not a unitary matrix of the given dimension.
+fn check_unitary(matrix: &Array2<Complex64>, dimension: usize) -> Result<(), RoqoqoBackendError> {
+    if matrix.dim() != (dimension, dimension) {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Expected a {dimension}x{dimension} unitary matrix, got a {}x{} matrix",
+                matrix.nrows(),
+                matrix.ncols()
+            ),
+        });
+    }
+    for row in 0..dimension {
+        for column in 0..dimension {
+            let product: Complex64 = (0..dimension)
+                .map(|k| matrix[[k, row]].conj() * matrix[[k, column]])
+                .sum();
+            let expected = if row == column { 1.0 } else { 0.0 };
+            if (product - expected).norm() > MATRIX_TOLERANCE {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: "Matrix is not unitary".to_owned(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds the RotateZ, RotateY, RotateZ sequence of a single-qubit unitary to the circuit.
+///
+/// After removing the determinant, the unitary is `[[alpha, -conj(beta)], [beta, conj(alpha)]]`,
+/// which is `rz(a) ry(b) rz(c)` with `b = 2 atan2(|beta|, |alpha|)`,
+/// `a = arg(beta) - arg(alpha)` and `c = -arg(alpha) - arg(beta)`.
+fn add_zyz_rotations(circuit: &mut Circuit, unitary: &Matrix2, qubit: usize) {
+    let normalization = (unitary[0][0] * unitary[1][1] - unitary[0][1] * unitary[1][0]).sqrt();
+    let alpha = unitary[0][0] / normalization;
+    let beta = unitary[1][0] / normalization;
+    let theta_y = 2.0 * beta.norm().atan2(alpha.norm());
+    let theta_z_first = -alpha.arg() - beta.arg();
+    let theta_z_last = beta.arg() - alpha.arg();
+    if theta_z_first.abs() > ANGLE_TOLERANCE {
+        circuit.add_operation(RotateZ::new(qubit, CalculatorFloat::from(theta_z_first)));
+    }
+    if theta_y.abs() > ANGLE_TOLERANCE {
+        circuit.add_operation(RotateY::new(qubit, CalculatorFloat::from(theta_y)));
+    }
+    if theta_z_last.abs() > ANGLE_TOLERANCE {
+        circuit.add_operation(RotateZ::new(qubit, CalculatorFloat::from(theta_z_last)));
+    }
+}
+
+/// Adds the rotation `exp(-i theta/2 P P)` for the Pauli matrix `P` of the axis to the circuit.
+fn add_pauli_pair_rotation(
+    circuit: &mut Circuit,
+    axis: char,
+    theta: f64,
+    control: usize,
+    target: usize,
+) {
+    if theta.abs() <= ANGLE_TOLERANCE {
+        return;
+    }
+    for qubit in [control, target] {
+        match axis {
+            'x' => circuit.add_operation(RotateY::new(qubit, -CalculatorFloat::FRAC_PI_2)),
+            'y' => circuit.add_operation(RotateX::new(qubit, CalculatorFloat::FRAC_PI_2)),
+            _ => (),
+        }
+    }
+    circuit.add_operation(CNOT::new(control, target));
+    circuit.add_operation(RotateZ::new(target, CalculatorFloat::from(theta)));
+    circuit.add_operation(CNOT::new(control, target));
+    for qubit in [control, target] {
+        match axis {
+            'x' => circuit.add_operation(RotateY::new(qubit, CalculatorFloat::FRAC_PI_2)),
+            'y' => circuit.add_operation(RotateX::new(qubit, -CalculatorFloat::FRAC_PI_2)),
+            _ => (),
+        }
+    }
+}
+
+/// Diagonalizes a complex symmetric unitary matrix with a real rotation.
+///
+/// The real and imaginary parts of such a matrix are commuting real symmetric matrices, the
+/// eigenvectors of a weighted sum of both therefore diagonalize the matrix.
+///
+/// # Returns
+///
+/// * `Ok((Matrix4, [Complex64; 4]))` - The orthogonal eigenvector matrix and the eigenvalues
+/// * `RoqoqoBackendError::GenericError` - None of the weighted sums could be used
+fn diagonalize_symmetric_unitary(
+    symmetric: &Matrix4,
+) -> Result<(Matrix4, [Complex64; 4]), RoqoqoBackendError> {
+    for weight in DIAGONALIZATION_WEIGHTS {
+        let real_symmetric = symmetric.map(|row| row.map(|entry| entry.re + weight * entry.im));
+        let eigenvectors = jacobi_eigenvectors(real_symmetric)
+            .map(|row| row.map(|entry| Complex64::new(entry, 0.0)));
+        let diagonalized = multiply(
+            &multiply(&transpose(&eigenvectors), symmetric),
+            &eigenvectors,
+        );
+        let is_diagonal = (0..4).all(|row| {
+            (0..4)
+                .all(|column| row == column || diagonalized[row][column].norm() < MATRIX_TOLERANCE)
+        });
+        if is_diagonal {
+            return Ok((
+                eigenvectors,
+                [0, 1, 2, 3].map(|index| diagonalized[index][index]),
+            ));
+        }
+    }
+    Err(RoqoqoBackendError::GenericError {
+        msg: "Two-qubit unitary could not be diagonalized in the magic basis".to_owned(),
+    })
+}
+
+/// Returns the eigenvectors of a real symmetric matrix as columns, using cyclic Jacobi rotations.
+fn jacobi_eigenvectors(mut matrix: [[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut eigenvectors = [[0.0; 4]; 4];
+    for (index, row) in eigenvectors.iter_mut().enumerate() {
+        row[index] = 1.0;
+    }
+    for _ in 0..100 {
+        let off_diagonal: f64 = (0..4)
+            .flat_map(|row| (0..4).map(move |column| (row, column)))
+            .filter(|(row, column)| row != column)
+            .map(|(row, column)| matrix[row][column].powi(2))
+            .sum();
+        if off_diagonal < 1e-30 {
+            break;
+        }
+        for p in 0..4 {
+            for q in (p + 1)..4 {
+                if matrix[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (matrix[q][q] - matrix[p][p]) / (2.0 * matrix[p][q]);
+                let tangent = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let cosine = 1.0 / (tangent * tangent + 1.0).sqrt();
+                let sine = tangent * cosine;
+                for row in matrix.iter_mut() {
+                    let (entry_p, entry_q) = (row[p], row[q]);
+                    row[p] = cosine * entry_p - sine * entry_q;
+                    row[q] = sine * entry_p + cosine * entry_q;
+                }
+                let (row_p, row_q) = (matrix[p], matrix[q]);
+                matrix[p] =
+                    std::array::from_fn(|column| cosine * row_p[column] - sine * row_q[column]);
+                matrix[q] =
+                    std::array::from_fn(|column| sine * row_p[column] + cosine * row_q[column]);
+                for row in eigenvectors.iter_mut() {
+                    let (entry_p, entry_q) = (row[p], row[q]);
+                    row[p] = cosine * entry_p - sine * entry_q;
+                    row[q] = sine * entry_p + cosine * entry_q;
+                }
+            }
+        }
+    }
+    eigenvectors
+}
+
+/// Splits a local two-qubit unitary into the single-qubit unitaries of its tensor product.
+///
+/// The factors are read off the rows and columns through the largest entry of the matrix, the
+/// phase between them is irrelevant for the synthesized circuit.
+fn factor_tensor_product(local: &Matrix4) -> (Matrix2, Matrix2) {
+    let mut largest = (0, 0);
+    for row in 0..4 {
+        for column in 0..4 {
+            if local[row][column].norm() > local[largest.0][largest.1].norm() {
+                largest = (row, column);
+            }
+        }
+    }
+    let (row_first, row_second) = (largest.0 / 2, largest.0 % 2);
+    let (column_first, column_second) = (largest.1 / 2, largest.1 % 2);
+    let second = [0, 1]
+        .map(|row| [0, 1].map(|column| local[2 * row_first + row][2 * column_first + column]));
+    let first = [0, 1].map(|row| {
+        [0, 1].map(|column| {
+            local[2 * row + row_second][2 * column + column_second]
+                / second[row_second][column_second]
+        })
+    });
+    (first, second)
+}
+
+fn multiply(left: &Matrix4, right: &Matrix4) -> Matrix4 {
+    let mut product = [[ZERO; 4]; 4];
+    for (row, product_row) in product.iter_mut().enumerate() {
+        for (column, entry) in product_row.iter_mut().enumerate() {
+            *entry = (0..4).map(|k| left[row][k] * right[k][column]).sum();
+        }
+    }
+    product
+}
+
+fn transpose(matrix: &Matrix4) -> Matrix4 {
+    [0, 1, 2, 3].map(|row| [0, 1, 2, 3].map(|column| matrix[column][row]))
+}
+
+fn adjoint(matrix: &Matrix4) -> Matrix4 {
+    transpose(matrix).map(|row| row.map(|entry| entry.conj()))
+}
+
+/// Determinant by Gaussian elimination with partial pivoting.
+fn determinant(matrix: &Matrix4) -> Complex64 {
+    let mut reduced = *matrix;
+    let mut determinant = Complex64::new(1.0, 0.0);
+    for column in 0..4 {
+        let pivot = (column..4)
+            .max_by(|&a, &b| {
+                reduced[a][column]
+                    .norm()
+                    .total_cmp(&reduced[b][column].norm())
+            })
+            .unwrap_or(column);
+        if reduced[pivot][column].norm() == 0.0 {
+            return ZERO;
+        }
+        if pivot != column {
+            reduced.swap(pivot, column);
+            determinant = -determinant;
+        }
+        determinant *= reduced[column][column];
+        for row in (column + 1)..4 {
+            let factor = reduced[row][column] / reduced[column][column];
+            let pivot_row = reduced[column];
+            for (entry, pivot_entry) in reduced[row][column..].iter_mut().zip(&pivot_row[column..])
+            {
+                *entry -= factor * pivot_entry;
+            }
+        }
+    }
+    determinant
+}
@@ -11,17 +11,29 @@
 // limitations under the License.
 //
 //! Testing the roqoqo-qir Backend
-//! run with `RUST_TEST_THREADS=1 cargo test`
 
-use std::{fs, path::Path, vec};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    vec,
+};
 
-use qoqo_calculator::CalculatorFloat;
-use roqoqo::{operations::*, Circuit};
-use roqoqo_qir::{Backend, NUMBER_LABEL, NUMBER_VARS};
-use serial_test::serial;
+use qoqo_calculator::{CalculatorError, CalculatorFloat};
+use roqoqo::{
+    measurements::{ClassicalRegister, PauliZProduct, PauliZProductInput},
+    operations::*,
+    Circuit, QuantumProgram, RoqoqoBackendError,
+};
+use roqoqo_qir::{
+    ir::{Instruction, Module},
+    Backend, PointerStyle, QirEntryPoint, QirError,
+};
+use test_case::test_case;
 
 #[test]
-#[serial]
 fn test_simple_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -31,7 +43,6 @@ fn test_simple_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_circuit_with_measure() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -42,7 +53,6 @@ fn test_circuit_with_measure() {
 }
 
 #[test]
-#[serial]
 fn test_qir_example_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -55,18 +65,16 @@ fn test_qir_example_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_xy_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
     circuit.add_operation(XY::new(0, 1, CalculatorFloat::FRAC_1_SQRT_2));
     circuit.add_operation(XY::new(2, 1, CalculatorFloat::PI));
     let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
-    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @xy(double -0.3535533905932738, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @xy(double -1.5707963267948966, %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\n\ndefine void @xy(double %theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double %theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double -1.5707963267948966, %Qubit* %qubit0)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @xy(double -0.3535533905932738, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @xy(double -1.5707963267948966, %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__ry__body(double, %Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rz__body(double, %Qubit*)\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\n\ndefine void @xy(double %theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double -1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double -1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* %qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
 }
 
 #[test]
-#[serial]
 fn test_qir_swap_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -77,7 +85,6 @@ fn test_qir_swap_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_iswap_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -88,7 +95,6 @@ fn test_qir_iswap_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_sqrtiswap_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -99,7 +105,6 @@ fn test_qir_sqrtiswap_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_inv_sqrtiswap_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -110,7 +115,6 @@ fn test_qir_inv_sqrtiswap_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_fswap_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -121,7 +125,6 @@ fn test_qir_fswap_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_pm_interaction_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -132,7 +135,6 @@ fn test_qir_pm_interaction_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_given_rotation_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -153,7 +155,6 @@ fn test_qir_given_rotation_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_given_rotation_little_endian_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -174,7 +175,6 @@ fn test_qir_given_rotation_little_endian_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_phase_shift_cz_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -185,7 +185,6 @@ fn test_qir_phase_shift_cz_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_phase_shift_ctrl_phase_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -206,29 +205,26 @@ fn test_qir_phase_shift_ctrl_phase_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_rxx_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
     circuit.add_operation(MolmerSorensenXX::new(0, 1));
     circuit.add_operation(VariableMSXX::new(2, 1, CalculatorFloat::PI));
     let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
-    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @rxx(double 0.0, double 0.0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @rxx(double 1.5707963267948966, double -1.5707963267948966, %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\n\ndefine void @rxx(double %half_theta, double %minus_half_theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__rx__body(double %half_theta, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double %half_theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double %minus_half_theta, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double %minus_half_theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @rxx(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @rxx(double 3.141592653589793, %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__ry__body(double, %Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rz__body(double, %Qubit*)\n\ndefine void @rxx(double %theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* %qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
 }
 
 #[test]
-#[serial]
 fn test_qir_cy_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
     circuit.add_operation(ControlledPauliY::new(0, 1));
     circuit.add_operation(ControlledPauliY::new(2, 1));
     let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
-    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @cy(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @cy(%Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__s__adj(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__s__body(%Qubit*)\n\ndefine void @cy(%Qubit* qubit0, %Qubit* qubit1) {\nentry:\n  call void @__quantum__qis__s__adj(%Qubit* qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* qubit0, %Qubit* qubit1)\n  call void @__quantum__qis__s__body(%Qubit* qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @cy(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @cy(%Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__s__adj(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__s__body(%Qubit*)\n\ndefine void @cy(%Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__s__adj(%Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__s__body(%Qubit* %qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
 }
 
 #[test]
-#[serial]
 fn test_qir_controlled_phase_shift_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -247,7 +243,6 @@ fn test_qir_controlled_phase_shift_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_rotate_xy_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -266,7 +261,6 @@ fn test_qir_rotate_xy_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_rotate_ccz_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -277,7 +271,6 @@ fn test_qir_rotate_ccz_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_qir_rotate_ccp_circuit() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -298,11 +291,12 @@ fn test_qir_rotate_ccp_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_conditional_circuit() {
-    *NUMBER_LABEL.lock().unwrap() = 0;
-    *NUMBER_VARS.lock().unwrap() = 0;
-    let backend = Backend::new(None, Some("0.1".to_string())).unwrap();
+    let backend = Backend::new(
+        Some("adaptive_profile".to_string()),
+        Some("0.1".to_string()),
+    )
+    .unwrap();
 
     let mut circuit_cond = Circuit::new();
     circuit_cond += PauliX::new(0);
@@ -323,14 +317,11 @@ fn test_conditional_circuit() {
     circuit += MeasureQubit::new(1, "ro".to_string(), 1);
     circuit += PragmaConditional::new("ro".to_owned(), 1, circuit_cond2);
     let qir_str = { backend.circuit_to_qir_str(&circuit, false).unwrap() };
-    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #1\n  %0 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 0 to %Result*))\n  br i1 %0, label %then0, label %continue0\n\nthen0:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 0.5, %Qubit* inttoptr (i64 1 to %Qubit*))\n  br label %continue0\n\ncontinue0:\n  call void @__quantum__qis__y__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Result* inttoptr (i64 1 to %Result*)) #1\n  %1 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 1 to %Result*))\n  br i1 %1, label %then1, label %continue1\n\nthen1:\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  br label %continue1\n\ncontinue1:\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\ndeclare i1 @__quantum__qis__read_result__body(%Result*)\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__y__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"2\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #1\n  %0 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 0 to %Result*))\n  br i1 %0, label %then0, label %continue0\n\nthen0:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 0.5, %Qubit* inttoptr (i64 1 to %Qubit*))\n  br label %continue0\n\ncontinue0:\n  call void @__quantum__qis__y__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Result* inttoptr (i64 1 to %Result*)) #1\n  %1 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 1 to %Result*))\n  br i1 %1, label %then1, label %continue1\n\nthen1:\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  br label %continue1\n\ncontinue1:\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\ndeclare i1 @__quantum__qis__read_result__body(%Result*)\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__y__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"2\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 false}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 false}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 false}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 false}\n!10 = !{i32 1, !\"extern_functions\", i1 false}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
 }
 
 #[test]
-#[serial]
 fn test_loop_circuit() {
-    *NUMBER_LABEL.lock().unwrap() = 0;
-    *NUMBER_VARS.lock().unwrap() = 0;
     let backend = Backend::new(None, Some("0.1".to_string())).unwrap();
 
     let mut circuitloop = Circuit::new();
@@ -349,10 +340,38 @@ fn test_loop_circuit() {
     circuit += PauliY::new(1);
     circuit += PragmaLoop::new(CalculatorFloat::from("3.2"), circuitloop2);
     let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
-    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  br label %header0\n\nheader0:\n  %0 = phi i64 [ 1, %entry ], [ %2, %loop0 ]\n  %1 = icmp slt i64 %0, 8\n  br i1 %1, label %loop0, label %continue0\n\nloop0:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  %2 = add i64 %0, 1\n  br label %header0\n\ncontinue0:\n  call void @__quantum__qis__y__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  br label %header1\n\nheader1:\n  %3 = phi i64 [ 1, %continue0 ], [ %5, %loop1 ]\n  %4 = icmp slt i64 %3, 4\n  br i1 %4, label %loop1, label %continue1\n\nloop1:\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  %5 = add i64 %3, 1\n  br label %header1\n\ncontinue1:\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__y__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rx__body(double 5.0, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__y__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__y__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
+
+/// Test that circuits translated concurrently are numbered independently
+#[test]
+fn test_concurrent_translation() {
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
+    let mut circuit_cond = Circuit::new();
+    circuit_cond += PauliX::new(1);
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += Hadamard::new(0);
+    circuit += MeasureQubit::new(0, "ro".to_string(), 0);
+    circuit += PragmaConditional::new("ro".to_owned(), 0, circuit_cond);
+    let expected = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert!(expected.contains("%0 = call i1 @__quantum__qis__read_result__body"));
+    assert!(expected.contains("then0:"));
+
+    let results: Vec<String> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| scope.spawn(|| backend.circuit_to_qir_str(&circuit, false).unwrap()))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    for qir_str in results {
+        assert_eq!(qir_str, expected);
+    }
 }
 #[test]
-#[serial]
 fn test_gate_definition_circuit() {
     let backend = Backend::new(None, Some("0.1".to_string())).unwrap();
 
@@ -367,9 +386,9 @@ fn test_gate_definition_circuit() {
     circuit += PauliY::new(0);
     circuit += GateDefinition::new(
         [
-            Operation::from(RotateX::new(0, CalculatorFloat::FRAC_PI_4)),
-            Operation::from(RotateZ::new(1, CalculatorFloat::from("phi"))),
-            Operation::from(MeasureQubit::new(1, "ro".to_owned(), 1)),
+            Operation::from(RotateX::new(1, CalculatorFloat::FRAC_PI_4)),
+            Operation::from(RotateZ::new(2, CalculatorFloat::from("phi"))),
+            Operation::from(MeasureQubit::new(2, "ro".to_owned(), 1)),
         ]
         .into_iter()
         .collect(),
@@ -407,7 +426,6 @@ fn test_gate_definition_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_simple_circuit_measure() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -417,9 +435,8 @@ fn test_simple_circuit_measure() {
 }
 
 #[test]
-#[serial]
 fn test_process_circuit() {
-    let backend = Backend::new(None, None).unwrap();
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
     let mut circuit = Circuit::new();
     circuit.add_operation(GateDefinition::new(
         Circuit::new(),
@@ -437,7 +454,7 @@ fn test_process_circuit() {
     gate_circ.add_operation(GateDefinition::new(
         circuit.clone(),
         "name2".to_owned(),
-        vec![0],
+        vec![0, 1],
         vec![],
     ));
     assert!(backend.circuit_to_qir_str(&circuit, false).is_ok());
@@ -445,7 +462,6 @@ fn test_process_circuit() {
 }
 
 #[test]
-#[serial]
 fn test_file_error() {
     let backend = Backend::new(None, None).unwrap();
     let mut circuit = Circuit::new();
@@ -469,18 +485,273 @@ fn test_file_error() {
         )
         .is_err());
     fs::remove_file(Path::new("Cargo.ll")).unwrap();
+    assert!(matches!(
+        backend.circuit_to_qir_file(
+            &circuit,
+            Path::new("missing_folder"),
+            Path::new("circuit"),
+            false,
+            true
+        ),
+        Err(QirError::Io {
+            kind: std::io::ErrorKind::NotFound,
+            ..
+        })
+    ));
+    assert!(!Path::new("missing_folder").exists());
+}
+
+/// Writer failing after a number of bytes
+struct FailingWriter(usize);
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.len() > self.0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "the reader went away",
+            ));
+        }
+        self.0 -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer counting the calls of write
+#[derive(Default)]
+struct CountingWriter(usize);
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Test streaming QIR to writers
+#[test]
+fn test_write_qir() {
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 2, true);
+    circuit += Hadamard::new(0);
+    circuit += CNOT::new(0, 1);
+    circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    circuit += PragmaConditional::new(
+        "ro".to_owned(),
+        0,
+        [Operation::from(PauliX::new(1))].into_iter().collect(),
+    );
+
+    for measure_all in [false, true] {
+        let mut buffer: Vec<u8> = vec![];
+        backend
+            .write_qir(&circuit, &mut buffer, measure_all)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            backend.circuit_to_qir_str(&circuit, measure_all).unwrap()
+        );
+    }
+
+    // The small writes of the module are buffered before reaching the writer
+    let mut counting_writer = CountingWriter::default();
+    backend
+        .write_qir(&circuit, &mut counting_writer, false)
+        .unwrap();
+    assert_eq!(counting_writer.0, 1);
+
+    assert_eq!(
+        backend.write_qir(&circuit, FailingWriter(100), false),
+        Err(QirError::Io {
+            path: None,
+            kind: std::io::ErrorKind::BrokenPipe,
+            msg: "the reader went away".to_owned()
+        })
+    );
+
+    let mut buffer: Vec<u8> = vec![];
+    circuit += QuantumRabi::new(0, 0, CalculatorFloat::ZERO);
+    assert_eq!(
+        backend.write_qir(&circuit, &mut buffer, false),
+        Err(QirError::UnsupportedOperation {
+            index: 5,
+            hqslang: "QuantumRabi"
+        })
+    );
+    assert!(buffer.is_empty());
+}
+
+/// Test the atomic writes and the creation of missing folders
+#[test]
+fn test_file_options() {
+    let mut backend = Backend::new(None, None).unwrap();
+    assert!(!backend.atomic_writes());
+    assert!(!backend.create_directories());
+    backend.set_atomic_writes(true);
+    backend.set_create_directories(true);
+    assert!(backend.atomic_writes());
+    assert!(backend.create_directories());
+
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+    let folder = std::env::temp_dir().join(format!("roqoqo_qir_options_{}", std::process::id()));
+    let nested_folder = folder.join("nested").join("folder");
+    backend
+        .circuit_to_qir_file(&circuit, &nested_folder, Path::new("circuit"), false, true)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(nested_folder.join("circuit.ll")).unwrap(),
+        backend.circuit_to_qir_str(&circuit, true).unwrap()
+    );
+    backend
+        .circuit_to_qir_file(&circuit, &nested_folder, Path::new("circuit"), true, false)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(nested_folder.join("circuit.ll")).unwrap(),
+        backend.circuit_to_qir_str(&circuit, false).unwrap()
+    );
+    // Only the output file is left behind
+    assert_eq!(fs::read_dir(&nested_folder).unwrap().count(), 1);
+
+    // A folder in place of the output file cannot be replaced
+    fs::create_dir(nested_folder.join("blocked.ll")).unwrap();
+    assert!(matches!(
+        backend.circuit_to_qir_file(&circuit, &nested_folder, Path::new("blocked"), true, false),
+        Err(QirError::Io { .. })
+    ));
+    assert_eq!(fs::read_dir(&nested_folder).unwrap().count(), 2);
+
+    let results =
+        backend.circuits_to_qir_files(&[circuit.clone()], &folder.join("batch"), false, false);
+    assert_eq!(results[0], Ok(folder.join("batch").join("circuit_0.ll")));
+    fs::remove_dir_all(folder).unwrap();
+}
+
+/// Circuits sharing gate sets, a circuit the Base Profile rejects and a circuit with a helper gate
+fn batch_circuits() -> Vec<Circuit> {
+    let mut first = Circuit::new();
+    first += Hadamard::new(0);
+    first += CNOT::new(0, 1);
+    first += RotateX::new(1, CalculatorFloat::FRAC_PI_2);
+    let mut conditional = Circuit::new();
+    conditional += DefinitionBit::new("ro".to_owned(), 1, true);
+    conditional += MeasureQubit::new(0, "ro".to_owned(), 0);
+    conditional += PragmaConditional::new("ro".to_owned(), 0, first.clone());
+    let mut reordered = Circuit::new();
+    reordered += CNOT::new(1, 2);
+    reordered += Hadamard::new(2);
+    let mut same_gates = Circuit::new();
+    same_gates += Hadamard::new(3);
+    same_gates += CNOT::new(3, 0);
+    same_gates += RotateX::new(0, CalculatorFloat::FRAC_PI_4);
+    let mut helper = Circuit::new();
+    helper += ControlledPauliY::new(0, 1);
+    helper += SWAP::new(1, 2);
+    // Gate definitions with the same name but different circuits need different declarations
+    let defined = |body: Operation| -> Circuit {
+        let mut circuit = Circuit::new();
+        circuit += GateDefinition::new(
+            [body].into_iter().collect(),
+            "custom".to_owned(),
+            vec![0],
+            vec![],
+        );
+        circuit += CallDefinedGate::new("custom".to_owned(), vec![0], vec![]);
+        circuit
+    };
+    vec![
+        first,
+        conditional,
+        reordered,
+        same_gates,
+        helper.clone(),
+        helper,
+        defined(Operation::from(Hadamard::new(0))),
+        defined(Operation::from(PauliX::new(0))),
+    ]
+}
+
+/// Test that a batch translates every circuit like circuit_to_qir_str
+#[test_case(false; "without_measure_all")]
+#[test_case(true; "with_measure_all")]
+fn test_circuits_to_qir_batch(measure_all: bool) {
+    let backend = Backend::new(None, None).unwrap();
+    let circuits = batch_circuits();
+    let results = backend.circuits_to_qir_batch(&circuits, measure_all);
+    assert_eq!(results.len(), circuits.len());
+    for (circuit, result) in circuits.iter().zip(results) {
+        assert_eq!(result, backend.circuit_to_qir_str(circuit, measure_all));
+    }
+    assert!(backend.circuits_to_qir_batch(&[], false).is_empty());
+}
+
+/// Test that a batch writes one file per circuit and reports the circuits that failed
+#[test]
+fn test_circuits_to_qir_files() {
+    let backend = Backend::new(None, None).unwrap();
+    let circuits = batch_circuits();
+    let folder = std::env::temp_dir().join(format!("roqoqo_qir_batch_{}", std::process::id()));
+    fs::create_dir_all(&folder).unwrap();
+
+    let results = backend.circuits_to_qir_files(&circuits, &folder, false, false);
+    assert_eq!(results.len(), circuits.len());
+    for (index, (circuit, result)) in circuits.iter().zip(results).enumerate() {
+        match backend.circuit_to_qir_str(circuit, false) {
+            Ok(qir_str) => {
+                let path = result.unwrap();
+                assert_eq!(path, folder.join(format!("circuit_{index}.ll")));
+                assert_eq!(fs::read_to_string(path).unwrap(), qir_str);
+            }
+            Err(error) => {
+                assert_eq!(result, Err(error));
+                assert!(!folder.join(format!("circuit_{index}.ll")).exists());
+            }
+        }
+    }
+
+    let results = backend.circuits_to_qir_files(&circuits[..1], &folder, false, false);
+    assert!(matches!(
+        results[0],
+        Err(QirError::Backend(
+            RoqoqoBackendError::FileAlreadyExists { .. }
+        ))
+    ));
+    let results = backend.circuits_to_qir_files(&circuits[..1], &folder, true, true);
+    assert_eq!(
+        fs::read_to_string(results[0].as_ref().unwrap()).unwrap(),
+        backend.circuit_to_qir_str(&circuits[0], true).unwrap()
+    );
+
+    let results =
+        backend.circuits_to_qir_files(&circuits[..1], &folder.join("missing"), false, false);
+    assert!(matches!(
+        results[0],
+        Err(QirError::Io {
+            kind: std::io::ErrorKind::NotFound,
+            ..
+        })
+    ));
+    fs::remove_dir_all(folder).unwrap();
 }
 
 /// Test Debug, Clone and PartialEq for Backend
 #[test]
-#[serial]
 fn test_debug_clone_partialeq() {
     let backend = Backend::new(None, None).unwrap();
 
     // Test Debug trait
     assert_eq!(
         format!("{backend:?}"),
-        "Backend { qir_profile: BaseProfile, qir_version: V0point1 }"
+        "Backend { qir_profile: BaseProfile, qir_version: V0point1, pointer_style: Typed, entry_point_name: \"main\", output_labeling_schema: None, entry_point_attributes: [], module_flags: [], atomic_writes: false, create_directories: false }"
     );
 
     // Test Clone trait
@@ -492,3 +763,851 @@ fn test_debug_clone_partialeq() {
     assert!(backend_0 == backend);
     assert!(backend == backend_0);
 }
+
+#[test]
+fn test_adaptive_profile_conditional_circuit() {
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
+
+    let mut circuit_cond = Circuit::new();
+    circuit_cond += PauliX::new(1);
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += Hadamard::new(0);
+    circuit += MeasureQubit::new(0, "ro".to_string(), 0);
+    circuit += PragmaConditional::new("ro".to_owned(), 0, circuit_cond);
+    circuit += PragmaActiveReset::new(0);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #1\n  %0 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 0 to %Result*))\n  br i1 %0, label %then0, label %continue0\n\nthen0:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  br label %continue0\n\ncontinue0:\n  call void @__quantum__qis__reset__body(%Qubit* inttoptr (i64 0 to %Qubit*)) #1\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\ndeclare i1 @__quantum__qis__read_result__body(%Result*)\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__reset__body(%Qubit*) #1\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"1\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 true}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 false}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 false}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 false}\n!10 = !{i32 1, !\"extern_functions\", i1 false}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
+}
+
+#[test]
+fn test_adaptive_profile_loop_circuit() {
+    let backend = Backend::new(Some("adaptive".to_string()), None).unwrap();
+
+    let mut circuitloop = Circuit::new();
+    circuitloop += Hadamard::new(0);
+    circuitloop += SWAP::new(0, 1);
+    let mut circuit = Circuit::new();
+    circuit += PragmaLoop::new(CalculatorFloat::from("2.7"), circuitloop);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @swap(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @swap(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\n\ndefine void @swap(%Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit1, %Qubit* %qubit0)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 false}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 false}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 true}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 false}\n!10 = !{i32 1, !\"extern_functions\", i1 false}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
+}
+
+#[test]
+fn test_base_profile_reset_error() {
+    let backend = Backend::new(Some("base_profile".to_string()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += Hadamard::new(0);
+    circuit += PragmaActiveReset::new(0);
+    assert!(backend.circuit_to_qir_str(&circuit, false).is_err());
+
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
+    assert!(backend.circuit_to_qir_str(&circuit, false).is_ok());
+
+    let mut circuit = Circuit::new();
+    circuit += PragmaLoop::new(CalculatorFloat::from("reps"), Circuit::new());
+    assert!(backend.circuit_to_qir_str(&circuit, false).is_err());
+}
+
+#[test]
+fn test_qir_version_1_0() {
+    let backend = Backend::new(None, Some("1.0".to_string())).unwrap();
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+    circuit.add_operation(MeasureQubit::new(0, "ro".to_owned(), 0));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #1\n  ret void\n}\n\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"1\" \"required_num_results\"=\"1\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
+
+#[test]
+fn test_qir_version_2_0() {
+    let backend = Backend::new(None, Some("2.0".to_string())).unwrap();
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+    circuit.add_operation(SWAP::new(0, 1));
+    circuit.add_operation(MeasureQubit::new(1, "ro".to_owned(), 0));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "define i64 @main() #0 {\nentry:\n  call void @__quantum__rt__initialize(ptr null)\n  call void @__quantum__qis__x__body(ptr null)\n  call void @swap(ptr null, ptr inttoptr (i64 1 to ptr))\n  call void @__quantum__qis__mz__body(ptr inttoptr (i64 1 to ptr), ptr null) #1\n  ret i64 0\n}\n\ndeclare void @__quantum__rt__initialize(ptr)\ndeclare void @__quantum__qis__cnot__body(ptr, ptr)\n\ndefine void @swap(ptr %qubit0, ptr %qubit1) {\nentry:\n  call void @__quantum__qis__cnot__body(ptr %qubit0, ptr %qubit1)\n  call void @__quantum__qis__cnot__body(ptr %qubit1, ptr %qubit0)\n  call void @__quantum__qis__cnot__body(ptr %qubit0, ptr %qubit1)\n  ret void\n}\n\ndeclare void @__quantum__qis__x__body(ptr)\ndeclare void @__quantum__qis__mz__body(ptr, ptr writeonly) #1\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"1\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 2}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+
+    let qir_str = backend.circuit_to_qir_str(&circuit, true).unwrap();
+    assert_eq!(qir_str, "define i64 @main() #0 {\nentry:\n  call void @__quantum__rt__initialize(ptr null)\n  call void @__quantum__qis__x__body(ptr null)\n  call void @swap(ptr null, ptr inttoptr (i64 1 to ptr))\n  call void @__quantum__qis__mz__body(ptr inttoptr (i64 1 to ptr), ptr null) #1\n  call void @__quantum__qis__mz__body(ptr null, ptr null) #1\n  call void @__quantum__qis__mz__body(ptr inttoptr (i64 1 to ptr), ptr inttoptr (i64 1 to ptr)) #1\n  call void @__quantum__rt__array_record_output(i64 2, ptr null)\n  call void @__quantum__rt__result_record_output(ptr null, ptr null)\n  call void @__quantum__rt__result_record_output(ptr inttoptr (i64 1 to ptr), ptr null)\n  ret i64 0\n}\n\ndeclare void @__quantum__rt__initialize(ptr)\ndeclare void @__quantum__qis__cnot__body(ptr, ptr)\n\ndefine void @swap(ptr %qubit0, ptr %qubit1) {\nentry:\n  call void @__quantum__qis__cnot__body(ptr %qubit0, ptr %qubit1)\n  call void @__quantum__qis__cnot__body(ptr %qubit1, ptr %qubit0)\n  call void @__quantum__qis__cnot__body(ptr %qubit0, ptr %qubit1)\n  ret void\n}\n\ndeclare void @__quantum__qis__x__body(ptr)\ndeclare void @__quantum__qis__mz__body(ptr, ptr writeonly) #1\ndeclare void @__quantum__rt__result_record_output(ptr, ptr)\ndeclare void @__quantum__rt__array_record_output(i64, ptr)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"2\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 2}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
+
+#[test]
+fn test_qir_version_2_0_adaptive_profile() {
+    let backend = Backend::new(
+        Some("adaptive_profile".to_string()),
+        Some("2.0".to_string()),
+    )
+    .unwrap();
+
+    let mut circuit_cond = Circuit::new();
+    circuit_cond += PauliX::new(1);
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += Hadamard::new(0);
+    circuit += MeasureQubit::new(0, "ro".to_string(), 0);
+    circuit += PragmaConditional::new("ro".to_owned(), 0, circuit_cond);
+    circuit += PragmaActiveReset::new(0);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "define i64 @main() #0 {\nentry:\n  call void @__quantum__rt__initialize(ptr null)\n  call void @__quantum__qis__h__body(ptr null)\n  call void @__quantum__qis__mz__body(ptr null, ptr null) #1\n  %0 = call i1 @__quantum__qis__read_result__body(ptr null)\n  br i1 %0, label %then0, label %continue0\n\nthen0:\n  call void @__quantum__qis__x__body(ptr inttoptr (i64 1 to ptr))\n  br label %continue0\n\ncontinue0:\n  call void @__quantum__qis__reset__body(ptr null) #1\n  ret i64 0\n}\n\ndeclare void @__quantum__rt__initialize(ptr)\ndeclare void @__quantum__qis__h__body(ptr)\ndeclare void @__quantum__qis__mz__body(ptr, ptr writeonly) #1\ndeclare i1 @__quantum__qis__read_result__body(ptr)\ndeclare void @__quantum__qis__x__body(ptr)\ndeclare void @__quantum__qis__reset__body(ptr) #1\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"1\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 2}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 7, !\"backwards_branching\", i2 0}\n!5 = !{i32 1, !\"multiple_target_branching\", i1 false}\n!6 = !{i32 1, !\"multiple_return_points\", i1 false}");
+
+    // Float arithmetic has to be declared with the float_computations flag
+    let mut circuit = Circuit::new();
+    circuit += RotateX::new(0, CalculatorFloat::from("2*theta"));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert!(qir_str.contains("  %0 = fmul double 2.0, %theta\n"));
+    assert!(qir_str.contains("!4 = !{i32 5, !\"float_computations\", !{!\"double\"}}\n!5 = !{i32 7, !\"backwards_branching\", i2 0}"));
+}
+
+#[test]
+fn test_qir_version_error() {
+    assert!(Backend::new(None, Some("1.1".to_string())).is_err());
+    assert!(Backend::new(None, Some("2".to_string())).is_ok());
+}
+
+#[test]
+fn test_opaque_pointers() {
+    let mut backend = Backend::new(None, Some("1.0".to_string())).unwrap();
+    assert_eq!(backend.pointer_style(), PointerStyle::Typed);
+    backend.set_pointer_style(PointerStyle::Opaque).unwrap();
+    assert_eq!(backend.pointer_style(), PointerStyle::Opaque);
+
+    let mut gate_circuit = Circuit::new();
+    gate_circuit += Hadamard::new(1);
+    gate_circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let mut circuit = Circuit::new();
+    circuit += GateDefinition::new(gate_circuit, "measure_gate".to_owned(), vec![0, 1], vec![]);
+    circuit += CallDefinedGate::new("measure_gate".to_owned(), vec![1, 0], vec![]);
+    circuit += CNOT::new(0, 2);
+    let qir_str = backend.circuit_to_qir_str(&circuit, true).unwrap();
+    assert_eq!(qir_str, "define void @main() #0 {\nentry:\n  call void @measure_gate(ptr inttoptr (i64 1 to ptr), ptr null)\n  call void @__quantum__qis__cnot__body(ptr null, ptr inttoptr (i64 2 to ptr))\n  call void @__quantum__qis__mz__body(ptr null, ptr null) #1\n  call void @__quantum__qis__mz__body(ptr inttoptr (i64 1 to ptr), ptr inttoptr (i64 1 to ptr)) #1\n  call void @__quantum__qis__mz__body(ptr inttoptr (i64 2 to ptr), ptr inttoptr (i64 2 to ptr)) #1\n  call void @__quantum__rt__array_record_output(i64 3, ptr null)\n  call void @__quantum__rt__result_record_output(ptr null, ptr null)\n  call void @__quantum__rt__result_record_output(ptr inttoptr (i64 1 to ptr), ptr null)\n  call void @__quantum__rt__result_record_output(ptr inttoptr (i64 2 to ptr), ptr null)\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(ptr)\ndeclare void @__quantum__qis__mz__body(ptr, ptr writeonly) #1\n\ndefine void @measure_gate(ptr %qubit0, ptr %qubit1) #1 {\nentry:\n  call void @__quantum__qis__h__body(ptr %qubit1)\n  call void @__quantum__qis__mz__body(ptr %qubit0, ptr null) #1\n  ret void\n}\n\ndeclare void @__quantum__qis__cnot__body(ptr, ptr)\ndeclare void @__quantum__rt__result_record_output(ptr, ptr)\ndeclare void @__quantum__rt__array_record_output(i64, ptr)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"3\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
+
+#[test]
+fn test_pointer_style_error() {
+    let mut backend = Backend::new(None, Some("2.0".to_string())).unwrap();
+    assert_eq!(backend.pointer_style(), PointerStyle::Opaque);
+    assert!(backend.set_pointer_style(PointerStyle::Typed).is_err());
+    assert_eq!(backend.pointer_style(), PointerStyle::Opaque);
+}
+
+#[test]
+fn test_nested_conditional_circuit() {
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
+
+    let mut inner_circuit = Circuit::new();
+    inner_circuit += PauliZ::new(1);
+    let mut outer_circuit = Circuit::new();
+    outer_circuit += PauliX::new(1);
+    outer_circuit += PragmaConditional::new("ro".to_owned(), 1, inner_circuit);
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 2, true);
+    circuit += MeasureQubit::new(0, "ro".to_string(), 0);
+    circuit += MeasureQubit::new(1, "ro".to_string(), 1);
+    circuit += PragmaConditional::new("ro".to_owned(), 0, outer_circuit);
+    circuit += Hadamard::new(0);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #1\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Result* inttoptr (i64 1 to %Result*)) #1\n  %0 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 0 to %Result*))\n  br i1 %0, label %then0, label %continue0\n\nthen0:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  %1 = call i1 @__quantum__qis__read_result__body(%Result* inttoptr (i64 1 to %Result*))\n  br i1 %1, label %then1, label %continue1\n\nthen1:\n  call void @__quantum__qis__z__body(%Qubit* inttoptr (i64 1 to %Qubit*))\n  br label %continue1\n\ncontinue1:\n  br label %continue0\n\ncontinue0:\n  call void @__quantum__qis__h__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\ndeclare i1 @__quantum__qis__read_result__body(%Result*)\ndeclare void @__quantum__qis__x__body(%Qubit*)\ndeclare void @__quantum__qis__z__body(%Qubit*)\ndeclare void @__quantum__qis__h__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"2\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 false}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 false}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 false}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 false}\n!10 = !{i32 1, !\"extern_functions\", i1 false}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
+}
+
+#[test]
+fn test_quantum_program_classical_register() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += DefinitionBit::new("ro".to_owned(), 2, true);
+    constant_circuit += DefinitionFloat::new("internal".to_owned(), 1, false);
+    constant_circuit += RotateX::new(0, CalculatorFloat::from("angle"));
+    let mut circuit_0 = Circuit::new();
+    circuit_0 += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let mut circuit_1 = Circuit::new();
+    circuit_1 += DefinitionBit::new("ancilla".to_owned(), 1, true);
+    circuit_1 += Hadamard::new(1);
+    circuit_1 += MeasureQubit::new(1, "ancilla".to_owned(), 0);
+    let program = QuantumProgram::ClassicalRegister {
+        measurement: ClassicalRegister {
+            constant_circuit: Some(constant_circuit),
+            circuits: vec![circuit_0, circuit_1],
+        },
+        input_parameter_names: vec!["angle".to_owned()],
+    };
+
+    let mut substituted_circuit = Circuit::new();
+    substituted_circuit += DefinitionBit::new("ro".to_owned(), 2, true);
+    substituted_circuit += DefinitionFloat::new("internal".to_owned(), 1, false);
+    substituted_circuit += RotateX::new(0, CalculatorFloat::from(0.5));
+    let mut expected_0 = substituted_circuit.clone();
+    expected_0 += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let mut expected_1 = substituted_circuit;
+    expected_1 += DefinitionBit::new("ancilla".to_owned(), 1, true);
+    expected_1 += Hadamard::new(1);
+    expected_1 += MeasureQubit::new(1, "ancilla".to_owned(), 0);
+
+    let entry_points = backend
+        .quantum_program_to_qir(&program, &[0.5], true)
+        .unwrap();
+    assert_eq!(
+        entry_points,
+        vec![
+            QirEntryPoint {
+                name: "circuit_0".to_owned(),
+                qir: backend.circuit_to_qir_str(&expected_0, true).unwrap(),
+                readout_registers: vec!["ro".to_owned()],
+            },
+            QirEntryPoint {
+                name: "circuit_1".to_owned(),
+                qir: backend.circuit_to_qir_str(&expected_1, true).unwrap(),
+                readout_registers: vec!["ro".to_owned(), "ancilla".to_owned()],
+            },
+        ]
+    );
+    assert!(entry_points[0].qir.contains("double 0.5"));
+
+    let error = backend
+        .quantum_program_to_qir(&program, &[], true)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        QirError::WrongParameterCount {
+            expected: 1,
+            given: 0
+        }
+    );
+    assert_eq!(error.index(), None);
+    assert_eq!(
+        error.to_string(),
+        "Wrong number of parameters: 1 parameters expected, 0 parameters given"
+    );
+}
+
+#[test]
+fn test_quantum_program_pauli_z_product() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += PauliX::new(0);
+    circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let mut input = PauliZProductInput::new(1, false);
+    input.add_pauliz_product("ro".to_owned(), vec![0]).unwrap();
+    let program = QuantumProgram::PauliZProduct {
+        measurement: PauliZProduct {
+            constant_circuit: None,
+            circuits: vec![circuit.clone()],
+            input,
+        },
+        input_parameter_names: vec![],
+    };
+    let entry_points = backend
+        .quantum_program_to_qir(&program, &[], false)
+        .unwrap();
+    assert_eq!(entry_points.len(), 1);
+    assert_eq!(
+        entry_points[0].qir,
+        backend.circuit_to_qir_str(&circuit, false).unwrap()
+    );
+    assert_eq!(entry_points[0].readout_registers, vec!["ro".to_owned()]);
+
+    let mut unsupported_circuit = Circuit::new();
+    unsupported_circuit += QuantumRabi::new(0, 0, CalculatorFloat::ZERO);
+    let program = QuantumProgram::ClassicalRegister {
+        measurement: ClassicalRegister {
+            constant_circuit: None,
+            circuits: vec![unsupported_circuit],
+        },
+        input_parameter_names: vec![],
+    };
+    assert!(backend
+        .quantum_program_to_qir(&program, &[], false)
+        .is_err());
+}
+
+#[test]
+fn test_multiple_entry_points() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut gate_circuit = Circuit::new();
+    gate_circuit += Hadamard::new(0);
+    gate_circuit += CNOT::new(0, 1);
+    let mut circuit_0 = Circuit::new();
+    circuit_0 += GateDefinition::new(gate_circuit, "bell".to_owned(), vec![0, 1], vec![]);
+    circuit_0 += CallDefinedGate::new("bell".to_owned(), vec![0, 1], vec![]);
+    circuit_0 += RotateZ::new(0, CalculatorFloat::from(0.1));
+    let mut circuit_1 = Circuit::new();
+    circuit_1 += RotateZ::new(2, CalculatorFloat::from(0.2));
+    circuit_1 += CNOT::new(2, 1);
+    circuit_1 += ControlledPauliY::new(1, 2);
+    let circuits = vec![
+        ("sweep_0".to_owned(), circuit_0),
+        ("sweep_1".to_owned(), circuit_1),
+    ];
+    let qir_str = backend.circuits_to_qir_str(&circuits, true).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @sweep_0() #0 {\nentry:\n  call void @bell(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rz__body(double 0.1, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #2\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Result* inttoptr (i64 1 to %Result*)) #2\n  call void @__quantum__rt__array_record_output(i64 2, i8* null)\n  call void @__quantum__rt__result_record_output(%Result* inttoptr (i64 0 to %Result*), i8* null)\n  call void @__quantum__rt__result_record_output(%Result* inttoptr (i64 1 to %Result*), i8* null)\n  ret void\n}\n\ndefine void @sweep_1() #1 {\nentry:\n  call void @__quantum__qis__rz__body(double 0.2, %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @cy(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Result* inttoptr (i64 0 to %Result*)) #2\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Result* inttoptr (i64 1 to %Result*)) #2\n  call void @__quantum__qis__mz__body(%Qubit* inttoptr (i64 2 to %Qubit*), %Result* inttoptr (i64 2 to %Result*)) #2\n  call void @__quantum__rt__array_record_output(i64 3, i8* null)\n  call void @__quantum__rt__result_record_output(%Result* inttoptr (i64 0 to %Result*), i8* null)\n  call void @__quantum__rt__result_record_output(%Result* inttoptr (i64 1 to %Result*), i8* null)\n  call void @__quantum__rt__result_record_output(%Result* inttoptr (i64 2 to %Result*), i8* null)\n  ret void\n}\n\ndeclare void @__quantum__qis__h__body(%Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\n\ndefine void @bell(%Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__h__body(%Qubit* %qubit0)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  ret void\n}\n\ndeclare void @__quantum__qis__rz__body(double, %Qubit*)\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #2\ndeclare void @__quantum__rt__result_record_output(%Result*, i8*)\ndeclare void @__quantum__rt__array_record_output(i64, i8*)\ndeclare void @__quantum__qis__s__adj(%Qubit*)\ndeclare void @__quantum__qis__s__body(%Qubit*)\n\ndefine void @cy(%Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__s__adj(%Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__s__body(%Qubit* %qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"2\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"entry_point\" \"required_num_qubits\"=\"3\" \"required_num_results\"=\"3\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #2 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
+
+#[test]
+fn test_multiple_entry_points_error() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += PauliX::new(0);
+    assert_eq!(
+        backend.circuits_to_qir_str(&[], false),
+        Err(QirError::InvalidConfiguration {
+            msg: "At least one circuit is needed to build a QIR module".to_owned()
+        })
+    );
+    assert_eq!(
+        backend.circuits_to_qir_str(
+            &[
+                ("entry".to_owned(), circuit.clone()),
+                ("entry".to_owned(), circuit.clone())
+            ],
+            false
+        ),
+        Err(QirError::InvalidConfiguration {
+            msg: "Function name 'entry' is used more than once in the QIR module".to_owned()
+        })
+    );
+    let mut gate_circuit = circuit.clone();
+    gate_circuit += GateDefinition::new(Circuit::new(), "entry".to_owned(), vec![0], vec![]);
+    assert!(backend
+        .circuits_to_qir_str(&[("entry".to_owned(), gate_circuit)], false)
+        .is_err());
+}
+
+#[test]
+fn test_gate_definition_with_measurement() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut gate_circuit = Circuit::new();
+    gate_circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let mut circuit = Circuit::new();
+    circuit += GateDefinition::new(gate_circuit, "measure".to_owned(), vec![0], vec![]);
+    circuit += CallDefinedGate::new("measure".to_owned(), vec![0], vec![]);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n%Result = type opaque\n\ndefine void @main() #0 {\nentry:\n  call void @measure(%Qubit* inttoptr (i64 0 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1\n\ndefine void @measure(%Qubit* %qubit0) #1 {\nentry:\n  call void @__quantum__qis__mz__body(%Qubit* %qubit0, %Result* inttoptr (i64 0 to %Result*)) #1\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"1\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" \"irreversible\" }\nattributes #1 = { \"irreversible\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
+
+#[test]
+fn test_entry_point_configuration() {
+    let mut backend = Backend::new(None, None).unwrap();
+    assert_eq!(backend.entry_point_name(), "main");
+    assert_eq!(backend.output_labeling_schema(), None);
+    backend.set_entry_point_name("program.entry").unwrap();
+    backend
+        .set_output_labeling_schema(Some("schema_id".to_owned()))
+        .unwrap();
+    backend
+        .add_entry_point_attribute("vendor_key", Some("vendor_value".to_owned()))
+        .unwrap();
+    backend
+        .add_entry_point_attribute("required_num_qubits", Some("4".to_owned()))
+        .unwrap();
+    backend
+        .add_entry_point_attribute("vendor_flag", None)
+        .unwrap();
+    backend.add_module_flag(1, "vendor_version", 32, 3).unwrap();
+    backend
+        .add_module_flag(1, "dynamic_qubit_management", 1, 1)
+        .unwrap();
+    assert_eq!(backend.entry_point_name(), "program.entry");
+    assert_eq!(backend.output_labeling_schema(), Some("schema_id"));
+
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @program.entry() #0 {\nentry:\n  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__x__body(%Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"4\" \"required_num_results\"=\"0\" \"output_labeling_schema\"=\"schema_id\" \"qir_profiles\"=\"base_profile\" \"vendor_key\"=\"vendor_value\" \"vendor_flag\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 true}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"vendor_version\", i32 3}");
+}
+
+#[test]
+fn test_entry_point_configuration_errors() {
+    let mut backend = Backend::new(None, None).unwrap();
+    assert_eq!(
+        backend.set_entry_point_name("0main"),
+        Err(QirError::InvalidConfiguration {
+            msg: "'0main' is not a valid QIR function name".to_owned()
+        })
+    );
+    assert!(backend.set_entry_point_name("").is_err());
+    assert!(backend.set_entry_point_name("main entry").is_err());
+    assert_eq!(
+        backend.add_entry_point_attribute("key", Some("quoted\"value".to_owned())),
+        Err(QirError::InvalidConfiguration {
+            msg: "'quoted\"value' cannot contain quotes, backslashes or control characters"
+                .to_owned()
+        })
+    );
+    assert!(backend
+        .set_output_labeling_schema(Some("back\\slash".to_owned()))
+        .is_err());
+    assert!(backend.add_module_flag(1, "flag", 0, 0).is_err());
+    assert!(backend.add_module_flag(1, "new\nline", 1, 0).is_err());
+    assert_eq!(backend, Backend::new(None, None).unwrap());
+}
+
+#[test]
+fn test_symbolic_parameters() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += DefinitionFloat::new("beta".to_owned(), 1, false);
+    circuit += RotateX::new(0, CalculatorFloat::from("alpha"));
+    circuit += RotateZ::new(1, CalculatorFloat::from("beta"));
+    circuit += RotateX::new(1, CalculatorFloat::from("alpha"));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main(double %beta, double %alpha) #0 {\nentry:\n  call void @__quantum__qis__rx__body(double %alpha, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rz__body(double %beta, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rx__body(double %alpha, %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__rz__body(double, %Qubit*)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 false}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 false}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 false}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 true}\n!10 = !{i32 1, !\"extern_functions\", i1 false}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
+
+    // The Base Profile allows no entry point parameters
+    let base_backend = Backend::new(None, None).unwrap();
+    assert_eq!(
+        base_backend.circuit_to_qir_str(&circuit, false),
+        Err(QirError::UnboundParameter {
+            index: 1,
+            hqslang: "RotateX",
+            parameter: "alpha".to_owned()
+        })
+    );
+
+    let mut circuit = Circuit::new();
+    circuit += RotateX::new(0, CalculatorFloat::from("theta[0]"));
+    assert_eq!(
+        backend.circuit_to_qir_str(&circuit, false),
+        Err(QirError::OperationFailed {
+            index: 0,
+            hqslang: "RotateX",
+            error: RoqoqoBackendError::CalculatorError(CalculatorError::ParsingError {
+                msg: "Unrecognized token"
+            })
+        })
+    );
+}
+
+/// Test that symbolic parameters with the name of a basic block are rejected
+#[test_case(Circuit::from_iter([Operation::from(RotateX::new(0, CalculatorFloat::from("entry")))]), "entry"; "entry")]
+#[test_case(Circuit::from_iter([Operation::from(PragmaConditional::new("ro".to_owned(), 0, Circuit::from_iter([Operation::from(RotateX::new(0, CalculatorFloat::from("then0")))])))]), "then0"; "then")]
+fn test_symbolic_parameter_label_error(circuit: Circuit, name: &str) {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    assert_eq!(
+        backend.circuit_to_qir_str(&circuit, false),
+        Err(QirError::InvalidParameter {
+            parameter: name.to_owned(),
+            msg: "it is also the label of a basic block".to_owned()
+        })
+    );
+}
+
+/// Test that symbolic expressions are computed in the entry point and in helper gates
+#[test]
+fn test_symbolic_expressions() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += RotateX::new(0, CalculatorFloat::from("2*theta + 0.5"));
+    circuit += RotateZ::new(1, CalculatorFloat::from("theta") * 3.0);
+    circuit += GateDefinition::new(
+        [Operation::from(RotateY::new(
+            0,
+            CalculatorFloat::from("-sin(phi)/2"),
+        ))]
+        .into_iter()
+        .collect(),
+        "half_rotation".to_owned(),
+        vec![0],
+        vec!["phi".to_owned()],
+    );
+    circuit += CallDefinedGate::new(
+        "half_rotation".to_owned(),
+        vec![1],
+        vec![CalculatorFloat::from("atan2(theta, 2)")],
+    );
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main(double %theta) #0 {\nentry:\n  %0 = fmul double 2.0, %theta\n  %1 = fadd double %0, 0.5\n  call void @__quantum__qis__rx__body(double %1, %Qubit* inttoptr (i64 0 to %Qubit*))\n  %2 = fmul double %theta, 3.0\n  call void @__quantum__qis__rz__body(double %2, %Qubit* inttoptr (i64 1 to %Qubit*))\n  %3 = call double @atan2(double %theta, double 2.0)\n  call void @half_rotation(double %3, %Qubit* inttoptr (i64 1 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__ry__body(double, %Qubit*)\n\ndefine void @half_rotation(double %phi, %Qubit* %qubit0) {\nentry:\n  %0 = call double @llvm.sin.f64(double %phi)\n  %1 = fneg double %0\n  %2 = fdiv double %1, 2.0\n  call void @__quantum__qis__ry__body(double %2, %Qubit* %qubit0)\n  ret void\n}\n\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\ndeclare void @__quantum__qis__rz__body(double, %Qubit*)\ndeclare double @atan2(double, double)\ndeclare double @llvm.sin.f64(double)\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 false}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 true}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 true}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 true}\n!10 = !{i32 1, !\"extern_functions\", i1 true}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
+
+    let adaptive_backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let qir_str = adaptive_backend
+        .circuit_to_qir_str(&circuit, false)
+        .unwrap();
+    assert!(qir_str.contains("!{i32 1, !\"classical_floats\", i1 true}"));
+    assert!(qir_str.contains("!{i32 1, !\"extern_functions\", i1 true}"));
+}
+
+/// Test that the derived arguments of helper gates are computed from symbolic parameters
+#[test]
+fn test_symbolic_derived_arguments() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += XY::new(0, 1, CalculatorFloat::from("theta"));
+    circuit += ControlledPhaseShift::new(1, 0, CalculatorFloat::from("2*theta"));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main(double %theta) #0 {\nentry:\n  %0 = fmul double %theta, -0.5\n  call void @xy(double %0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  %1 = fmul double 2.0, %theta\n  %2 = fmul double %1, 0.5\n  %3 = fmul double %1, -0.5\n  call void @cp(double %2, double %3, %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 0 to %Qubit*))\n  ret void\n}\n\ndeclare void @__quantum__qis__ry__body(double, %Qubit*)\ndeclare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)\ndeclare void @__quantum__qis__rz__body(double, %Qubit*)\ndeclare void @__quantum__qis__rx__body(double, %Qubit*)\n\ndefine void @xy(double %theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double -1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double -1.5707963267948966, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double 1.5707963267948966, %Qubit* %qubit1)\n  ret void\n}\n\ndefine void @cp(double %half_theta, double %minus_half_theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__rz__body(double %half_theta, %Qubit* %qubit0)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %minus_half_theta, %Qubit* %qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit0, %Qubit* %qubit1)\n  call void @__quantum__qis__rz__body(double %half_theta, %Qubit* %qubit1)\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"adaptive_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3, !4, !5, !6, !7, !8, !9, !10, !11}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}\n!4 = !{i32 1, !\"qubit_resetting\", i1 false}\n!5 = !{i32 1, !\"classical_ints\", i1 false}\n!6 = !{i32 1, !\"classical_floats\", i1 true}\n!7 = !{i32 1, !\"classical_fixed_points\", i1 false}\n!8 = !{i32 1, !\"user_functions\", i1 true}\n!9 = !{i32 1, !\"dynamic_float_args\", i1 true}\n!10 = !{i32 1, !\"extern_functions\", i1 false}\n!11 = !{i32 1, !\"backwards_branching\", i1 false}");
+}
+
+/// Test that symbolic parameters are bound before translation
+#[test]
+fn test_circuit_with_parameters() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += RotateX::new(0, CalculatorFloat::from("2*theta"));
+    circuit += RotateZ::new(0, CalculatorFloat::from("phi"));
+    let substitutions: HashMap<String, f64> = [("theta".to_owned(), 0.5), ("phi".to_owned(), 0.25)]
+        .into_iter()
+        .collect();
+    let qir_str = backend
+        .circuit_to_qir_str_with_parameters(&circuit, &substitutions, false)
+        .unwrap();
+
+    let mut bound_circuit = Circuit::new();
+    bound_circuit += RotateX::new(0, CalculatorFloat::from(1.0));
+    bound_circuit += RotateZ::new(0, CalculatorFloat::from(0.25));
+    assert_eq!(
+        qir_str,
+        backend.circuit_to_qir_str(&bound_circuit, false).unwrap()
+    );
+    assert!(qir_str.contains("define void @main() #0"));
+
+    let substitutions: HashMap<String, f64> = [("theta".to_owned(), 0.5)].into_iter().collect();
+    assert_eq!(
+        backend.circuit_to_qir_str_with_parameters(&circuit, &substitutions, false),
+        Err(QirError::UnboundParameter {
+            index: 1,
+            hqslang: "RotateZ",
+            parameter: "phi".to_owned()
+        })
+    );
+}
+
+/// Test that InputSymbolic operations bind their symbol and are not translated
+#[test_case("base_profile"; "base")]
+#[test_case("adaptive_profile"; "adaptive")]
+fn test_input_symbolic(qir_profile: &str) {
+    let backend = Backend::new(Some(qir_profile.to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += InputSymbolic::new("theta".to_owned(), 0.5);
+    circuit += RotateZ::new(0, CalculatorFloat::from("theta"));
+
+    let mut bound_circuit = Circuit::new();
+    bound_circuit += RotateZ::new(0, CalculatorFloat::from(0.5));
+    let expected = backend.circuit_to_qir_str(&bound_circuit, false).unwrap();
+    assert_eq!(
+        backend.circuit_to_qir_str(&circuit, false).unwrap(),
+        expected
+    );
+    assert_eq!(
+        backend
+            .circuit_to_qir_str_with_parameters(&circuit, &HashMap::new(), false)
+            .unwrap(),
+        expected
+    );
+}
+
+/// Test that symbols set by InputSymbolic operations do not become entry point arguments
+#[test]
+fn test_input_symbolic_with_free_symbols() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += InputSymbolic::new("theta".to_owned(), 0.5);
+    circuit += RotateZ::new(0, CalculatorFloat::from("theta"));
+    circuit += RotateX::new(0, CalculatorFloat::from("phi"));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert!(qir_str.contains("define void @main(double %phi) #0"));
+    assert!(qir_str.contains("call void @__quantum__qis__rz__body(double 0.5, "));
+
+    // The circuits of conditional operations keep their unbound symbols
+    let mut conditional_circuit = Circuit::new();
+    conditional_circuit += InputSymbolic::new("theta".to_owned(), 0.5);
+    conditional_circuit += RotateZ::new(0, CalculatorFloat::from("theta"));
+    conditional_circuit += PragmaConditional::new(
+        "ro".to_owned(),
+        0,
+        Circuit::from_iter([Operation::from(RotateX::new(
+            0,
+            CalculatorFloat::from("phi"),
+        ))]),
+    );
+    let qir_str = backend
+        .circuit_to_qir_str(&conditional_circuit, false)
+        .unwrap();
+    assert!(qir_str.contains("define void @main(double %phi) #0"));
+    assert_eq!(
+        backend.circuit_to_qir_str_with_parameters(&conditional_circuit, &HashMap::new(), false),
+        Err(QirError::UnboundParameter {
+            index: 2,
+            hqslang: "PragmaConditional",
+            parameter: "phi".to_owned()
+        })
+    );
+
+    circuit += RotateZ::new(0, CalculatorFloat::from("theta + phi"));
+    assert_eq!(
+        backend.circuit_to_qir_str(&circuit, false),
+        Err(QirError::InvalidParameter {
+            parameter: "theta".to_owned(),
+            msg: "it is set by an InputSymbolic operation but used together with unbound symbols"
+                .to_owned()
+        })
+    );
+}
+
+/// Test that translation errors refer to the failing operation of the circuit
+#[test]
+fn test_error_location() {
+    let backend = Backend::new(Some("adaptive_profile".to_string()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += Hadamard::new(0);
+    circuit += PragmaConditional::new(
+        "ro".to_owned(),
+        0,
+        [Operation::from(QuantumRabi::new(
+            0,
+            0,
+            CalculatorFloat::ZERO,
+        ))]
+        .into_iter()
+        .collect(),
+    );
+    let error = backend.circuit_to_qir_str(&circuit, false).unwrap_err();
+    assert_eq!(
+        error,
+        QirError::UnsupportedOperation {
+            index: 1,
+            hqslang: "QuantumRabi"
+        }
+    );
+    assert_eq!(error.index(), Some(1));
+    assert_eq!(error.hqslang(), Some("QuantumRabi"));
+    assert_eq!(
+        error.to_string(),
+        "Operation 1 (QuantumRabi) is not supported by the QIR backend"
+    );
+    assert_eq!(
+        RoqoqoBackendError::from(error),
+        RoqoqoBackendError::GenericError {
+            msg: "Operation 1 (QuantumRabi) is not supported by the QIR backend".to_owned()
+        }
+    );
+
+    let mut circuit = Circuit::new();
+    circuit += GateDefinition::new(
+        [Operation::from(CNOT::new(0, 1))].into_iter().collect(),
+        "entangle".to_owned(),
+        vec![0, 1],
+        vec![],
+    );
+    circuit += PauliX::new(0);
+    circuit += CallDefinedGate::new("entangle".to_owned(), vec![0], vec![]);
+    assert_eq!(
+        backend.circuit_to_qir_str(&circuit, false),
+        Err(QirError::InvalidQubitCount {
+            index: 2,
+            hqslang: "CallDefinedGate",
+            msg: "Gate 'entangle' is defined on 2 qubits but called on 1".to_owned()
+        })
+    );
+
+    let mut circuit = Circuit::new();
+    circuit += MultiQubitCNOT::new(vec![]);
+    let error = backend.circuit_to_qir_str(&circuit, false).unwrap_err();
+    assert_eq!(error.index(), Some(0));
+    assert_eq!(
+        RoqoqoBackendError::from(error),
+        RoqoqoBackendError::GenericError {
+            msg: "Operation 0 (MultiQubitCNOT) acts on an invalid number of qubits: At least one qubit is needed".to_owned()
+        }
+    );
+}
+
+/// Test that the remaining single-qubit gates are declared once and defined where needed
+#[test]
+fn test_single_qubit_gates() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += InvSGate::new(0);
+    circuit += ControlledPauliY::new(0, 1);
+    circuit += GPi2::new(1, CalculatorFloat::from("theta"));
+    circuit += RotateAroundSphericalAxis::new(
+        0,
+        CalculatorFloat::PI,
+        CalculatorFloat::FRAC_PI_2,
+        CalculatorFloat::ZERO,
+    );
+    circuit += SingleQubitGate::new(
+        1,
+        CalculatorFloat::FRAC_1_SQRT_2,
+        CalculatorFloat::ZERO,
+        CalculatorFloat::FRAC_1_SQRT_2,
+        CalculatorFloat::ZERO,
+        CalculatorFloat::ZERO,
+    );
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert_eq!(
+        qir_str
+            .matches("declare void @__quantum__qis__s__adj(%Qubit*)")
+            .count(),
+        1
+    );
+    assert!(qir_str.contains(
+        "define void @rxy(double %theta, double %phi, double %minus_phi, %Qubit* %qubit0)"
+    ));
+    assert!(qir_str.contains("define void @rsph(double %theta, double %spherical_theta, double %minus_spherical_theta, double %spherical_phi, double %minus_spherical_phi, %Qubit* %qubit0)"));
+    assert!(qir_str.contains("declare void @__quantum__qis__ry__body(double, %Qubit*)"));
+    assert!(qir_str.contains("define void @main(double %theta) #0"));
+}
+
+/// Test that the two-qubit helper gates are defined with their parameters
+#[test]
+fn test_two_qubit_gates() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += Fsim::new(
+        0,
+        1,
+        CalculatorFloat::from("t"),
+        CalculatorFloat::ZERO,
+        CalculatorFloat::ZERO,
+    );
+    circuit += Bogoliubov::new(0, 1, CalculatorFloat::from(0.5), CalculatorFloat::ZERO);
+    circuit += EchoCrossResonance::new(1, 0);
+    circuit += ControlledRotateXY::new(0, 1, CalculatorFloat::PI, CalculatorFloat::ZERO);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert!(qir_str.contains("define void @fsim(double %t_minus_delta, double %t_plus_delta, double %half_phase, double %minus_half_phase, %Qubit* %qubit0, %Qubit* %qubit1)"));
+    assert!(qir_str.contains("define void @bogoliubov(double %abs_delta, double %minus_abs_delta, double %phi, double %minus_phi, %Qubit* %qubit0, %Qubit* %qubit1)"));
+    assert!(qir_str.contains("define void @ecr(%Qubit* %qubit0, %Qubit* %qubit1)"));
+    assert!(qir_str.contains("define void @crxy(double %half_theta, double %minus_half_theta, double %phi, double %minus_phi, %Qubit* %qubit0, %Qubit* %qubit1)"));
+    assert!(qir_str.contains("declare void @__quantum__qis__x__body(%Qubit*)"));
+    assert!(qir_str.contains("define void @main(double %t) #0"));
+}
+
+/// Test that multi-qubit gates are decomposed and count all of their qubits
+#[test]
+fn test_multi_qubit_gates() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += MultiQubitZZ::new(vec![0, 3, 1], CalculatorFloat::from("theta"));
+    circuit += MultiQubitMS::new(vec![1, 2], CalculatorFloat::FRAC_PI_2);
+    circuit += MultiQubitCNOT::new(vec![0, 1, 2, 5]);
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert!(qir_str.contains("\"required_num_qubits\"=\"6\""));
+    assert!(qir_str.contains("define void @main(double %theta) #0"));
+    assert!(qir_str.contains(
+        "  call void @__quantum__qis__rz__body(double %theta, %Qubit* inttoptr (i64 1 to %Qubit*))"
+    ));
+    assert_eq!(
+        qir_str
+            .matches("call void @__quantum__qis__h__body")
+            .count(),
+        2
+    );
+    assert_eq!(
+        qir_str
+            .matches("call void @__quantum__qis__rz__body(double 0.39269908169872414")
+            .count(),
+        8
+    );
+    assert!(qir_str.contains("declare void @__quantum__qis__cnot__body(%Qubit*, %Qubit*)"));
+    assert!(qir_str.contains("declare void @__quantum__qis__ry__body(double, %Qubit*)"));
+    assert!(!qir_str.contains("rzz"));
+}
+
+/// Test that the three- and four-qubit controlled gates are defined through helper gates
+#[test]
+fn test_multi_controlled_gates() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += ControlledSWAP::new(2, 0, 1);
+    circuit += TripleControlledPauliX::new(0, 1, 2, 3);
+    circuit += TripleControlledPhaseShift::new(0, 1, 2, 3, CalculatorFloat::from("theta"));
+    let qir_str = backend.circuit_to_qir_str(&circuit, false).unwrap();
+    assert!(qir_str.contains("define void @cswap(%Qubit* %qubit0, %Qubit* %qubit1, %Qubit* %qubit2) {\nentry:\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit2, %Qubit* %qubit1)\n  call void @__quantum__qis__ccx__body(%Qubit* %qubit0, %Qubit* %qubit1, %Qubit* %qubit2)\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit2, %Qubit* %qubit1)\n  ret void\n}"));
+    assert!(qir_str.contains(
+        "define void @c3x(%Qubit* %qubit0, %Qubit* %qubit1, %Qubit* %qubit2, %Qubit* %qubit3)"
+    ));
+    assert!(qir_str.contains("define void @c3p(double %frac_theta_8, double %minus_frac_theta_8, %Qubit* %qubit0, %Qubit* %qubit1, %Qubit* %qubit2, %Qubit* %qubit3)"));
+    assert_eq!(
+        qir_str
+            .matches("@__quantum__qis__rz__body(double %frac_theta_8, ")
+            .count(),
+        8
+    );
+    assert_eq!(
+        qir_str
+            .matches("@__quantum__qis__rz__body(double %minus_frac_theta_8, ")
+            .count(),
+        7
+    );
+    assert!(qir_str.contains("declare void @__quantum__qis__h__body(%Qubit*)"));
+    assert!(qir_str.contains("\"required_num_qubits\"=\"4\""));
+}
+
+/// Asserts that every helper function only uses its parameters and calls functions of the module,
+/// and that the printed module is accepted by `llvm-as` if LLVM is installed.
+fn assert_valid_module(module: &Module) {
+    for function in module.functions.iter() {
+        assert_eq!(function.free_values(), vec![], "@{}", function.name);
+        for instruction in function
+            .blocks
+            .iter()
+            .flat_map(|block| block.instructions.iter())
+        {
+            if let Instruction::Call { callee, .. } = instruction {
+                assert!(
+                    module.function(callee).is_some(),
+                    "@{callee} is not declared"
+                );
+            }
+        }
+    }
+    let Ok(mut llvm_as) = Command::new("llvm-as")
+        .args(["-o", "/dev/null", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    else {
+        return;
+    };
+    llvm_as
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(module.to_string().as_bytes())
+        .unwrap();
+    let output = llvm_as.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Test that the generated bodies of all helper gates are valid IR
+#[test_case(Operation::from(XY::new(0, 1, CalculatorFloat::from("theta"))), "xy"; "xy")]
+#[test_case(Operation::from(SWAP::new(0, 1)), "swap"; "swap")]
+#[test_case(Operation::from(ISwap::new(0, 1)), "iswap"; "iswap")]
+#[test_case(Operation::from(SqrtISwap::new(0, 1)), "siswap"; "siswap")]
+#[test_case(Operation::from(InvSqrtISwap::new(0, 1)), "siswap_adj"; "siswap_adj")]
+#[test_case(Operation::from(FSwap::new(0, 1)), "fswap"; "fswap")]
+#[test_case(Operation::from(PMInteraction::new(0, 1, CalculatorFloat::from("theta"))), "pmint"; "pmint")]
+#[test_case(Operation::from(GivensRotation::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "gvnsrot"; "gvnsrot")]
+#[test_case(Operation::from(GivensRotationLittleEndian::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "gvnsrotle"; "gvnsrotle")]
+#[test_case(Operation::from(PhaseShiftedControlledZ::new(0, 1, CalculatorFloat::from("phi"))), "pscz"; "pscz")]
+#[test_case(Operation::from(PhaseShiftedControlledPhase::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "pscp"; "pscp")]
+#[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::from("theta"))), "rxx"; "rxx")]
+#[test_case(Operation::from(ControlledPauliY::new(0, 1)), "cy"; "cy")]
+#[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::from("theta"))), "cp"; "cp")]
+#[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "rxy"; "rxy")]
+#[test_case(Operation::from(RotateAroundSphericalAxis::new(0, CalculatorFloat::from("theta"), CalculatorFloat::from("spherical_theta"), CalculatorFloat::from("spherical_phi"))), "rsph"; "rsph")]
+#[test_case(Operation::from(ControlledControlledPauliZ::new(0, 1, 2)), "ccz"; "ccz")]
+#[test_case(Operation::from(ControlledControlledPhaseShift::new(0, 1, 2, CalculatorFloat::from("theta"))), "ccp"; "ccp")]
+#[test_case(Operation::from(SpinInteraction::new(0, 1, CalculatorFloat::from("x"), CalculatorFloat::from("y"), CalculatorFloat::from("z"))), "spinint"; "spinint")]
+#[test_case(Operation::from(Qsim::new(0, 1, CalculatorFloat::from("x"), CalculatorFloat::from("y"), CalculatorFloat::from("z"))), "qsim"; "qsim")]
+#[test_case(Operation::from(Fsim::new(0, 1, CalculatorFloat::from("t"), CalculatorFloat::from("u"), CalculatorFloat::from("delta"))), "fsim"; "fsim")]
+#[test_case(Operation::from(Bogoliubov::new(0, 1, CalculatorFloat::from("delta_real"), CalculatorFloat::from("delta_imag"))), "bogoliubov"; "bogoliubov")]
+#[test_case(Operation::from(ComplexPMInteraction::new(0, 1, CalculatorFloat::from("t_real"), CalculatorFloat::from("t_imag"))), "cpmint"; "cpmint")]
+#[test_case(Operation::from(ControlledRotateX::new(0, 1, CalculatorFloat::from("theta"))), "crx"; "crx")]
+#[test_case(Operation::from(ControlledRotateXY::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "crxy"; "crxy")]
+#[test_case(Operation::from(EchoCrossResonance::new(0, 1)), "ecr"; "ecr")]
+#[test_case(Operation::from(ControlledSWAP::new(0, 1, 2)), "cswap"; "cswap")]
+#[test_case(Operation::from(TripleControlledPauliX::new(0, 1, 2, 3)), "c3x"; "c3x")]
+#[test_case(Operation::from(TripleControlledPauliZ::new(0, 1, 2, 3)), "c3z"; "c3z")]
+#[test_case(Operation::from(TripleControlledPhaseShift::new(0, 1, 2, 3, CalculatorFloat::from("theta"))), "c3p"; "c3p")]
+fn test_helper_gate_bodies(operation: Operation, name: &str) {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let circuit: Circuit = [operation].into_iter().collect();
+    let module = backend.circuit_to_qir_module(&circuit, true).unwrap();
+    assert!(!module.function(name).unwrap().is_declaration());
+    assert_valid_module(&module);
+}
@@ -0,0 +1,196 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Testing the roqoqo-qir bitcode writer
+
+#![cfg(feature = "bitcode")]
+
+use std::{fs, path::Path};
+
+use qoqo_calculator::CalculatorFloat;
+use roqoqo::{operations::*, Circuit, RoqoqoBackendError};
+use roqoqo_qir::ir::*;
+use roqoqo_qir::{Backend, PointerStyle};
+use test_case::test_case;
+
+/// Decodes the bitcode to search the function names stored as raw bytes in its string table
+fn string_table(bitcode: &[u8]) -> String {
+    String::from_utf8_lossy(bitcode).to_string()
+}
+
+/// Test that the bitcode is wrapped in the LLVM bitcode magic number and is word aligned
+#[test_case(None, None; "base_profile")]
+#[test_case(Some("adaptive_profile"), None; "adaptive_profile")]
+#[test_case(None, Some("1.0"); "version_1_0")]
+#[test_case(Some("adaptive_profile"), Some("2.0"); "version_2_0")]
+fn test_bitcode_header(profile: Option<&str>, version: Option<&str>) {
+    let backend = Backend::new(
+        profile.map(|profile| profile.to_owned()),
+        version.map(|version| version.to_owned()),
+    )
+    .unwrap();
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += Hadamard::new(0);
+    circuit += CNOT::new(0, 1);
+    circuit += RotateX::new(1, CalculatorFloat::FRAC_PI_2);
+    circuit += ControlledPauliY::new(1, 0);
+    circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    let bitcode = backend.circuit_to_qir_bitcode(&circuit, true).unwrap();
+    assert_eq!(&bitcode[..4], &[b'B', b'C', 0xC0, 0xDE]);
+    assert_eq!(bitcode.len() % 4, 0);
+    let strings = string_table(&bitcode);
+    for name in [
+        "main",
+        "cy",
+        "__quantum__qis__h__body",
+        "__quantum__qis__mz__body",
+        "__quantum__rt__result_record_output",
+    ] {
+        assert!(strings.contains(name));
+    }
+    // Writing the same module twice gives the same bytes
+    assert_eq!(
+        bitcode,
+        backend.circuit_to_qir_bitcode(&circuit, true).unwrap()
+    );
+}
+
+/// Test that both pointer styles and control flow can be written
+#[test]
+fn test_bitcode_control_flow() {
+    let mut backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut conditional_circuit = Circuit::new();
+    conditional_circuit += PauliX::new(1);
+    let mut loop_circuit = Circuit::new();
+    loop_circuit += Hadamard::new(0);
+    let mut circuit = Circuit::new();
+    circuit += DefinitionBit::new("ro".to_owned(), 1, true);
+    circuit += MeasureQubit::new(0, "ro".to_owned(), 0);
+    circuit += PragmaConditional::new("ro".to_owned(), 0, conditional_circuit);
+    circuit += PragmaLoop::new(CalculatorFloat::from(2), loop_circuit);
+    let typed = backend.circuit_to_qir_bitcode(&circuit, false).unwrap();
+    backend.set_pointer_style(PointerStyle::Opaque).unwrap();
+    let opaque = backend.circuit_to_qir_bitcode(&circuit, false).unwrap();
+    assert_ne!(typed, opaque);
+    assert!(string_table(&opaque).contains("__quantum__qis__read_result__body"));
+}
+
+/// Test that symbolic parameters of the entry point are named in the bitcode
+#[test]
+fn test_bitcode_symbolic_parameters() {
+    let backend = Backend::new(Some("adaptive_profile".to_owned()), None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += RotateX::new(0, CalculatorFloat::from("theta"));
+    let bitcode = backend.circuit_to_qir_bitcode(&circuit, false).unwrap();
+    let module = backend.circuit_to_qir_module(&circuit, false).unwrap();
+    assert_eq!(module.to_bitcode().unwrap(), bitcode);
+    assert_eq!(
+        module.functions[0].parameters,
+        vec![Parameter::named(Type::Double, "theta")]
+    );
+}
+
+/// Test that symbolic expressions, the called math functions and the float_computations flag
+/// are written to the bitcode
+#[test]
+fn test_bitcode_symbolic_expressions() {
+    let backend =
+        Backend::new(Some("adaptive_profile".to_owned()), Some("2.0".to_owned())).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += RotateX::new(0, CalculatorFloat::from("-2*theta + delta(theta)"));
+    circuit += RotateZ::new(0, CalculatorFloat::from("atan2(theta, 2)^2"));
+    let bitcode = backend.circuit_to_qir_bitcode(&circuit, false).unwrap();
+    let strings = string_table(&bitcode);
+    for name in ["llvm.fabs.f64", "llvm.pow.f64", "atan2"] {
+        assert!(strings.contains(name));
+    }
+    let module = backend.circuit_to_qir_module(&circuit, false).unwrap();
+    assert!(module
+        .module_flags
+        .contains(&ModuleFlag::strings("float_computations", ["double"])));
+    assert_eq!(module.to_bitcode().unwrap(), bitcode);
+}
+
+/// Test that modules referencing undefined functions, labels or values are rejected
+#[test]
+fn test_bitcode_errors() {
+    let mut module = Module::new(PointerStyle::Typed);
+    let mut main = Function::definition("main", Type::Void, vec![]);
+    main.push(Instruction::call("undeclared", vec![Value::Qubit(0)]));
+    main.push(Instruction::Return(None));
+    module.functions.push(main);
+    assert_eq!(
+        module.to_bitcode(),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Unable to write bitcode: function '@undeclared' is not declared".to_owned()
+        })
+    );
+
+    let mut module = Module::new(PointerStyle::Typed);
+    let mut main = Function::definition("main", Type::Void, vec![]);
+    main.push(Instruction::Branch {
+        label: "missing".to_owned(),
+    });
+    module.functions.push(main);
+    assert_eq!(
+        module.to_bitcode(),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Unable to write bitcode: label 'missing' is not defined".to_owned()
+        })
+    );
+
+    let mut module = Module::new(PointerStyle::Typed);
+    let mut main = Function::definition("main", Type::Void, vec![]);
+    main.push(Instruction::Return(Some(Value::local(
+        "theta",
+        Type::Double,
+    ))));
+    module.functions.push(main);
+    assert_eq!(
+        module.to_bitcode(),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Unable to write bitcode: value '%theta' is not defined".to_owned()
+        })
+    );
+}
+
+/// Test writing bitcode files with the .bc extension
+#[test]
+fn test_bitcode_file() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit.add_operation(PauliX::new(0));
+    backend
+        .circuit_to_qir_bitcode_file(
+            &circuit,
+            Path::new("."),
+            Path::new("bitcode_test"),
+            false,
+            true,
+        )
+        .unwrap();
+    assert_eq!(
+        fs::read(Path::new("bitcode_test.bc")).unwrap(),
+        backend.circuit_to_qir_bitcode(&circuit, true).unwrap()
+    );
+    assert!(backend
+        .circuit_to_qir_bitcode_file(
+            &circuit,
+            Path::new("."),
+            Path::new("bitcode_test"),
+            false,
+            true
+        )
+        .is_err());
+    fs::remove_file(Path::new("bitcode_test.bc")).unwrap();
+}
@@ -0,0 +1,104 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Testing the lowering of symbolic expressions
+
+use qoqo_calculator::CalculatorError;
+use roqoqo::RoqoqoBackendError;
+use roqoqo_qir::ir::*;
+use roqoqo_qir::{lower_expression, math_declarations, PointerStyle, QirError, TranslationContext};
+use std::f64::consts::{FRAC_PI_2, PI};
+use test_case::test_case;
+
+/// Test that expressions without variables are evaluated
+#[test_case("pi/2", FRAC_PI_2; "pi_2")]
+#[test_case("-pi", -PI; "minus_pi")]
+#[test_case("2*pi + 0.5", 2.0 * PI + 0.5; "sum")]
+#[test_case("sin(pi/2)^2 - 1e-1", 0.9; "function")]
+#[test_case("max(2, 3) ** 2", 9.0; "two_arguments")]
+#[test_case("theta(0)", 0.5; "step_function")]
+fn test_constant_expression(expression: &str, value: f64) {
+    let mut fragment = Fragment::default();
+    let result =
+        lower_expression(expression, &mut fragment, &mut TranslationContext::new()).unwrap();
+    assert!(fragment.is_empty());
+    match result {
+        Value::Double(result) => assert!((result - value).abs() < 1e-12),
+        _ => panic!("{} is not constant", expression),
+    }
+}
+
+/// Test the instructions emitted for symbolic expressions
+#[test_case("theta", "", "%theta"; "variable")]
+#[test_case("2*theta + 0.5", "  %0 = fmul double 2.0, %theta\n  %1 = fadd double %0, 0.5", "%1"; "linear")]
+#[test_case("(-0.5e0 * theta)", "  %0 = fmul double -0.5, %theta", "%0"; "calculator_float_product")]
+#[test_case("-theta/pi", "  %0 = fneg double %theta\n  %1 = fdiv double %0, 3.141592653589793", "%1"; "negation")]
+#[test_case("sin(theta)*cos(phi - 1)", "  %0 = call double @llvm.sin.f64(double %theta)\n  %1 = fsub double %phi, 1.0\n  %2 = call double @llvm.cos.f64(double %1)\n  %3 = fmul double %0, %2", "%3"; "intrinsics")]
+#[test_case("theta^2", "  %0 = call double @llvm.pow.f64(double %theta, double 2.0)", "%0"; "power")]
+#[test_case("atan2(theta, 1)", "  %0 = call double @atan2(double %theta, double 1.0)", "%0"; "math_library")]
+#[test_case("fract(theta)", "  %0 = call double @llvm.trunc.f64(double %theta)\n  %1 = fsub double %theta, %0", "%1"; "fract")]
+#[test_case("sign(theta)", "  %0 = call double @llvm.copysign.f64(double 1.0, double %theta)", "%0"; "sign")]
+#[test_case("delta(theta)", "  %0 = call double @llvm.fabs.f64(double %theta)\n  %1 = fcmp olt double %0, 0.0000000000000002220446049250313\n  %2 = select i1 %1, double 1.0, double 0.0", "%2"; "delta")]
+#[test_case("theta(theta)", "  %0 = fcmp oeq double %theta, 0.0\n  %1 = fcmp olt double %theta, 0.0\n  %2 = select i1 %1, double 0.0, double 1.0\n  %3 = select i1 %0, double 0.5, double %2", "%3"; "step_function")]
+fn test_symbolic_expression(expression: &str, instructions: &str, result: &str) {
+    let mut fragment = Fragment::default();
+    let value =
+        lower_expression(expression, &mut fragment, &mut TranslationContext::new()).unwrap();
+    assert_eq!(fragment.to_qir(PointerStyle::Typed), instructions);
+    assert_eq!(value.to_qir(PointerStyle::Typed), result);
+    assert_eq!(value.ty(), Type::Double);
+}
+
+/// Test the errors for expressions that can not be lowered
+#[test_case("theta / 0", CalculatorError::DivisionByZero; "division_by_zero")]
+#[test_case("parity(theta)", CalculatorError::FunctionNotFound { fct: "parity".to_owned() }; "unknown_function")]
+#[test_case("sin(theta, phi)", CalculatorError::NotEnoughFunctionArguments; "wrong_arguments")]
+#[test_case("theta!", CalculatorError::NotImplementedError { fct: "Factorial" }; "factorial")]
+#[test_case("theta = 1", CalculatorError::ForbiddenAssign { variable_name: "theta".to_owned() }; "assignment")]
+#[test_case("(theta", CalculatorError::ParsingError { msg: "Expected Braket close" }; "bracket")]
+#[test_case("theta phi", CalculatorError::ParsingError { msg: "Expected end of expression" }; "trailing")]
+#[test_case("theta[0]", CalculatorError::ParsingError { msg: "Unrecognized token" }; "unrecognized")]
+fn test_expression_error(expression: &str, error: CalculatorError) {
+    let mut fragment = Fragment::default();
+    assert_eq!(
+        lower_expression(expression, &mut fragment, &mut TranslationContext::new()),
+        Err(QirError::Backend(RoqoqoBackendError::CalculatorError(
+            error
+        )))
+    );
+}
+
+/// Test that every called math function is declared once
+#[test]
+fn test_math_declarations() {
+    let mut function = Function::definition("main", Type::Void, vec![]);
+    let mut fragment = Fragment::default();
+    lower_expression(
+        "sin(theta) + tan(sin(phi)) * hypot(theta, 2)",
+        &mut fragment,
+        &mut TranslationContext::new(),
+    )
+    .unwrap();
+    function.append(fragment);
+    function.push(Instruction::Return(None));
+    assert_eq!(
+        math_declarations(&[function])
+            .iter()
+            .map(|declaration| declaration.to_qir(PointerStyle::Typed))
+            .collect::<Vec<String>>(),
+        vec![
+            "declare double @llvm.sin.f64(double)",
+            "declare double @tan(double)",
+            "declare double @hypot(double, double)",
+        ]
+    );
+}
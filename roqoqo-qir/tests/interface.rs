@@ -11,12 +11,16 @@
 // limitations under the License.
 //
 //! Testing the roqoqo-qir Interface
-//! run with `RUST_TEST_THREADS=1 cargo test`
 
+use ndarray::{array, s, Array2};
+use num_complex::Complex64;
 use qoqo_calculator::CalculatorFloat;
 use roqoqo::{operations::*, Circuit};
-use roqoqo_qir::{call_operation, gate_declaration, NUMBER_LABEL, NUMBER_VARS};
-use std::f64::consts::PI;
+use roqoqo_qir::ir::{BinaryOperator, Instruction, Module, Value};
+use roqoqo_qir::{
+    call_operation, gate_declaration, Backend, PointerStyle, QirError, TranslationContext,
+};
+use std::{collections::HashMap, f64::consts::PI};
 use test_case::test_case;
 
 /// Test that all operations return the correct gate declaration
@@ -38,7 +42,7 @@ use test_case::test_case;
 #[test_case(Operation::from(FSwap::new(0, 1)), ""; "FSwap")]
 #[test_case(Operation::from(MeasureQubit::new(0,"ro".to_owned(), 0)), "declare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1"; "MeasureQubit")]
 #[test_case(Operation::from(Toffoli::new(0, 1, 2)), "declare void @__quantum__qis__ccx__body(%Qubit*, %Qubit*, %Qubit*)"; "Toffoli")]
-#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(RotateX::new(0, CalculatorFloat::from("theta"))), Operation::from(RotateX::new(1, CalculatorFloat::PI))].into_iter().collect(), "test_gate".to_owned(), vec![0, 1], vec!["theta".to_owned()])), "\ndefine void @test_gate(double %theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__rx__body(double %theta, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double 3.141592653589793, %Qubit* %qubit1)\n  ret void\n}\n"; "GateDefinition")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(RotateX::new(0, CalculatorFloat::from("theta"))), Operation::from(RotateX::new(1, CalculatorFloat::PI))].into_iter().collect(), "test_gate".to_owned(), vec![0, 1], vec!["theta".to_owned()])), "define void @test_gate(double %theta, %Qubit* %qubit0, %Qubit* %qubit1) {\nentry:\n  call void @__quantum__qis__rx__body(double %theta, %Qubit* %qubit0)\n  call void @__quantum__qis__rx__body(double 3.141592653589793, %Qubit* %qubit1)\n  ret void\n}"; "GateDefinition")]
 #[test_case(Operation::from(CallDefinedGate::new("test".to_owned(), vec![0, 1], vec![CalculatorFloat::from("3.14")])), ""; "CallDefinedGate")]
 #[test_case(Operation::from(PragmaConditional::new("q".to_owned(), 0, Circuit::new())), ""; "PragmaConditional")]
 #[test_case(Operation::from(PragmaLoop::new(CalculatorFloat::Float(5.2), Circuit::new())), ""; "PragmaLoop")]
@@ -54,15 +58,80 @@ use test_case::test_case;
 #[test_case(Operation::from(PhaseShiftState1::new(4, CalculatorFloat::from("-pi/4"))), ""; "PhaseShiftState1")]
 #[test_case(Operation::from(MolmerSorensenXX::new(0, 1)), ""; "MolmerSorensenXX")]
 #[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::FRAC_PI_4)), ""; "VariableMSXX")]
-#[test_case(Operation::from(ControlledPauliY::new(0, 1)), "\ndefine void @cy(%Qubit* qubit0, %Qubit* qubit1) {\nentry:\n  call void @__quantum__qis__s__adj(%Qubit* qubit1)\n  call void @__quantum__qis__cnot__body(%Qubit* qubit0, %Qubit* qubit1)\n  call void @__quantum__qis__s__body(%Qubit* qubit1)\n  ret void\n}\n"; "ControlledPauliY")]
+#[test_case(Operation::from(ControlledPauliY::new(0, 1)), ""; "ControlledPauliY")]
 #[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::FRAC_PI_2)), ""; "ControlledPhaseShift")]
 #[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::from("1"), CalculatorFloat::FRAC_PI_2)), ""; "RotateXY")]
 #[test_case(Operation::from(ControlledControlledPauliZ::new(0, 1, 2)), ""; "ControlledControlledPauliZ")]
 #[test_case(Operation::from(ControlledControlledPhaseShift::new(0, 1, 2, CalculatorFloat::PI)), ""; "ControlledControlledPhaseShift")]
-#[serial_test::serial]
+#[test_case(Operation::from(SqrtPauliY::new(0)), ""; "SqrtPauliY")]
+#[test_case(Operation::from(InvSqrtPauliY::new(0)), ""; "InvSqrtPauliY")]
+#[test_case(Operation::from(InvSGate::new(0)), "declare void @__quantum__qis__s__adj(%Qubit*)"; "InvSGate")]
+#[test_case(Operation::from(InvTGate::new(0)), "declare void @__quantum__qis__t__adj(%Qubit*)"; "InvTGate")]
+#[test_case(Operation::from(PhaseShiftState0::new(0, CalculatorFloat::FRAC_PI_4)), ""; "PhaseShiftState0")]
+#[test_case(Operation::from(RotateAroundSphericalAxis::new(0, CalculatorFloat::PI, CalculatorFloat::FRAC_PI_2, CalculatorFloat::FRAC_PI_4)), ""; "RotateAroundSphericalAxis")]
+#[test_case(Operation::from(SingleQubitGate::new(0, CalculatorFloat::ZERO, CalculatorFloat::from(1.0), CalculatorFloat::ZERO, CalculatorFloat::ZERO, CalculatorFloat::ZERO)), ""; "SingleQubitGate")]
+#[test_case(Operation::from(GPi::new(0, CalculatorFloat::FRAC_PI_4)), ""; "GPi")]
+#[test_case(Operation::from(GPi2::new(0, CalculatorFloat::FRAC_PI_4)), ""; "GPi2")]
+#[test_case(Operation::from(SpinInteraction::new(0, 1, CalculatorFloat::FRAC_PI_4, CalculatorFloat::from(0.5), CalculatorFloat::ZERO)), ""; "SpinInteraction")]
+#[test_case(Operation::from(Qsim::new(0, 1, CalculatorFloat::FRAC_PI_4, CalculatorFloat::from(0.5), CalculatorFloat::ZERO)), ""; "Qsim")]
+#[test_case(Operation::from(Fsim::new(0, 1, CalculatorFloat::FRAC_PI_2, CalculatorFloat::ZERO, CalculatorFloat::FRAC_PI_4)), ""; "Fsim")]
+#[test_case(Operation::from(Bogoliubov::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from(2.0))), ""; "Bogoliubov")]
+#[test_case(Operation::from(ComplexPMInteraction::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from(2.0))), ""; "ComplexPMInteraction")]
+#[test_case(Operation::from(ControlledRotateX::new(0, 1, CalculatorFloat::PI)), ""; "ControlledRotateX")]
+#[test_case(Operation::from(ControlledRotateXY::new(0, 1, CalculatorFloat::PI, CalculatorFloat::FRAC_PI_4)), ""; "ControlledRotateXY")]
+#[test_case(Operation::from(EchoCrossResonance::new(0, 1)), ""; "EchoCrossResonance")]
+#[test_case(Operation::from(MultiQubitZZ::new(vec![0, 1, 2], CalculatorFloat::from(-PI))), ""; "MultiqubitZZ three qubits")]
+#[test_case(Operation::from(MultiQubitMS::new(vec![0, 1], CalculatorFloat::FRAC_PI_2)), ""; "MultiQubitMS")]
+#[test_case(Operation::from(MultiQubitCNOT::new(vec![0, 1, 2, 3])), ""; "MultiQubitCNOT")]
+#[test_case(Operation::from(ControlledSWAP::new(0, 1, 2)), ""; "ControlledSWAP")]
+#[test_case(Operation::from(TripleControlledPauliX::new(0, 1, 2, 3)), ""; "TripleControlledPauliX")]
+#[test_case(Operation::from(TripleControlledPauliZ::new(3, 2, 1, 0)), ""; "TripleControlledPauliZ")]
+#[test_case(Operation::from(TripleControlledPhaseShift::new(0, 1, 2, 3, CalculatorFloat::PI)), ""; "TripleControlledPhaseShift")]
 fn test_gate_definition(operation: Operation, converted: &str) {
-    *NUMBER_LABEL.lock().unwrap() = 0;
-    assert_eq!(gate_declaration(&operation).unwrap(), converted.to_string())
+    assert_eq!(
+        gate_declaration(&operation)
+            .unwrap()
+            .map(|function| function.to_qir(PointerStyle::Typed))
+            .unwrap_or_default(),
+        converted.to_string()
+    )
+}
+
+/// Test that the qubits of a GateDefinition are mapped to the arguments by index, not by position
+#[test]
+fn test_gate_definition_qubit_mapping() {
+    let gate_definition = GateDefinition::new(
+        [
+            Operation::from(CNOT::new(5, 2)),
+            Operation::from(Hadamard::new(2)),
+        ]
+        .into_iter()
+        .collect(),
+        "mapped".to_owned(),
+        vec![2, 5],
+        vec![],
+    );
+    assert_eq!(
+        gate_declaration(&Operation::from(gate_definition))
+            .unwrap()
+            .unwrap()
+            .to_qir(PointerStyle::Typed),
+        "define void @mapped(%Qubit* %qubit2, %Qubit* %qubit5) {\nentry:\n  call void @__quantum__qis__cnot__body(%Qubit* %qubit5, %Qubit* %qubit2)\n  call void @__quantum__qis__h__body(%Qubit* %qubit2)\n  ret void\n}"
+    );
+    let gate_definition = GateDefinition::new(
+        [Operation::from(PauliX::new(3))].into_iter().collect(),
+        "undeclared".to_owned(),
+        vec![0, 1],
+        vec![],
+    );
+    assert_eq!(
+        gate_declaration(&Operation::from(gate_definition)),
+        Err(QirError::InvalidDefinition {
+            index: 0,
+            hqslang: "GateDefinition",
+            msg: "Qubit 3 is used by PauliX in gate definition 'undeclared' but is not one of its qubits".to_owned()
+        })
+    );
 }
 
 /// Test that all operations return the correct gate call
@@ -98,44 +167,401 @@ fn test_gate_definition(operation: Operation, converted: &str) {
 #[test_case(Operation::from(PhaseShiftedControlledZ::new(0, 1, CalculatorFloat::from("-pi/4"))), "  call void @pscz(double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "PhaseShiftedControlledZ")]
 #[test_case(Operation::from(PhaseShiftedControlledPhase::new(0, 1, CalculatorFloat::PI, CalculatorFloat::FRAC_PI_4)), "  call void @pscp(double 1.5707963267948966, double -1.5707963267948966, double 0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "PhaseShiftedControlledPhase")]
 #[test_case(Operation::from(PhaseShiftState1::new(4, CalculatorFloat::from("pi/4"))), "  call void @__quantum__qis__rz__body(double 0.7853981633974483, %Qubit* inttoptr (i64 4 to %Qubit*))"; "PhaseShiftState1")]
-#[test_case(Operation::from(MolmerSorensenXX::new(0, 1)), "  call void @rxx(double 0.0, double 0.0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "MolmerSorensenXX")]
-#[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::FRAC_PI_2)), "  call void @rxx(double 0.7853981633974483, double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "VariableMSXX")]
+#[test_case(Operation::from(MolmerSorensenXX::new(0, 1)), "  call void @rxx(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "MolmerSorensenXX")]
+#[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::FRAC_PI_2)), "  call void @rxx(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "VariableMSXX")]
 #[test_case(Operation::from(ControlledPauliY::new(0, 1)), "  call void @cy(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledPauliY")]
 #[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::FRAC_PI_2)), "  call void @cp(double 0.7853981633974483, double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledPhaseShift")]
 #[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::from("1"), CalculatorFloat::from("-pi/2"))), "  call void @rxy(double 1.0, double -1.5707963267948966, double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))"; "RotateXY")]
 #[test_case(Operation::from(ControlledControlledPauliZ::new(0, 1, 2)), "  call void @ccz(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))"; "ControlledControlledPauliZ")]
 #[test_case(Operation::from(ControlledControlledPhaseShift::new(0, 1, 2, CalculatorFloat::PI)), "  call void @ccp(double 0.7853981633974483, double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))"; "ControlledControlledPhaseShift")]
 #[test_case(Operation::from(ControlledPauliZ::new(0, 1)), "  call void @__quantum__qis__cz__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledPauliZ")]
-#[serial_test::serial]
+#[test_case(Operation::from(SqrtPauliY::new(0)), "  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))"; "SqrtPauliY")]
+#[test_case(Operation::from(InvSqrtPauliY::new(0)), "  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))"; "InvSqrtPauliY")]
+#[test_case(Operation::from(InvSGate::new(1)), "  call void @__quantum__qis__s__adj(%Qubit* inttoptr (i64 1 to %Qubit*))"; "InvSGate")]
+#[test_case(Operation::from(InvTGate::new(2)), "  call void @__quantum__qis__t__adj(%Qubit* inttoptr (i64 2 to %Qubit*))"; "InvTGate")]
+#[test_case(Operation::from(PhaseShiftState0::new(4, CalculatorFloat::from("pi/4"))), "  call void @__quantum__qis__rz__body(double -0.7853981633974483, %Qubit* inttoptr (i64 4 to %Qubit*))"; "PhaseShiftState0")]
+#[test_case(Operation::from(RotateAroundSphericalAxis::new(0, CalculatorFloat::PI, CalculatorFloat::FRAC_PI_2, CalculatorFloat::FRAC_PI_4)), "  call void @rsph(double 3.141592653589793, double 1.5707963267948966, double -1.5707963267948966, double 0.7853981633974483, double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*))"; "RotateAroundSphericalAxis")]
+#[test_case(Operation::from(SingleQubitGate::new(0, CalculatorFloat::ZERO, CalculatorFloat::from(1.0), CalculatorFloat::ZERO, CalculatorFloat::ZERO, CalculatorFloat::ZERO)), "  call void @__quantum__qis__rz__body(double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__ry__body(double 0.0, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rz__body(double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))"; "SingleQubitGate")]
+#[test_case(Operation::from(SingleQubitGate::new(0, CalculatorFloat::FRAC_1_SQRT_2, CalculatorFloat::ZERO, CalculatorFloat::FRAC_1_SQRT_2, CalculatorFloat::ZERO, CalculatorFloat::ZERO)), "  call void @__quantum__qis__rz__body(double -0.0, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rz__body(double 0.0, %Qubit* inttoptr (i64 0 to %Qubit*))"; "SingleQubitGate rotation")]
+#[test_case(Operation::from(GPi::new(0, CalculatorFloat::FRAC_PI_4)), "  call void @__quantum__qis__x__body(%Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__rz__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))"; "GPi")]
+#[test_case(Operation::from(GPi2::new(0, CalculatorFloat::FRAC_PI_4)), "  call void @rxy(double 1.5707963267948966, double 0.7853981633974483, double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*))"; "GPi2")]
+#[test_case(Operation::from(SpinInteraction::new(0, 1, CalculatorFloat::FRAC_PI_4, CalculatorFloat::from(0.5), CalculatorFloat::ZERO)), "  call void @spinint(double 1.5707963267948966, double 1.0, double 0.0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "SpinInteraction")]
+#[test_case(Operation::from(Qsim::new(0, 1, CalculatorFloat::FRAC_PI_4, CalculatorFloat::from(0.5), CalculatorFloat::ZERO)), "  call void @qsim(double 1.5707963267948966, double 1.0, double 0.0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "Qsim")]
+#[test_case(Operation::from(Fsim::new(0, 1, CalculatorFloat::FRAC_PI_2, CalculatorFloat::ZERO, CalculatorFloat::FRAC_PI_4)), "  call void @fsim(double 0.7853981633974483, double 2.356194490192345, double 1.5707963267948966, double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "Fsim")]
+#[test_case(Operation::from(Bogoliubov::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from(2.0))), "  call void @bogoliubov(double 2.0, double -2.0, double 1.5707963267948966, double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "Bogoliubov")]
+#[test_case(Operation::from(ComplexPMInteraction::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from(2.0))), "  call void @cpmint(double 2.0, double 1.5707963267948966, double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ComplexPMInteraction")]
+#[test_case(Operation::from(ControlledRotateX::new(0, 1, CalculatorFloat::PI)), "  call void @crx(double 1.5707963267948966, double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledRotateX")]
+#[test_case(Operation::from(ControlledRotateXY::new(0, 1, CalculatorFloat::PI, CalculatorFloat::FRAC_PI_4)), "  call void @crxy(double 1.5707963267948966, double -1.5707963267948966, double 0.7853981633974483, double -0.7853981633974483, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledRotateXY")]
+#[test_case(Operation::from(EchoCrossResonance::new(0, 1)), "  call void @ecr(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "EchoCrossResonance")]
+#[test_case(Operation::from(MultiQubitZZ::new(vec![0, 1, 2], CalculatorFloat::PI)), "  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__rz__body(double 3.141592653589793, %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "MultiqubitZZ three qubits")]
+#[test_case(Operation::from(MultiQubitMS::new(vec![0, 1], CalculatorFloat::FRAC_PI_2)), "  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__ry__body(double -1.5707963267948966, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__rz__body(double 1.5707963267948966, %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__cnot__body(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* inttoptr (i64 0 to %Qubit*))\n  call void @__quantum__qis__ry__body(double 1.5707963267948966, %Qubit* inttoptr (i64 1 to %Qubit*))"; "MultiQubitMS")]
+#[test_case(Operation::from(MultiQubitCNOT::new(vec![2, 0, 1])), "  call void @__quantum__qis__ccx__body(%Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "MultiQubitCNOT")]
+#[test_case(Operation::from(ControlledSWAP::new(2, 0, 1)), "  call void @cswap(%Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledSWAP")]
+#[test_case(Operation::from(TripleControlledPauliX::new(0, 1, 2, 3)), "  call void @c3x(%Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 3 to %Qubit*))"; "TripleControlledPauliX")]
+#[test_case(Operation::from(TripleControlledPauliZ::new(3, 2, 1, 0)), "  call void @c3z(%Qubit* inttoptr (i64 3 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 0 to %Qubit*))"; "TripleControlledPauliZ")]
+#[test_case(Operation::from(TripleControlledPhaseShift::new(0, 1, 2, 3, CalculatorFloat::PI)), "  call void @c3p(double 0.39269908169872414, double -0.39269908169872414, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*), %Qubit* inttoptr (i64 3 to %Qubit*))"; "TripleControlledPhaseShift")]
 fn test_gate_call(operation: Operation, converted: &str) {
-    *NUMBER_LABEL.lock().unwrap() = 0;
-    *NUMBER_VARS.lock().unwrap() = 0;
-    assert_eq!(call_operation(&operation).unwrap(), converted.to_string())
+    assert_eq!(
+        call_operation(&operation, &mut TranslationContext::new())
+            .unwrap()
+            .to_qir(PointerStyle::Typed),
+        converted.to_string()
+    )
+}
+
+#[test_case(Operation::from(PauliX::new(0)), "  call void @__quantum__qis__x__body(ptr null)"; "PauliX")]
+#[test_case(Operation::from(CNOT::new(2, 0)), "  call void @__quantum__qis__cnot__body(ptr inttoptr (i64 2 to ptr), ptr null)"; "CNOT")]
+#[test_case(Operation::from(RotateZ::new(1, CalculatorFloat::FRAC_PI_2)), "  call void @__quantum__qis__rz__body(double 1.5707963267948966, ptr inttoptr (i64 1 to ptr))"; "RotateZ")]
+#[test_case(Operation::from(MeasureQubit::new(1, "ro".to_owned(), 0)), "  call void @__quantum__qis__mz__body(ptr inttoptr (i64 1 to ptr), ptr null) #1"; "MeasureQubit")]
+#[test_case(Operation::from(SWAP::new(0, 1)), "  call void @swap(ptr null, ptr inttoptr (i64 1 to ptr))"; "SWAP")]
+fn test_gate_call_opaque(operation: Operation, converted: &str) {
+    assert_eq!(
+        call_operation(&operation, &mut TranslationContext::new())
+            .unwrap()
+            .to_qir(PointerStyle::Opaque),
+        converted.to_string()
+    )
+}
+
+/// Test that operations translated with the same context continue its numbering
+#[test]
+fn test_shared_translation_context() {
+    let operation = Operation::from(XY::new(0, 1, CalculatorFloat::from("theta")));
+    let mut context = TranslationContext::new();
+    let first = call_operation(&operation, &mut context)
+        .unwrap()
+        .to_qir(PointerStyle::Typed);
+    let second = call_operation(&operation, &mut context)
+        .unwrap()
+        .to_qir(PointerStyle::Typed);
+    assert!(first.starts_with("  %0 = fmul double %theta, -0.5\n  call void @xy(double %0"));
+    assert!(second.starts_with("  %1 = fmul double %theta, -0.5\n  call void @xy(double %1"));
+    assert_eq!(
+        call_operation(&operation, &mut TranslationContext::new())
+            .unwrap()
+            .to_qir(PointerStyle::Typed),
+        first
+    );
+}
+
+#[test_case(Operation::from(CNOT::new(0, 1)), "declare void @__quantum__qis__cnot__body(ptr, ptr)"; "CNOT")]
+#[test_case(Operation::from(MeasureQubit::new(0,"ro".to_owned(), 0)), "declare void @__quantum__qis__mz__body(ptr, ptr writeonly) #1"; "MeasureQubit")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(RotateX::new(0, CalculatorFloat::from("theta"))), Operation::from(MeasureQubit::new(1, "ro".to_owned(), 0))].into_iter().collect(), "test_gate".to_owned(), vec![0, 1], vec!["theta".to_owned()])), "define void @test_gate(double %theta, ptr %qubit0, ptr %qubit1) #1 {\nentry:\n  call void @__quantum__qis__rx__body(double %theta, ptr %qubit0)\n  call void @__quantum__qis__mz__body(ptr %qubit1, ptr null) #1\n  ret void\n}"; "GateDefinition")]
+fn test_gate_definition_opaque(operation: Operation, converted: &str) {
+    assert_eq!(
+        gate_declaration(&operation)
+            .unwrap()
+            .map(|function| function.to_qir(PointerStyle::Opaque))
+            .unwrap_or_default(),
+        converted.to_string()
+    )
+}
+
+/// Test that derived arguments of symbolic gates are computed at runtime
+#[test_case(Operation::from(XY::new(0, 1, CalculatorFloat::from("theta"))), "  %0 = fmul double %theta, -0.5\n  call void @xy(double %0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "XY")]
+#[test_case(Operation::from(PhaseShiftedControlledPhase::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "  %0 = fmul double %theta, 0.5\n  %1 = fmul double %theta, -0.5\n  call void @pscp(double %0, double %1, double %phi, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "PhaseShiftedControlledPhase")]
+#[test_case(Operation::from(GivensRotation::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::from("phi"))), "  %0 = fneg double %theta\n  %1 = fadd double %phi, 1.5707963267948966\n  call void @gvnsrot(double %0, double %1, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "GivensRotation")]
+#[test_case(Operation::from(GivensRotationLittleEndian::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from("phi"))), "  %0 = fadd double %phi, 1.5707963267948966\n  call void @gvnsrotle(double -0.0, double %0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "GivensRotationLittleEndian")]
+#[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::from("2*theta"))), "  %0 = fmul double 2.0, %theta\n  call void @rxx(double %0, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "VariableMSXX")]
+#[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::from("theta"))), "  %0 = fmul double %theta, 0.5\n  %1 = fmul double %theta, -0.5\n  call void @cp(double %0, double %1, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*))"; "ControlledPhaseShift")]
+#[test_case(Operation::from(ControlledControlledPhaseShift::new(0, 1, 2, CalculatorFloat::from("theta"))), "  %0 = fmul double %theta, 0.25\n  %1 = fmul double %theta, -0.25\n  call void @ccp(double %0, double %1, %Qubit* inttoptr (i64 0 to %Qubit*), %Qubit* inttoptr (i64 1 to %Qubit*), %Qubit* inttoptr (i64 2 to %Qubit*))"; "ControlledControlledPhaseShift")]
+#[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::ZERO, CalculatorFloat::from("-phi"))), "  %0 = fneg double %phi\n  %1 = fneg double %0\n  call void @rxy(double 0.0, double %0, double %1, %Qubit* inttoptr (i64 0 to %Qubit*))"; "RotateXY")]
+fn test_gate_call_symbolic(operation: Operation, converted: &str) {
+    assert_eq!(
+        call_operation(&operation, &mut TranslationContext::new())
+            .unwrap()
+            .to_qir(PointerStyle::Typed),
+        converted.to_string()
+    )
+}
+
+#[test_case(Operation::from(PragmaLoop::new(CalculatorFloat::from("error"), vec![Operation::from(Hadamard::new(0))].into_iter().collect())), QirError::UnboundParameter { index: 0, hqslang: "PragmaLoop", parameter: "error".to_owned() }; "PragmaLoop")]
+#[test_case(Operation::from(QuantumRabi::new(0, 0, CalculatorFloat::ZERO)), QirError::UnsupportedOperation { index: 0, hqslang: "QuantumRabi" }; "QuantumRabi")]
+#[test_case(Operation::from(PragmaConditional::new("ro".to_owned(), 0, vec![Operation::from(QuantumRabi::new(0, 0, CalculatorFloat::ZERO))].into_iter().collect())), QirError::UnsupportedOperation { index: 0, hqslang: "QuantumRabi" }; "nested QuantumRabi")]
+#[test_case(Operation::from(MultiQubitCNOT::new(vec![])), QirError::InvalidQubitCount { index: 0, hqslang: "MultiQubitCNOT", msg: "At least one qubit is needed".to_owned() }; "MultiQubitCNOT without qubits")]
+fn test_gate_call_errors(operation: Operation, error: QirError) {
+    assert_eq!(
+        call_operation(&operation, &mut TranslationContext::new()),
+        Err(error)
+    )
+}
+
+#[test_case(Operation::from(QuantumRabi::new(0, 0, CalculatorFloat::ZERO)), QirError::UnsupportedOperation { index: 0, hqslang: "QuantumRabi" }; "QuantumRabi")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(CNOT::new(0, 2))].into_iter().collect(), "undeclared".to_owned(), vec![0, 1], vec![])), QirError::InvalidDefinition { index: 0, hqslang: "GateDefinition", msg: "Qubit 2 is used by CNOT in gate definition 'undeclared' but is not one of its qubits".to_owned() }; "GateDefinition undeclared qubit")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(CNOT::new(0, 1))].into_iter().collect(), "duplicate".to_owned(), vec![0, 1, 0], vec![])), QirError::InvalidDefinition { index: 0, hqslang: "GateDefinition", msg: "Qubit 0 is listed more than once in the qubits of gate definition 'duplicate'".to_owned() }; "GateDefinition duplicate qubit")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(QuantumRabi::new(0, 0, CalculatorFloat::ZERO))].into_iter().collect(), "unsupported".to_owned(), vec![0], vec![])), QirError::UnsupportedOperation { index: 0, hqslang: "QuantumRabi" }; "GateDefinition unsupported operation")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(RotateZ::new(0, CalculatorFloat::from("qubit0")))].into_iter().collect(), "qubit_parameter".to_owned(), vec![0], vec!["qubit0".to_owned()])), QirError::InvalidDefinition { index: 0, hqslang: "GateDefinition", msg: "Free parameter 'qubit0' of gate definition 'qubit_parameter' has the name of the argument of a qubit".to_owned() }; "GateDefinition qubit parameter")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(RotateZ::new(0, CalculatorFloat::from("entry")))].into_iter().collect(), "label_parameter".to_owned(), vec![0], vec!["entry".to_owned()])), QirError::InvalidDefinition { index: 0, hqslang: "GateDefinition", msg: "Free parameter 'entry' of gate definition 'label_parameter' has the name of the label of a basic block".to_owned() }; "GateDefinition label parameter")]
+#[test_case(Operation::from(GateDefinition::new(vec![Operation::from(RotateZ::new(0, CalculatorFloat::from("theta")))].into_iter().collect(), "duplicate_parameter".to_owned(), vec![0], vec!["theta".to_owned(), "theta".to_owned()])), QirError::InvalidDefinition { index: 0, hqslang: "GateDefinition", msg: "Free parameter 'theta' of gate definition 'duplicate_parameter' has the name of another free parameter".to_owned() }; "GateDefinition duplicate parameter")]
+fn test_gate_declaration_errors(operation: Operation, error: QirError) {
+    assert_eq!(gate_declaration(&operation), Err(error))
+}
+
+/// Value of the QIR interpreter used to simulate emitted modules
+#[derive(Debug, Clone, Copy)]
+enum SimulatedValue {
+    Double(f64),
+    Qubit(usize),
+}
+
+/// Returns the matrix of a `__quantum__qis__` intrinsic, qubit arguments in order of significance.
+fn intrinsic_matrix(name: &str, angles: &[f64]) -> Array2<Complex64> {
+    let c = |re: f64, im: f64| Complex64::new(re, im);
+    let (zero, one, i) = (c(0.0, 0.0), c(1.0, 0.0), c(0.0, 1.0));
+    let half = |angle: f64| angle / 2.0;
+    match name {
+        "h" => array![[one, one], [one, -one]] / c(2f64.sqrt(), 0.0),
+        "x" => array![[zero, one], [one, zero]],
+        "y" => array![[zero, -i], [i, zero]],
+        "z" => array![[one, zero], [zero, -one]],
+        "s" => array![[one, zero], [zero, i]],
+        "s_adj" => array![[one, zero], [zero, -i]],
+        "t" => array![[one, zero], [zero, Complex64::from_polar(1.0, PI / 4.0)]],
+        "t_adj" => array![[one, zero], [zero, Complex64::from_polar(1.0, -PI / 4.0)]],
+        "rx" => array![
+            [
+                c(half(angles[0]).cos(), 0.0),
+                c(0.0, -half(angles[0]).sin())
+            ],
+            [
+                c(0.0, -half(angles[0]).sin()),
+                c(half(angles[0]).cos(), 0.0)
+            ]
+        ],
+        "ry" => array![
+            [
+                c(half(angles[0]).cos(), 0.0),
+                c(-half(angles[0]).sin(), 0.0)
+            ],
+            [c(half(angles[0]).sin(), 0.0), c(half(angles[0]).cos(), 0.0)]
+        ],
+        "rz" => Array2::from_diag(&array![
+            Complex64::from_polar(1.0, -half(angles[0])),
+            Complex64::from_polar(1.0, half(angles[0]))
+        ]),
+        "rzz" => Array2::from_diag(&array![
+            Complex64::from_polar(1.0, -half(angles[0])),
+            Complex64::from_polar(1.0, half(angles[0])),
+            Complex64::from_polar(1.0, half(angles[0])),
+            Complex64::from_polar(1.0, -half(angles[0]))
+        ]),
+        "cnot" => array![
+            [one, zero, zero, zero],
+            [zero, one, zero, zero],
+            [zero, zero, zero, one],
+            [zero, zero, one, zero]
+        ],
+        "cz" => Array2::from_diag(&array![one, one, one, -one]),
+        "ccx" => {
+            let mut matrix = Array2::<Complex64>::eye(8);
+            matrix
+                .slice_mut(s![6.., 6..])
+                .assign(&array![[zero, one], [one, zero]]);
+            matrix
+        }
+        _ => panic!("Unknown intrinsic {name}"),
+    }
+}
+
+/// Applies a gate on `qubits` to the unitary of `number_qubits` qubits, qubit 0 being the most significant.
+fn apply_gate(
+    unitary: &Array2<Complex64>,
+    gate: &Array2<Complex64>,
+    qubits: &[usize],
+    number_qubits: usize,
+) -> Array2<Complex64> {
+    let dimension = 1 << number_qubits;
+    let bit = |state: usize, qubit: usize| (state >> (number_qubits - 1 - qubit)) & 1;
+    let sub_index = |state: usize| {
+        qubits
+            .iter()
+            .fold(0, |index, qubit| (index << 1) | bit(state, *qubit))
+    };
+    let mask: usize = qubits
+        .iter()
+        .map(|qubit| 1 << (number_qubits - 1 - qubit))
+        .sum();
+    let mut full_gate = Array2::<Complex64>::zeros((dimension, dimension));
+    for row in 0..dimension {
+        for column in 0..dimension {
+            if row & !mask == column & !mask {
+                full_gate[[row, column]] = gate[[sub_index(row), sub_index(column)]];
+            }
+        }
+    }
+    full_gate.dot(unitary)
+}
+
+/// Simulates a call of a function of the module, applying the called intrinsics to `unitary`.
+fn simulate_call(
+    module: &Module,
+    name: &str,
+    arguments: &[SimulatedValue],
+    unitary: &mut Array2<Complex64>,
+    number_qubits: usize,
+) {
+    if let Some(intrinsic) = name.strip_prefix("__quantum__qis__") {
+        let intrinsic = intrinsic
+            .strip_suffix("__body")
+            .unwrap_or(&intrinsic.replace("__adj", "_adj"))
+            .to_owned();
+        let angles: Vec<f64> = arguments
+            .iter()
+            .filter_map(|argument| match argument {
+                SimulatedValue::Double(angle) => Some(*angle),
+                _ => None,
+            })
+            .collect();
+        let qubits: Vec<usize> = arguments
+            .iter()
+            .filter_map(|argument| match argument {
+                SimulatedValue::Qubit(qubit) => Some(*qubit),
+                _ => None,
+            })
+            .collect();
+        *unitary = apply_gate(
+            unitary,
+            &intrinsic_matrix(&intrinsic, &angles),
+            &qubits,
+            number_qubits,
+        );
+        return;
+    }
+    let function = module.function(name).unwrap();
+    let mut locals: HashMap<String, SimulatedValue> = function
+        .parameters
+        .iter()
+        .zip(arguments.iter())
+        .map(|(parameter, argument)| (parameter.name.clone().unwrap(), *argument))
+        .collect();
+    let evaluate = |value: &Value, locals: &HashMap<String, SimulatedValue>| match value {
+        Value::Double(value) => SimulatedValue::Double(*value),
+        Value::Qubit(qubit) => SimulatedValue::Qubit(*qubit),
+        Value::Local { name, .. } => locals[name],
+        _ => panic!("Unexpected value {value:?}"),
+    };
+    let double = |value: SimulatedValue| match value {
+        SimulatedValue::Double(value) => value,
+        SimulatedValue::Qubit(_) => panic!("Expected a double"),
+    };
+    for instruction in function.blocks[0].instructions.iter() {
+        match instruction {
+            Instruction::Call {
+                callee, arguments, ..
+            } => {
+                let arguments: Vec<SimulatedValue> = arguments
+                    .iter()
+                    .map(|argument| evaluate(argument, &locals))
+                    .collect();
+                simulate_call(module, callee, &arguments, unitary, number_qubits);
+            }
+            Instruction::Binary {
+                result,
+                operator,
+                lhs,
+                rhs,
+            } => {
+                let (lhs, rhs) = (
+                    double(evaluate(lhs, &locals)),
+                    double(evaluate(rhs, &locals)),
+                );
+                let value = match operator {
+                    BinaryOperator::FAdd => lhs + rhs,
+                    BinaryOperator::FSub => lhs - rhs,
+                    BinaryOperator::FMul => lhs * rhs,
+                    BinaryOperator::FDiv => lhs / rhs,
+                    _ => panic!("Unexpected operator {operator:?}"),
+                };
+                locals.insert(result.clone(), SimulatedValue::Double(value));
+            }
+            Instruction::FloatNegate { result, operand } => {
+                let value = -double(evaluate(operand, &locals));
+                locals.insert(result.clone(), SimulatedValue::Double(value));
+            }
+            Instruction::Return(_) => {}
+            _ => panic!("Unexpected instruction {instruction:?}"),
+        }
+    }
 }
 
-#[test_case(Operation::from(PragmaLoop::new(CalculatorFloat::from("error"), vec![Operation::from(Hadamard::new(0))].into_iter().collect())); "PragmaLoop")]
-#[test_case(Operation::from(MultiQubitZZ::new(vec![0, 1, 2], CalculatorFloat::from(-PI))); "MultiqubitZZ")]
-#[test_case(Operation::from(XY::new(0, 1, CalculatorFloat::from("theta"))); "XY")]
-#[test_case(Operation::from(PhaseShiftedControlledPhase::new(0, 1, CalculatorFloat::from("theta"), CalculatorFloat::ZERO)); "PhaseShiftedControlledPhase")]
-#[test_case(Operation::from(GivensRotation::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from("theta"))); "GivensRotation")]
-#[test_case(Operation::from(GivensRotation::new(0, 1, CalculatorFloat::from("theta"),  CalculatorFloat::ZERO)); "GivensRotation2")]
-#[test_case(Operation::from(GivensRotationLittleEndian::new(0, 1, CalculatorFloat::ZERO, CalculatorFloat::from("theta"))); "GivensRotationLittleEndian")]
-#[test_case(Operation::from(GivensRotationLittleEndian::new(0, 1, CalculatorFloat::from("theta"),  CalculatorFloat::ZERO)); "GivensRotationLittleEndian2")]
-#[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::from("theta"))); "VariableMSXX")]
-#[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::from("theta"))); "ControlledPhaseShift")]
-#[test_case(Operation::from(ControlledControlledPhaseShift::new(0, 1, 2, CalculatorFloat::from("theta"))); "ControlledControlledPhaseShift")]
-#[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::ZERO, CalculatorFloat::from("theta"))); "RotateXY")]
-#[serial_test::serial]
-fn test_gate_call_errors(operation: Operation) {
-    *NUMBER_LABEL.lock().unwrap() = 0;
-    *NUMBER_VARS.lock().unwrap() = 0;
-    assert!(call_operation(&operation).is_err())
+/// Asserts that two unitary matrices are equal up to a global phase.
+fn assert_equal_up_to_phase(actual: &Array2<Complex64>, expected: &Array2<Complex64>) {
+    let largest = expected
+        .indexed_iter()
+        .max_by(|(_, a), (_, b)| a.norm().total_cmp(&b.norm()))
+        .unwrap()
+        .0;
+    let phase = actual[largest] / expected[largest];
+    assert!((phase.norm() - 1.0).abs() < 1e-8, "{actual} != {expected}");
+    for (index, entry) in expected.indexed_iter() {
+        assert!(
+            (actual[index] - phase * entry).norm() < 1e-8,
+            "{actual} != {expected}"
+        );
+    }
 }
 
-#[test_case(Operation::from(MultiQubitZZ::new(vec![0, 1, 2], CalculatorFloat::from(-PI))); "MultiqubitZZ")]
-#[serial_test::serial]
-fn test_gate_declaration_errors(operation: Operation) {
-    *NUMBER_LABEL.lock().unwrap() = 0;
-    *NUMBER_VARS.lock().unwrap() = 0;
-    assert!(gate_declaration(&operation).is_err())
+/// Test that the emitted calls and helper gates implement the unitary matrix of the operation
+///
+/// The operations act on the qubits 0..n in the order of their unitary matrix.
+#[test_case(Operation::from(SqrtPauliY::new(0)); "SqrtPauliY")]
+#[test_case(Operation::from(InvSqrtPauliY::new(0)); "InvSqrtPauliY")]
+#[test_case(Operation::from(InvSGate::new(0)); "InvSGate")]
+#[test_case(Operation::from(InvTGate::new(0)); "InvTGate")]
+#[test_case(Operation::from(PhaseShiftState0::new(0, CalculatorFloat::from(0.3))); "PhaseShiftState0")]
+#[test_case(Operation::from(PhaseShiftState1::new(0, CalculatorFloat::from(0.3))); "PhaseShiftState1")]
+#[test_case(Operation::from(RotateAroundSphericalAxis::new(0, CalculatorFloat::from(0.4), CalculatorFloat::from(1.1), CalculatorFloat::from(-0.6))); "RotateAroundSphericalAxis")]
+#[test_case(Operation::from(SingleQubitGate::new(0, CalculatorFloat::from(0.5), CalculatorFloat::from(0.5), CalculatorFloat::from(0.5), CalculatorFloat::from(-0.5), CalculatorFloat::from(0.2))); "SingleQubitGate")]
+#[test_case(Operation::from(GPi::new(0, CalculatorFloat::from(0.7))); "GPi")]
+#[test_case(Operation::from(GPi2::new(0, CalculatorFloat::from(0.7))); "GPi2")]
+#[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::from(0.3), CalculatorFloat::from(1.2))); "RotateXY")]
+#[test_case(Operation::from(SWAP::new(0, 1)); "SWAP")]
+#[test_case(Operation::from(ISwap::new(0, 1)); "ISwap")]
+#[test_case(Operation::from(SqrtISwap::new(0, 1)); "SqrtISwap")]
+#[test_case(Operation::from(InvSqrtISwap::new(0, 1)); "InvSqrtISwap")]
+#[test_case(Operation::from(FSwap::new(0, 1)); "FSwap")]
+#[test_case(Operation::from(ControlledPauliY::new(0, 1)); "ControlledPauliY")]
+#[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::from(0.7))); "ControlledPhaseShift")]
+#[test_case(Operation::from(XY::new(0, 1, CalculatorFloat::from(0.7))); "XY")]
+#[test_case(Operation::from(MolmerSorensenXX::new(0, 1)); "MolmerSorensenXX")]
+#[test_case(Operation::from(VariableMSXX::new(0, 1, CalculatorFloat::from(0.7))); "VariableMSXX")]
+#[test_case(Operation::from(PMInteraction::new(0, 1, CalculatorFloat::from(0.7))); "PMInteraction")]
+#[test_case(Operation::from(GivensRotation::new(0, 1, CalculatorFloat::from(0.7), CalculatorFloat::from(0.4))); "GivensRotation")]
+#[test_case(Operation::from(GivensRotationLittleEndian::new(0, 1, CalculatorFloat::from(0.7), CalculatorFloat::from(0.4))); "GivensRotationLittleEndian")]
+#[test_case(Operation::from(PhaseShiftedControlledZ::new(0, 1, CalculatorFloat::from(0.4))); "PhaseShiftedControlledZ")]
+#[test_case(Operation::from(PhaseShiftedControlledPhase::new(0, 1, CalculatorFloat::from(0.7), CalculatorFloat::from(0.4))); "PhaseShiftedControlledPhase")]
+#[test_case(Operation::from(Fsim::new(0, 1, CalculatorFloat::from(0.3), CalculatorFloat::from(0.5), CalculatorFloat::from(0.7))); "Fsim")]
+#[test_case(Operation::from(Qsim::new(0, 1, CalculatorFloat::from(0.3), CalculatorFloat::from(0.5), CalculatorFloat::from(0.7))); "Qsim")]
+#[test_case(Operation::from(SpinInteraction::new(0, 1, CalculatorFloat::from(0.3), CalculatorFloat::from(0.5), CalculatorFloat::from(0.7))); "SpinInteraction")]
+#[test_case(Operation::from(Bogoliubov::new(0, 1, CalculatorFloat::from(0.37), CalculatorFloat::from(0.0))); "Bogoliubov real")]
+#[test_case(Operation::from(Bogoliubov::new(0, 1, CalculatorFloat::from(0.37), CalculatorFloat::from(-0.6))); "Bogoliubov complex")]
+#[test_case(Operation::from(ComplexPMInteraction::new(0, 1, CalculatorFloat::from(0.37), CalculatorFloat::from(-0.6))); "ComplexPMInteraction")]
+#[test_case(Operation::from(ControlledRotateX::new(0, 1, CalculatorFloat::from(0.7))); "ControlledRotateX")]
+#[test_case(Operation::from(ControlledRotateXY::new(0, 1, CalculatorFloat::from(0.7), CalculatorFloat::from(0.4))); "ControlledRotateXY")]
+#[test_case(Operation::from(EchoCrossResonance::new(0, 1)); "EchoCrossResonance")]
+#[test_case(Operation::from(ControlledControlledPauliZ::new(0, 1, 2)); "ControlledControlledPauliZ")]
+#[test_case(Operation::from(ControlledControlledPhaseShift::new(0, 1, 2, CalculatorFloat::from(0.7))); "ControlledControlledPhaseShift")]
+#[test_case(Operation::from(Toffoli::new(0, 1, 2)); "Toffoli")]
+#[test_case(Operation::from(ControlledSWAP::new(0, 1, 2)); "ControlledSWAP")]
+#[test_case(Operation::from(TripleControlledPauliX::new(0, 1, 2, 3)); "TripleControlledPauliX")]
+#[test_case(Operation::from(TripleControlledPauliZ::new(0, 1, 2, 3)); "TripleControlledPauliZ")]
+#[test_case(Operation::from(TripleControlledPhaseShift::new(0, 1, 2, 3, CalculatorFloat::from(0.7))); "TripleControlledPhaseShift")]
+#[test_case(Operation::from(MultiQubitZZ::new(vec![0, 1, 2], CalculatorFloat::from(0.7))); "MultiQubitZZ")]
+#[test_case(Operation::from(MultiQubitMS::new(vec![0, 1, 2], CalculatorFloat::from(0.7))); "MultiQubitMS")]
+#[test_case(Operation::from(MultiQubitCNOT::new(vec![0, 1, 2])); "MultiQubitCNOT")]
+fn test_helper_gate_unitaries(operation: Operation) {
+    let number_qubits = match operation.involved_qubits() {
+        InvolvedQubits::Set(qubits) => qubits.len(),
+        _ => panic!("The operation has to act on a set of qubits"),
+    };
+    let backend = Backend::new(None, None).unwrap();
+    let circuit: Circuit = [operation.clone()].into_iter().collect();
+    let module = backend.circuit_to_qir_module(&circuit, false).unwrap();
+    let mut unitary = Array2::<Complex64>::eye(1 << number_qubits);
+    simulate_call(&module, "main", &[], &mut unitary, number_qubits);
+    let expected = GateOperation::try_from(operation)
+        .unwrap()
+        .unitary_matrix()
+        .unwrap();
+    assert_equal_up_to_phase(&unitary, &expected);
 }
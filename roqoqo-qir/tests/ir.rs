@@ -0,0 +1,206 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Testing the roqoqo-qir IR module and printer
+
+use roqoqo::{operations::*, Circuit};
+use roqoqo_qir::ir::*;
+use roqoqo_qir::{Backend, PointerStyle};
+use test_case::test_case;
+
+/// Test the printing of values in both pointer styles
+#[test_case(Value::Qubit(0), "%Qubit* inttoptr (i64 0 to %Qubit*)", "ptr null"; "Qubit0")]
+#[test_case(Value::Qubit(3), "%Qubit* inttoptr (i64 3 to %Qubit*)", "ptr inttoptr (i64 3 to ptr)"; "Qubit3")]
+#[test_case(Value::Result(0), "%Result* inttoptr (i64 0 to %Result*)", "ptr null"; "Result0")]
+#[test_case(Value::Null(Pointee::I8), "i8* null", "ptr null"; "Label")]
+#[test_case(Value::Double(2.0), "double 2.0", "double 2.0"; "Integral double")]
+#[test_case(Value::Double(0.25), "double 0.25", "double 0.25"; "Double")]
+#[test_case(Value::Double(f64::NEG_INFINITY), "double 0xFFF0000000000000", "double 0xFFF0000000000000"; "Infinite double")]
+#[test_case(Value::Integer { bits: 1, value: 1 }, "i1 true", "i1 true"; "Boolean")]
+#[test_case(Value::Integer { bits: 64, value: -4 }, "i64 -4", "i64 -4"; "Integer")]
+#[test_case(Value::local("qubit1", Type::QUBIT), "%Qubit* %qubit1", "ptr %qubit1"; "Local")]
+fn test_value_to_qir(value: Value, typed: &str, opaque: &str) {
+    assert_eq!(value.to_typed_qir(PointerStyle::Typed), typed);
+    assert_eq!(value.to_typed_qir(PointerStyle::Opaque), opaque);
+}
+
+#[test]
+fn test_fragment() {
+    let mut fragment = Fragment::default();
+    assert!(fragment.is_empty());
+    fragment.push(Instruction::call(
+        "__quantum__qis__h__body",
+        vec![Value::Qubit(2)],
+    ));
+    fragment.start_block("next");
+    fragment.append(Fragment::from(Instruction::call(
+        "__quantum__qis__x__body",
+        vec![Value::Qubit(0)],
+    )));
+    fragment.map_values(&|value| match value {
+        Value::Qubit(0) => Some(Value::local("qubit0", Type::QUBIT)),
+        _ => None,
+    });
+    assert_eq!(fragment.instructions.len(), 1);
+    assert_eq!(fragment.blocks.len(), 1);
+    assert_eq!(
+        fragment.to_qir(PointerStyle::Opaque),
+        "  call void @__quantum__qis__h__body(ptr inttoptr (i64 2 to ptr))\n\nnext:\n  call void @__quantum__qis__x__body(ptr %qubit0)"
+    );
+}
+
+/// Test the printing of floating point instructions
+#[test]
+fn test_float_instructions() {
+    let theta = Value::local("theta", Type::Double);
+    let instructions = [
+        Instruction::Binary {
+            result: "0".to_owned(),
+            operator: BinaryOperator::FMul,
+            lhs: Value::Double(2.0),
+            rhs: theta.clone(),
+        },
+        Instruction::FloatNegate {
+            result: "1".to_owned(),
+            operand: theta.clone(),
+        },
+        Instruction::FloatCompare {
+            result: "2".to_owned(),
+            predicate: FloatPredicate::Olt,
+            lhs: theta,
+            rhs: Value::Double(0.0),
+        },
+        Instruction::Select {
+            result: "3".to_owned(),
+            condition: Value::local("2", Type::Integer(1)),
+            true_value: Value::Double(0.0),
+            false_value: Value::Double(1.0),
+        },
+    ];
+    assert_eq!(
+        instructions
+            .iter()
+            .map(|instruction| instruction.to_qir(PointerStyle::Typed))
+            .collect::<Vec<String>>(),
+        vec![
+            "%0 = fmul double 2.0, %theta",
+            "%1 = fneg double %theta",
+            "%2 = fcmp olt double %theta, 0.0",
+            "%3 = select i1 %2, double 0.0, double 1.0",
+        ]
+    );
+    assert_eq!(instructions[3].result(), Some("3"));
+    assert_eq!(instructions[3].values().len(), 3);
+}
+
+#[test]
+fn test_function() {
+    let mut declaration = Function::declaration(
+        "__quantum__qis__mz__body",
+        Type::Void,
+        &[Type::QUBIT, Type::RESULT],
+    );
+    declaration.parameters[1]
+        .attributes
+        .push("writeonly".to_owned());
+    declaration.attribute_group = Some(1);
+    assert!(declaration.is_declaration());
+    assert_eq!(
+        declaration.to_qir(PointerStyle::Typed),
+        "declare void @__quantum__qis__mz__body(%Qubit*, %Result* writeonly) #1"
+    );
+
+    let mut definition = Function::definition(
+        "flip",
+        Type::Void,
+        vec![Parameter::named(Type::QUBIT, "qubit0")],
+    );
+    let qubit = definition.parameters[0].value().unwrap();
+    definition.push(Instruction::call("__quantum__qis__x__body", vec![qubit]));
+    definition.push(Instruction::Return(None));
+    assert!(!definition.is_declaration());
+    assert_eq!(
+        definition.to_qir(PointerStyle::Opaque),
+        "define void @flip(ptr %qubit0) {\nentry:\n  call void @__quantum__qis__x__body(ptr %qubit0)\n  ret void\n}"
+    );
+}
+
+#[test]
+fn test_function_free_values() {
+    let mut function = Function::definition(
+        "rotate",
+        Type::Void,
+        vec![Parameter::named(Type::Double, "theta")],
+    );
+    function.push(Instruction::Call {
+        result: Some("angle".to_owned()),
+        return_type: Type::Double,
+        callee: "angle".to_owned(),
+        arguments: vec![Value::local("phi", Type::Double)],
+        attribute_group: None,
+    });
+    function.push(Instruction::call(
+        "__quantum__qis__rz__body",
+        vec![
+            Value::local("theta", Type::Double),
+            Value::local("angle", Type::Double),
+            Value::local("phi", Type::Double),
+            Value::local("qubit", Type::QUBIT),
+        ],
+    ));
+    assert_eq!(
+        function.free_values(),
+        vec![
+            ("phi".to_owned(), Type::Double),
+            ("qubit".to_owned(), Type::QUBIT)
+        ]
+    );
+    assert_eq!(function.blocks[0].instructions[0].result(), Some("angle"));
+    assert_eq!(function.blocks[0].instructions[1].values().len(), 4);
+}
+
+/// Test that a translated module can be inspected and transformed before printing
+#[test]
+fn test_module_transformation() {
+    let backend = Backend::new(None, None).unwrap();
+    let mut circuit = Circuit::new();
+    circuit += Hadamard::new(0);
+    circuit += CNOT::new(0, 1);
+    let mut module = backend.circuit_to_qir_module(&circuit, false).unwrap();
+
+    assert_eq!(module.pointer_style, PointerStyle::Typed);
+    assert!(!module.function("main").unwrap().is_declaration());
+    assert!(module
+        .function("__quantum__qis__cnot__body")
+        .unwrap()
+        .is_declaration());
+    assert!(module.function("__quantum__qis__x__body").is_none());
+    assert_eq!(
+        module.to_string(),
+        backend.circuit_to_qir_str(&circuit, false).unwrap()
+    );
+
+    module.pointer_style = PointerStyle::Opaque;
+    module
+        .functions
+        .retain(|function| !function.is_declaration());
+    module.module_flags.truncate(1);
+    assert_eq!(module.to_string(), "define void @main() #0 {\nentry:\n  call void @__quantum__qis__h__body(ptr null)\n  call void @__quantum__qis__cnot__body(ptr null, ptr inttoptr (i64 1 to ptr))\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"2\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}");
+}
+
+/// Test that the sections of a module are separated by exactly one blank line
+#[test]
+fn test_empty_circuit() {
+    let backend = Backend::new(None, None).unwrap();
+    let qir_str = backend.circuit_to_qir_str(&Circuit::new(), false).unwrap();
+    assert_eq!(qir_str, "%Qubit = type opaque\n\ndefine void @main() #0 {\nentry:\n  ret void\n}\n\nattributes #0 = { \"entry_point\" \"required_num_qubits\"=\"0\" \"required_num_results\"=\"0\" \"output_labeling_schema\" \"qir_profiles\"=\"base_profile\" }\n\n!llvm.module.flags = !{!0, !1, !2, !3}\n\n!0 = !{i32 1, !\"qir_major_version\", i32 1}\n!1 = !{i32 7, !\"qir_minor_version\", i32 0}\n!2 = !{i32 1, !\"dynamic_qubit_management\", i1 false}\n!3 = !{i32 1, !\"dynamic_result_management\", i1 false}");
+}
@@ -15,3 +15,18 @@ mod backend;
 
 #[cfg(test)]
 mod interface;
+
+#[cfg(test)]
+mod profile;
+
+#[cfg(test)]
+mod ir;
+
+#[cfg(test)]
+mod expression;
+
+#[cfg(all(test, feature = "bitcode"))]
+mod bitcode;
+
+#[cfg(test)]
+mod synthesis;
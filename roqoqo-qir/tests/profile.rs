@@ -0,0 +1,124 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Testing the roqoqo-qir profile conformance checks
+
+use qoqo_calculator::CalculatorFloat;
+use roqoqo::{operations::*, Circuit};
+use roqoqo_qir::{prepare_circuit_for_profile, Backend, QirError, QirProfile};
+use test_case::test_case;
+
+fn conditional_circuit() -> Circuit {
+    let mut circuit = Circuit::new();
+    circuit += PauliX::new(0);
+    Circuit::from_iter([Operation::from(PragmaConditional::new(
+        "ro".to_owned(),
+        0,
+        circuit,
+    ))])
+}
+
+/// Test that operations not allowed in the Base Profile are rejected, also when nested
+#[test_case(conditional_circuit(), 0, "PragmaConditional"; "PragmaConditional")]
+#[test_case(Circuit::from_iter([Operation::from(Hadamard::new(0)), Operation::from(PragmaActiveReset::new(0))]), 1, "PragmaActiveReset"; "PragmaActiveReset")]
+#[test_case(Circuit::from_iter([Operation::from(PragmaLoop::new(CalculatorFloat::from(2.0), conditional_circuit()))]), 0, "PragmaConditional"; "PragmaLoop")]
+#[test_case(Circuit::from_iter([Operation::from(GateDefinition::new(conditional_circuit(), "gate".to_owned(), vec![0], vec![]))]), 0, "PragmaConditional"; "GateDefinition")]
+fn test_base_profile_errors(circuit: Circuit, index: usize, hqslang: &'static str) {
+    let error = || QirError::ProfileViolation {
+        index,
+        hqslang,
+        profile: "base_profile",
+    };
+    assert_eq!(
+        prepare_circuit_for_profile(&circuit, QirProfile::BaseProfile),
+        Err(error())
+    );
+    let backend = Backend::new(Some("base_profile".to_owned()), None).unwrap();
+    assert_eq!(backend.circuit_to_qir_str(&circuit, false), Err(error()));
+}
+
+/// Test that PragmaLoops are unrolled in every profile
+#[test_case(QirProfile::BaseProfile; "base")]
+#[test_case(QirProfile::AdaptiveProfile; "adaptive")]
+fn test_unroll_loops(qir_profile: QirProfile) {
+    let mut inner_loop = Circuit::new();
+    inner_loop += PauliZ::new(1);
+    let mut body = Circuit::new();
+    body += Hadamard::new(0);
+    body += PragmaLoop::new(CalculatorFloat::from(2.0), inner_loop);
+    let mut circuit = Circuit::new();
+    circuit += PragmaLoop::new(CalculatorFloat::from(2.5), body);
+    circuit += PauliX::new(0);
+
+    let mut unrolled = Circuit::new();
+    unrolled += Hadamard::new(0);
+    unrolled += PauliZ::new(1);
+    unrolled += PauliZ::new(1);
+    unrolled += Hadamard::new(0);
+    unrolled += PauliZ::new(1);
+    unrolled += PauliZ::new(1);
+    unrolled += PauliX::new(0);
+    assert_eq!(
+        prepare_circuit_for_profile(&circuit, qir_profile).unwrap(),
+        unrolled
+    );
+}
+
+/// Test that PragmaLoops with a symbolic number of repetitions can not be unrolled
+#[test_case(QirProfile::BaseProfile; "base")]
+#[test_case(QirProfile::AdaptiveProfile; "adaptive")]
+fn test_unroll_symbolic_loop_error(qir_profile: QirProfile) {
+    let circuit = Circuit::from_iter([Operation::from(PragmaLoop::new(
+        CalculatorFloat::from("repetitions"),
+        Circuit::new(),
+    ))]);
+    assert_eq!(
+        prepare_circuit_for_profile(&circuit, qir_profile),
+        Err(QirError::UnboundParameter {
+            index: 0,
+            hqslang: "PragmaLoop",
+            parameter: "repetitions".to_owned()
+        })
+    );
+}
+
+/// Test that the Adaptive Profile keeps conditional operations
+#[test]
+fn test_adaptive_profile_conditional() {
+    let circuit = conditional_circuit();
+    assert_eq!(
+        prepare_circuit_for_profile(&circuit, QirProfile::AdaptiveProfile).unwrap(),
+        circuit
+    );
+}
+
+/// Test that the Base Profile accepts symbols set by InputSymbolic operations
+#[test]
+fn test_base_profile_input_symbolic() {
+    let mut circuit = Circuit::new();
+    circuit += InputSymbolic::new("theta".to_owned(), 0.5);
+    circuit += RotateZ::new(0, CalculatorFloat::from("theta"));
+    assert_eq!(
+        prepare_circuit_for_profile(&circuit, QirProfile::BaseProfile).unwrap(),
+        circuit
+    );
+
+    circuit += RotateX::new(0, CalculatorFloat::from("phi"));
+    assert_eq!(
+        prepare_circuit_for_profile(&circuit, QirProfile::BaseProfile),
+        Err(QirError::UnboundParameter {
+            index: 2,
+            hqslang: "RotateX",
+            parameter: "phi".to_owned()
+        })
+    );
+}
@@ -0,0 +1,180 @@
+// Copyright © 2021-2024 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Testing the roqoqo-qir unitary synthesis
+
+use ndarray::{array, linalg::kron, Array2};
+use num_complex::Complex64;
+use qoqo_calculator::CalculatorFloat;
+use roqoqo::{operations::*, Circuit, RoqoqoBackendError};
+use roqoqo_qir::{synthesize_gate, synthesize_single_qubit_unitary, synthesize_two_qubit_unitary};
+use test_case::test_case;
+
+/// Returns the unitary matrix of a synthesized circuit in the basis `|control, target>`.
+fn circuit_matrix(circuit: &Circuit, dimension: usize, control: usize) -> Array2<Complex64> {
+    let mut matrix = Array2::<Complex64>::eye(dimension);
+    for operation in circuit.iter() {
+        let operation_matrix = match operation {
+            Operation::CNOT(op) => op.unitary_matrix().unwrap(),
+            _ => {
+                let gate = SingleQubitGateOperation::try_from(operation.clone()).unwrap();
+                let gate_matrix = gate.unitary_matrix().unwrap();
+                if dimension == 2 {
+                    gate_matrix
+                } else if *gate.qubit() == control {
+                    kron(&gate_matrix, &Array2::eye(2))
+                } else {
+                    kron(&Array2::eye(2), &gate_matrix)
+                }
+            }
+        };
+        matrix = operation_matrix.dot(&matrix);
+    }
+    matrix
+}
+
+/// Asserts that two unitary matrices are equal up to a global phase.
+fn assert_equal_up_to_phase(actual: &Array2<Complex64>, expected: &Array2<Complex64>) {
+    let largest = expected
+        .indexed_iter()
+        .max_by(|(_, a), (_, b)| a.norm().total_cmp(&b.norm()))
+        .unwrap()
+        .0;
+    let phase = actual[largest] / expected[largest];
+    assert!((phase.norm() - 1.0).abs() < 1e-8);
+    for (index, entry) in expected.indexed_iter() {
+        assert!(
+            (actual[index] - phase * entry).norm() < 1e-8,
+            "{actual} != {expected}"
+        );
+    }
+}
+
+/// Test that single-qubit gates are synthesized as rotations equal to their unitary matrix
+#[test_case(Operation::from(Hadamard::new(0)); "Hadamard")]
+#[test_case(Operation::from(PauliY::new(1)); "PauliY")]
+#[test_case(Operation::from(SGate::new(0)); "SGate")]
+#[test_case(Operation::from(TGate::new(2)); "TGate")]
+#[test_case(Operation::from(RotateX::new(0, CalculatorFloat::from(0.7))); "RotateX")]
+#[test_case(Operation::from(RotateXY::new(0, CalculatorFloat::from(0.3), CalculatorFloat::from(1.2))); "RotateXY")]
+#[test_case(Operation::from(RotateAroundSphericalAxis::new(0, CalculatorFloat::from(0.4), CalculatorFloat::from(1.1), CalculatorFloat::from(-0.6))); "RotateAroundSphericalAxis")]
+#[test_case(Operation::from(SingleQubitGate::new(0, CalculatorFloat::from(0.5), CalculatorFloat::from(0.5), CalculatorFloat::from(0.5), CalculatorFloat::from(-0.5), CalculatorFloat::from(0.2))); "SingleQubitGate")]
+fn test_synthesize_single_qubit_gate(operation: Operation) {
+    let circuit = synthesize_gate(&operation).unwrap();
+    assert!(circuit.len() <= 3);
+    let gate = SingleQubitGateOperation::try_from(operation).unwrap();
+    assert!(circuit
+        .iter()
+        .all(|synthesized| synthesized.involved_qubits() == gate.involved_qubits()));
+    assert_equal_up_to_phase(
+        &circuit_matrix(&circuit, 2, *gate.qubit()),
+        &gate.unitary_matrix().unwrap(),
+    );
+}
+
+/// Test that two-qubit gates are synthesized as rotations and CNOTs equal to their unitary matrix
+#[test_case(Operation::from(CNOT::new(0, 1)); "CNOT")]
+#[test_case(Operation::from(CNOT::new(2, 0)); "CNOT reversed")]
+#[test_case(Operation::from(SWAP::new(0, 1)); "SWAP")]
+#[test_case(Operation::from(ISwap::new(1, 3)); "ISwap")]
+#[test_case(Operation::from(ControlledPhaseShift::new(0, 1, CalculatorFloat::from(0.9))); "ControlledPhaseShift")]
+#[test_case(Operation::from(PhaseShiftedControlledZ::new(0, 1, CalculatorFloat::from(0.4))); "PhaseShiftedControlledZ")]
+#[test_case(Operation::from(MolmerSorensenXX::new(0, 1)); "MolmerSorensenXX")]
+#[test_case(Operation::from(GivensRotation::new(0, 1, CalculatorFloat::from(0.3), CalculatorFloat::from(1.3))); "GivensRotation")]
+#[test_case(Operation::from(PMInteraction::new(0, 1, CalculatorFloat::from(0.8))); "PMInteraction")]
+#[test_case(Operation::from(Bogoliubov::new(0, 1, CalculatorFloat::from(0.2), CalculatorFloat::from(-0.7))); "Bogoliubov")]
+#[test_case(Operation::from(Qsim::new(0, 1, CalculatorFloat::from(0.1), CalculatorFloat::from(0.2), CalculatorFloat::from(0.3))); "Qsim")]
+#[test_case(Operation::from(Fsim::new(1, 0, CalculatorFloat::from(0.5), CalculatorFloat::from(1.0), CalculatorFloat::from(-0.3))); "Fsim")]
+fn test_synthesize_two_qubit_gate(operation: Operation) {
+    let circuit = synthesize_gate(&operation).unwrap();
+    let gate = TwoQubitGateOperation::try_from(operation).unwrap();
+    assert!(
+        circuit
+            .iter()
+            .filter(|synthesized| matches!(synthesized, Operation::CNOT(_)))
+            .count()
+            <= 6
+    );
+    assert!(circuit.iter().all(|synthesized| match synthesized {
+        Operation::CNOT(cnot) => cnot.control() == gate.control() && cnot.target() == gate.target(),
+        _ => [gate.control(), gate.target()]
+            .iter()
+            .any(|qubit| synthesized.involved_qubits() == InvolvedQubits::Set([**qubit].into())),
+    }));
+    assert_equal_up_to_phase(
+        &circuit_matrix(&circuit, 4, *gate.control()),
+        &gate.unitary_matrix().unwrap(),
+    );
+}
+
+/// Test that matrices are synthesized on the given qubits
+#[test]
+fn test_synthesize_unitary() {
+    let half = Complex64::new(0.5, 0.0);
+    let i_half = Complex64::new(0.0, 0.5);
+    let matrix = array![
+        [half + i_half, half - i_half],
+        [half - i_half, half + i_half]
+    ];
+    let circuit = synthesize_single_qubit_unitary(&matrix, 4).unwrap();
+    assert!(circuit
+        .iter()
+        .all(|operation| operation.involved_qubits() == InvolvedQubits::Set([4].into())));
+    assert_equal_up_to_phase(&circuit_matrix(&circuit, 2, 4), &matrix);
+
+    let matrix = kron(&matrix, &Array2::eye(2));
+    let circuit = synthesize_two_qubit_unitary(&matrix, 3, 5).unwrap();
+    assert_eq!(
+        circuit
+            .iter()
+            .filter(|op| matches!(op, Operation::CNOT(_)))
+            .count(),
+        0
+    );
+    assert_equal_up_to_phase(&circuit_matrix(&circuit, 4, 3), &matrix);
+}
+
+/// Test that operations without a unitary matrix are not synthesized
+#[test]
+fn test_synthesize_gate_errors() {
+    assert_eq!(
+        synthesize_gate(&Operation::from(Toffoli::new(0, 1, 2))),
+        Err(RoqoqoBackendError::OperationNotInBackend {
+            backend: "QirBackend",
+            hqslang: "Toffoli"
+        })
+    );
+    assert!(synthesize_gate(&Operation::from(RotateX::new(
+        0,
+        CalculatorFloat::from("theta")
+    )))
+    .is_err());
+}
+
+/// Test that matrices which are not unitary or of the wrong dimension are rejected
+#[test]
+fn test_synthesize_unitary_errors() {
+    let one = Complex64::new(1.0, 0.0);
+    let zero = Complex64::new(0.0, 0.0);
+    assert_eq!(
+        synthesize_single_qubit_unitary(&array![[one, one], [zero, one]], 0),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Matrix is not unitary".to_owned()
+        })
+    );
+    assert_eq!(
+        synthesize_two_qubit_unitary(&Array2::eye(2), 0, 1),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Expected a 4x4 unitary matrix, got a 2x2 matrix".to_owned()
+        })
+    );
+}